@@ -0,0 +1,202 @@
+use library::Library;
+
+use serde_json::{self, Value};
+
+use std::env;
+use std::env::consts::DLL_SUFFIX;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// File marking a bundle directory as assembled by cargo-lv2.
+const MARKER: &'static str = ".cargo-lv2";
+
+/// The shared library built for a plugin crate.
+pub struct Artifact {
+    /// Name of the library target, used to name the bundle.
+    pub name: String,
+    pub path: PathBuf,
+    pub manifest_dir: PathBuf,
+}
+
+/// Builds the library of the crate at `manifest_path`.
+pub fn build(manifest_path: &Path, release: bool) -> Result<Artifact, String> {
+    let manifest_path = manifest_path.canonicalize()
+        .map_err(|e| format!("{}: {}", manifest_path.display(), e))?;
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let mut cmd = Command::new(cargo);
+    cmd.args(["build", "--lib", "--message-format=json-render-diagnostics"])
+        .arg("--manifest-path").arg(&manifest_path)
+        .stdout(Stdio::piped());
+    if release {
+        cmd.arg("--release");
+    }
+    let mut child = cmd.spawn().map_err(|e| format!("cannot run cargo: {}", e))?;
+
+    let mut artifact = None;
+    for line in BufReader::new(child.stdout.take().unwrap()).lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        let msg: Value = match serde_json::from_str(&line) {
+            Ok(msg) => msg,
+            Err(_) => continue,
+        };
+        if msg["reason"] != "compiler-artifact"
+                || msg["manifest_path"].as_str().map(Path::new) != Some(&manifest_path) {
+            continue;
+        }
+        let kinds = msg["target"]["kind"].as_array().cloned().unwrap_or_default();
+        if !kinds.iter().any(|k| k == "dylib" || k == "cdylib") {
+            continue;
+        }
+        let files = msg["filenames"].as_array().cloned().unwrap_or_default();
+        let path = files.iter().filter_map(|f| f.as_str()).find(|f| f.ends_with(DLL_SUFFIX));
+        if let Some(path) = path {
+            artifact = Some(Artifact {
+                name: msg["target"]["name"].as_str().unwrap_or_default().to_string(),
+                path: PathBuf::from(path),
+                manifest_dir: manifest_path.parent().unwrap().to_path_buf(),
+            });
+        }
+    }
+
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err(format!("build of {} failed", manifest_path.display()));
+    }
+    artifact.ok_or_else(|| {
+        format!("{}: no dylib or cdylib target was built", manifest_path.display())
+    })
+}
+
+/// Generates the manifest and the plugin descriptions from the library,
+/// or returns `None` if some plugin does not provide its metadata.
+fn generate(lib: &Library, count: usize, binary: &str, see_also: &str)
+        -> Result<Option<(String, String)>, String> {
+    let mut manifest = String::new();
+    let mut description = String::new();
+    for i in 0 .. count as u32 {
+        match (lib.manifest(i, binary, see_also)?, lib.description(i)?) {
+            (Some(m), Some(d)) => {
+                manifest.push_str(&m);
+                description.push_str(&d);
+            },
+            _ => return Ok(None),
+        }
+    }
+    Ok(Some((manifest, description)))
+}
+
+/// Removes a bundle previously assembled at `bundle`. Directories without
+/// the marker file are not ours and are left untouched.
+fn clear(bundle: &Path) -> Result<(), String> {
+    if !bundle.exists() {
+        return Ok(());
+    }
+    if !bundle.join(MARKER).is_file() {
+        return Err(format!("{}: not assembled by cargo-lv2, refusing to replace it",
+                           bundle.display()));
+    }
+    fs::remove_dir_all(bundle).map_err(|e| format!("{}: {}", bundle.display(), e))
+}
+
+/// Assembles the bundle of `artifact` in `lv2_dir` and returns its path.
+/// Turtle files are generated from the library when all its plugins provide
+/// their metadata, and copied from the crate directory otherwise.
+pub fn assemble(artifact: &Artifact, lv2_dir: &Path) -> Result<PathBuf, String> {
+    let binary = format!("{}{}", artifact.name, DLL_SUFFIX);
+    let see_also = format!("{}.ttl", artifact.name);
+
+    let lib = Library::open(&artifact.path)?;
+    let uris = lib.plugin_uris()
+        .ok_or_else(|| format!("{}: lv2_descriptor is not exported", artifact.path.display()))?;
+    if uris.is_empty() {
+        return Err(format!("{}: no plugin descriptor", artifact.path.display()));
+    }
+    let generated = generate(&lib, uris.len(), &binary, &see_also)
+        .map_err(|e| format!("{}: {}", artifact.path.display(), e))?;
+    drop(lib);
+
+    let bundle = lv2_dir.join(format!("{}.lv2", artifact.name));
+    clear(&bundle)?;
+    fs::create_dir_all(&bundle).map_err(|e| format!("{}: {}", bundle.display(), e))?;
+    fs::write(bundle.join(MARKER), "").map_err(|e| e.to_string())?;
+    fs::copy(&artifact.path, bundle.join(&binary)).map_err(|e| e.to_string())?;
+
+    match generated {
+        Some((manifest, description)) => {
+            fs::write(bundle.join("manifest.ttl"), manifest).map_err(|e| e.to_string())?;
+            fs::write(bundle.join(&see_also), description).map_err(|e| e.to_string())?;
+        },
+        None => {
+            let entries = fs::read_dir(&artifact.manifest_dir).map_err(|e| e.to_string())?;
+            for entry in entries.filter_map(|e| e.ok()) {
+                let path = entry.path();
+                if path.extension().is_some_and(|ext| ext == "ttl") {
+                    fs::copy(&path, bundle.join(path.file_name().unwrap()))
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+        },
+    }
+    Ok(bundle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use check;
+    use testing::{amp, temp_dir};
+
+    #[test]
+    fn clear_missing() {
+        let dir = temp_dir("clear_missing");
+        assert!(clear(&dir.join("none.lv2")).is_ok());
+    }
+
+    #[test]
+    fn clear_unmarked() {
+        let dir = temp_dir("clear_unmarked");
+        fs::write(dir.join("manifest.ttl"), "").unwrap();
+        assert!(clear(&dir).is_err());
+        assert!(dir.join("manifest.ttl").is_file());
+    }
+
+    #[test]
+    fn clear_marked() {
+        let dir = temp_dir("clear_marked");
+        fs::write(dir.join(MARKER), "").unwrap();
+        fs::write(dir.join("manifest.ttl"), "").unwrap();
+        clear(&dir).unwrap();
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn assemble_generated() {
+        let dir = temp_dir("assemble_generated");
+        let artifact = amp();
+        let bundle = assemble(artifact, &dir).unwrap();
+        assert_eq!(bundle, dir.join("amp.lv2"));
+        assert!(bundle.join(MARKER).is_file());
+        assert!(bundle.join(format!("amp{}", DLL_SUFFIX)).is_file());
+        let manifest = fs::read_to_string(bundle.join("manifest.ttl")).unwrap();
+        assert!(manifest.contains(&format!("<amp{}>", DLL_SUFFIX)));
+        assert!(manifest.contains("<amp.ttl>"));
+        assert!(bundle.join("amp.ttl").is_file());
+        assert_eq!(check::check_bundle(&bundle), Ok(1));
+
+        // the bundle is ours, so it is replaced
+        fs::write(bundle.join("stale.ttl"), "").unwrap();
+        assemble(artifact, &dir).unwrap();
+        assert!(!bundle.join("stale.ttl").exists());
+    }
+
+    #[test]
+    fn assemble_foreign() {
+        let dir = temp_dir("assemble_foreign");
+        fs::create_dir(dir.join("amp.lv2")).unwrap();
+        fs::write(dir.join("amp.lv2/manifest.ttl"), "").unwrap();
+        assert!(assemble(amp(), &dir).is_err());
+        assert!(dir.join("amp.lv2/manifest.ttl").is_file());
+    }
+}
@@ -0,0 +1,156 @@
+use library::Library;
+
+use lv2::core::prop;
+use lv2::turtle::Graph;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Resolves a `lv2:binary` IRI against the bundle directory.
+fn binary_path(bundle: &Path, iri: &str) -> Option<PathBuf> {
+    if let Some(path) = iri.strip_prefix("file://") {
+        Some(PathBuf::from(path))
+    }
+    else if iri.contains(':') {
+        None
+    }
+    else {
+        Some(bundle.join(iri))
+    }
+}
+
+/// Checks that every `lv2:binary` referenced by the Turtle files of `bundle`
+/// exists, exports `lv2_descriptor` and provides the plugin referring to it.
+/// Returns the number of plugins checked, or the list of problems found.
+pub fn check_bundle(bundle: &Path) -> Result<usize, Vec<String>> {
+    let mut errors = Vec::new();
+    if !bundle.join("manifest.ttl").is_file() {
+        return Err(vec![format!("{}: no manifest.ttl", bundle.display())]);
+    }
+
+    let mut graph = Graph::default();
+    let entries = fs::read_dir(bundle).map_err(|e| vec![format!("{}: {}", bundle.display(), e)])?;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.extension().map_or(true, |ext| ext != "ttl") {
+            continue;
+        }
+        match fs::read_to_string(&path).map_err(|e| e.to_string())
+                .and_then(|ttl| Graph::parse(&ttl).map_err(|e| e.to_string())) {
+            Ok(g) => graph.triples.extend(g.triples),
+            Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    let mut count = 0;
+    for triple in graph.triples.iter().filter(|t| t.predicate == prop::BINARY) {
+        let plugin = match triple.subject.as_iri() {
+            Some(uri) => uri,
+            None => continue,
+        };
+        let iri = match triple.object.as_iri() {
+            Some(iri) => iri,
+            None => {
+                errors.push(format!("<{}>: lv2:binary is not an IRI", plugin));
+                continue;
+            },
+        };
+        let path = match binary_path(bundle, iri) {
+            Some(path) => path,
+            None => {
+                errors.push(format!("<{}>: unsupported lv2:binary <{}>", plugin, iri));
+                continue;
+            },
+        };
+        if !path.is_file() {
+            errors.push(format!("<{}>: {} does not exist", plugin, path.display()));
+            continue;
+        }
+        let lib = match Library::open(&path) {
+            Ok(lib) => lib,
+            Err(e) => {
+                errors.push(format!("<{}>: cannot load {}: {}", plugin, path.display(), e));
+                continue;
+            },
+        };
+        match lib.plugin_uris() {
+            None => errors.push(format!("{}: lv2_descriptor is not exported", path.display())),
+            Some(ref uris) if !uris.iter().any(|u| u == plugin) => {
+                errors.push(format!("{}: does not provide <{}>", path.display(), plugin))
+            },
+            Some(_) => count += 1,
+        }
+    }
+
+    if count == 0 && errors.is_empty() {
+        errors.push(format!("{}: no lv2:binary referenced", bundle.display()));
+    }
+    if errors.is_empty() { Ok(count) } else { Err(errors) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing::temp_dir;
+
+    const PLUGIN: &'static str = "@prefix lv2: <http://lv2plug.in/ns/lv2core#> .\n\
+                                  <urn:test:plugin> a lv2:Plugin ; lv2:binary <%s> .\n";
+
+    fn bundle(name: &str, binary: &str) -> PathBuf {
+        let dir = temp_dir(name);
+        fs::write(dir.join("manifest.ttl"), PLUGIN.replace("%s", binary)).unwrap();
+        dir
+    }
+
+    #[test]
+    fn binary_paths() {
+        let bundle = Path::new("/lv2/test.lv2");
+        assert_eq!(binary_path(bundle, "test.so"), Some(bundle.join("test.so")));
+        assert_eq!(binary_path(bundle, "file:///lib/test.so"), Some(PathBuf::from("/lib/test.so")));
+        assert_eq!(binary_path(bundle, "http://example.org/test.so"), None);
+    }
+
+    #[test]
+    fn no_manifest() {
+        let dir = temp_dir("no_manifest");
+        assert_eq!(check_bundle(&dir).unwrap_err().len(), 1);
+    }
+
+    #[test]
+    fn no_binary() {
+        let dir = temp_dir("no_binary");
+        fs::write(dir.join("manifest.ttl"), "").unwrap();
+        let errors = check_bundle(&dir).unwrap_err();
+        assert!(errors[0].ends_with("no lv2:binary referenced"), "{:?}", errors);
+    }
+
+    #[test]
+    fn missing_binary() {
+        let dir = bundle("missing_binary", "test.so");
+        let errors = check_bundle(&dir).unwrap_err();
+        assert!(errors[0].ends_with("does not exist"), "{:?}", errors);
+    }
+
+    #[test]
+    fn unsupported_binary() {
+        let dir = bundle("unsupported_binary", "http://example.org/test.so");
+        let errors = check_bundle(&dir).unwrap_err();
+        assert!(errors[0].contains("unsupported lv2:binary"), "{:?}", errors);
+    }
+
+    #[test]
+    fn invalid_binary() {
+        let dir = bundle("invalid_binary", "test.so");
+        fs::write(dir.join("test.so"), "").unwrap();
+        let errors = check_bundle(&dir).unwrap_err();
+        assert!(errors[0].contains("cannot load"), "{:?}", errors);
+    }
+
+    #[test]
+    fn parse_error() {
+        let dir = bundle("parse_error", "test.so");
+        fs::write(dir.join("broken.ttl"), "<urn:test:plugin> a").unwrap();
+        let errors = check_bundle(&dir).unwrap_err();
+        assert!(errors.iter().any(|e| e.contains("broken.ttl")), "{:?}", errors);
+    }
+}
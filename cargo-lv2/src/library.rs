@@ -0,0 +1,148 @@
+use lv2::ffi::LV2_Descriptor;
+use lv2::metadata;
+
+use libc::{self, c_char, c_void};
+
+use std::ffi::{CStr, CString};
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr;
+
+type DescriptorFn = unsafe extern "C" fn(u32) -> *const LV2_Descriptor;
+type TurtleFn = unsafe extern "C" fn(u32, u32, *const c_char, *const c_char, *mut *mut c_char)
+                                     -> *mut c_char;
+type FreeTurtleFn = unsafe extern "C" fn(*mut c_char);
+
+/// A plugin shared library loaded in the process.
+pub struct Library {
+    handle: *mut c_void,
+}
+
+fn dlerror() -> String {
+    unsafe {
+        let err = libc::dlerror();
+        if err.is_null() {
+            "unknown error".to_string()
+        }
+        else {
+            CStr::from_ptr(err).to_string_lossy().into_owned()
+        }
+    }
+}
+
+impl Library {
+    pub fn open(path: &Path) -> Result<Library, String> {
+        let cpath = CString::new(path.as_os_str().as_bytes()).map_err(|e| e.to_string())?;
+        let handle = unsafe { libc::dlopen(cpath.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        if handle.is_null() {
+            Err(dlerror())
+        }
+        else {
+            Ok(Library { handle: handle })
+        }
+    }
+
+    fn symbol(&self, name: &str) -> Option<*mut c_void> {
+        let name = CString::new(name).unwrap();
+        let sym = unsafe { libc::dlsym(self.handle, name.as_ptr()) };
+        if sym.is_null() { None } else { Some(sym) }
+    }
+
+    /// The URIs of the plugins returned by `lv2_descriptor`, or `None` if
+    /// the library does not export it.
+    pub fn plugin_uris(&self) -> Option<Vec<String>> {
+        let descriptor: DescriptorFn = unsafe { mem::transmute(self.symbol("lv2_descriptor")?) };
+        let mut uris = Vec::new();
+        loop {
+            let desc = unsafe { descriptor(uris.len() as u32) };
+            if desc.is_null() {
+                break;
+            }
+            let uri = unsafe { CStr::from_ptr((*desc).URI) };
+            uris.push(uri.to_string_lossy().into_owned());
+        }
+        Some(uris)
+    }
+
+    /// The Turtle document of `kind` generated by the plugin at `index`, or
+    /// `None` if the plugin was not declared with its `Metadata`.
+    pub fn turtle(&self, index: u32, kind: u32, binary: &str, see_also: &str)
+            -> Result<Option<String>, String> {
+        let (turtle, free) = match (self.symbol("lv2_rs_turtle"), self.symbol("lv2_rs_free_turtle")) {
+            (Some(turtle), Some(free)) => unsafe {
+                (mem::transmute::<_, TurtleFn>(turtle), mem::transmute::<_, FreeTurtleFn>(free))
+            },
+            _ => return Ok(None),
+        };
+        let binary = CString::new(binary).map_err(|e| e.to_string())?;
+        let see_also = CString::new(see_also).map_err(|e| e.to_string())?;
+        unsafe {
+            let mut error = ptr::null_mut();
+            let ttl = turtle(index, kind, binary.as_ptr(), see_also.as_ptr(), &mut error);
+            if !error.is_null() {
+                let err = CStr::from_ptr(error).to_string_lossy().into_owned();
+                free(error);
+                return Err(err);
+            }
+            if ttl.is_null() {
+                return Ok(None);
+            }
+            let res = CStr::from_ptr(ttl).to_string_lossy().into_owned();
+            free(ttl);
+            Ok(Some(res))
+        }
+    }
+
+    pub fn manifest(&self, index: u32, binary: &str, see_also: &str)
+            -> Result<Option<String>, String> {
+        self.turtle(index, metadata::MANIFEST, binary, see_also)
+    }
+
+    pub fn description(&self, index: u32) -> Result<Option<String>, String> {
+        self.turtle(index, metadata::DESCRIPTION, "", "")
+    }
+}
+
+impl Drop for Library {
+    fn drop(&mut self) {
+        unsafe {
+            libc::dlclose(self.handle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use testing::{amp, AMP_URI};
+
+    #[test]
+    fn open_missing() {
+        assert!(Library::open(Path::new("/nonexistent/libnone.so")).is_err());
+    }
+
+    #[test]
+    fn plugin_uris() {
+        let lib = Library::open(&amp().path).unwrap();
+        assert_eq!(lib.plugin_uris(), Some(vec![AMP_URI.to_string()]));
+    }
+
+    #[test]
+    fn documents() {
+        let lib = Library::open(&amp().path).unwrap();
+        let manifest = lib.manifest(0, "libamp.so", "amp.ttl").unwrap().unwrap();
+        assert!(manifest.contains(AMP_URI));
+        assert!(manifest.contains("<libamp.so>"));
+        let description = lib.description(0).unwrap().unwrap();
+        assert!(description.contains("Rust Amplifier"));
+        assert_eq!(lib.description(1), Ok(None));
+    }
+
+    #[test]
+    fn unknown_kind() {
+        let lib = Library::open(&amp().path).unwrap();
+        let err = lib.turtle(0, 42, "", "").unwrap_err();
+        assert_eq!(err, format!("<{}>: unknown document kind 42", AMP_URI));
+    }
+}
@@ -0,0 +1,143 @@
+//! `cargo lv2`: builds plugin crates into LV2 bundles and checks bundles.
+
+extern crate libc;
+extern crate lv2;
+extern crate serde_json;
+
+mod bundle;
+mod check;
+mod library;
+#[cfg(test)]
+mod testing;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+const USAGE: &'static str = "\
+Build LV2 plugin crates into bundles, or check existing bundles.
+
+Usage:
+    cargo lv2 [options] [<crate>...]
+    cargo lv2 --check [options] [<bundle>...]
+
+Each <crate> is a crate directory or its Cargo.toml (default: current directory).
+With --check and no <bundle>, every bundle of the destination is checked.
+
+Options:
+    --release           Build in release mode
+    --lv2-path <dir>    Destination directory of the bundles
+                        (default: first entry of LV2_PATH, or ~/.lv2)
+    --check             Check bundles instead of building them
+    -h, --help          Print this help
+";
+
+struct Args {
+    release: bool,
+    check: bool,
+    lv2_path: Option<PathBuf>,
+    paths: Vec<PathBuf>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args { release: false, check: false, lv2_path: None, paths: Vec::new() };
+    let mut iter = env::args_os().skip(1).peekable();
+    // skip the subcommand name when run as `cargo lv2`
+    if iter.peek().is_some_and(|a| a == "lv2") {
+        iter.next();
+    }
+    while let Some(arg) = iter.next() {
+        match arg.to_str() {
+            Some("--release") => args.release = true,
+            Some("--check") => args.check = true,
+            Some("--lv2-path") => match iter.next() {
+                Some(dir) => args.lv2_path = Some(PathBuf::from(dir)),
+                None => return Err("--lv2-path expects a directory".to_string()),
+            },
+            Some("-h") | Some("--help") => {
+                print!("{}", USAGE);
+                process::exit(0);
+            },
+            Some(a) if a.starts_with('-') => return Err(format!("unknown option {}", a)),
+            _ => args.paths.push(PathBuf::from(arg)),
+        }
+    }
+    Ok(args)
+}
+
+/// The default bundle destination.
+fn default_lv2_path() -> Result<PathBuf, String> {
+    if let Some(path) = env::var_os("LV2_PATH") {
+        if let Some(first) = env::split_paths(&path).find(|p| !p.as_os_str().is_empty()) {
+            return Ok(first);
+        }
+    }
+    env::var_os("HOME")
+        .map(|home| Path::new(&home).join(".lv2"))
+        .ok_or_else(|| "cannot determine the LV2 directory, use --lv2-path".to_string())
+}
+
+fn report(bundle: &Path, res: Result<usize, Vec<String>>) -> bool {
+    match res {
+        Ok(count) => {
+            println!("{}: {} plugin(s) ok", bundle.display(), count);
+            true
+        },
+        Err(errors) => {
+            for e in errors {
+                eprintln!("error: {}", e);
+            }
+            false
+        },
+    }
+}
+
+fn run(args: Args) -> Result<bool, String> {
+    let lv2_path = match args.lv2_path {
+        Some(ref path) => path.clone(),
+        None => default_lv2_path()?,
+    };
+
+    if args.check {
+        let mut bundles = args.paths.clone();
+        if bundles.is_empty() {
+            let entries = fs::read_dir(&lv2_path)
+                .map_err(|e| format!("{}: {}", lv2_path.display(), e))?;
+            bundles = entries.filter_map(|e| e.ok()).map(|e| e.path())
+                .filter(|p| p.is_dir() && p.extension().is_some_and(|ext| ext == "lv2"))
+                .collect();
+            bundles.sort();
+        }
+        let mut ok = true;
+        for bundle in bundles {
+            ok &= report(&bundle, check::check_bundle(&bundle));
+        }
+        return Ok(ok);
+    }
+
+    let mut crates = args.paths.clone();
+    if crates.is_empty() {
+        crates.push(PathBuf::from("."));
+    }
+    let mut ok = true;
+    for krate in crates {
+        let manifest = if krate.is_dir() { krate.join("Cargo.toml") } else { krate };
+        let artifact = bundle::build(&manifest, args.release)?;
+        let bundle = bundle::assemble(&artifact, &lv2_path)?;
+        ok &= report(&bundle, check::check_bundle(&bundle));
+    }
+    Ok(ok)
+}
+
+fn main() {
+    let res = parse_args().and_then(run);
+    match res {
+        Ok(true) => {},
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        },
+    }
+}
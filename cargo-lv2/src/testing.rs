@@ -0,0 +1,30 @@
+//! Helpers shared by the cargo-lv2 tests.
+
+use bundle::{self, Artifact};
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::OnceLock;
+
+/// An empty temporary directory, specific to the test `name`.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("cargo-lv2-{}-{}", process::id(), name));
+    if dir.exists() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// The library of the amp example plugin, built once for all tests.
+pub fn amp() -> &'static Artifact {
+    static AMP: OnceLock<Artifact> = OnceLock::new();
+    AMP.get_or_init(|| {
+        let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("../plugins/amp/Cargo.toml");
+        bundle::build(&manifest, false).unwrap()
+    })
+}
+
+pub const AMP_URI: &'static str = "https://github.com/rtbo/lv2-rs/plugins/eg-amp";
@@ -0,0 +1,82 @@
+#![no_main]
+
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate lv2;
+
+use lv2::atom::{self, Header, Types};
+
+use std::mem;
+use std::slice;
+
+/// Arbitrary URIDs, so that fuzzed headers hit every atom type.
+fn types() -> Types {
+    Types {
+        blank: 1, bool: 2, chunk: 3, double: 4, float: 5, int: 6, literal: 7,
+        long: 8, object: 9, path: 10, property: 11, resource: 12, sequence: 13,
+        sound: 14, string: 15, tuple: 16, uri: 17, urid: 18, vector: 19,
+        frame_time: 20, beat_time: 21,
+    }
+}
+
+fn visit(atom: &Header, types: &Types, depth: usize) {
+    if depth > 32 {
+        return;
+    }
+    if let Some(seq) = atom.downcast::<atom::Sequence>(types) {
+        let _ = seq.check();
+        for ev in seq.iter() {
+            visit(ev.atom(), types, depth + 1);
+        }
+    }
+    else if let Some(obj) = atom.downcast::<atom::Object>(types) {
+        let _ = obj.check();
+        for (_, _, value) in obj.iter() {
+            visit(value, types, depth + 1);
+        }
+        let (mut a, mut b) = (None, None);
+        obj.get_raw(&mut [(1, &mut a), (2, &mut b)]);
+        let (mut c, mut d): (Option<&atom::Int>, Option<&atom::String>) = (None, None);
+        obj.get(types, &mut [(1, &mut c), (2, &mut d)]);
+    }
+    else if let Some(tuple) = atom.downcast::<atom::Tuple>(types) {
+        let _ = tuple.check();
+        for elem in tuple.iter() {
+            visit(elem, types, depth + 1);
+        }
+    }
+    else if let Some(s) = atom.downcast::<atom::String>(types) {
+        let _ = s.as_str();
+    }
+    else if let Some(lit) = atom.downcast::<atom::Literal>(types) {
+        let _ = lit.as_str();
+    }
+    else if let Some(uri) = atom.downcast::<atom::URI>(types) {
+        let _ = uri.as_str();
+    }
+    else if let Some(path) = atom.downcast::<atom::Path>(types) {
+        let _ = path.as_path();
+    }
+    else if let Some(chunk) = atom.downcast::<atom::Chunk>(types) {
+        let _ = chunk.as_bytes();
+    }
+    else if let Some(sound) = atom.downcast::<atom::Sound>(types) {
+        let _ = sound.as_slice(types);
+    }
+    else if let Some(vec) = atom.downcast::<atom::Vector>(types) {
+        let _ = vec.as_slice::<f32>(types);
+        let _ = vec.as_slice::<f64>(types);
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    // copy into a 64-bit aligned buffer, as hosts provide
+    let mut buf = vec![0u64; (data.len() + 7) / 8];
+    let bytes = unsafe {
+        slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u8, buf.len() * mem::size_of::<u64>())
+    };
+    bytes[.. data.len()].copy_from_slice(data);
+    if let Ok(atom) = atom::read::atom(&bytes[.. data.len()]) {
+        visit(atom, &types(), 0);
+    }
+});
@@ -1,10 +1,12 @@
 
+#![recursion_limit = "128"]
+
 extern crate proc_macro;
 extern crate syn;
 #[macro_use]
 extern crate quote;
 
-use proc_macro::TokenStream;
+use proc_macro::{Delimiter, Group, Literal, TokenStream, TokenTree};
 
 #[proc_macro_derive(Atom, attributes(AtomURI))]
 pub fn atom(input: TokenStream) -> TokenStream {
@@ -43,6 +45,7 @@ fn impl_atom(ast: &syn::DeriveInput) -> quote::Tokens {
     let name = &ast.ident;
     let uri = find_atom_uri(&ast.attrs)
             .expect("#[AtomURI = \"...\"] must be defined with #[derive(Atom)]");
+    let uri = syn::Ident::new(uri);
     quote! {
         impl Atom for #name {
             fn type_uri() -> &'static str {
@@ -60,3 +63,477 @@ fn impl_atom(ast: &syn::DeriveInput) -> quote::Tokens {
         }
     }
 }
+
+
+#[proc_macro_derive(PortCollection, attributes(Plugin, Lv2Crate, port))]
+pub fn port_collection(input: TokenStream) -> TokenStream {
+    let s = quote_negative_literals(input).to_string();
+    let ast = syn::parse_derive_input(&s).unwrap();
+    let gen = impl_port_collection(&ast);
+    gen.parse().unwrap()
+}
+
+// syn can't parse negative literals in attributes such as `min = -90.0`,
+// so within `#[port(...)]` attributes the `-` and literal tokens are merged
+// into a string literal: `min = "-90.0"`. Other tokens are left untouched.
+fn quote_negative_literals(input: TokenStream) -> TokenStream {
+    let mut out = Vec::new();
+    let mut iter = input.into_iter().peekable();
+    while let Some(tt) = iter.next() {
+        // field attributes are within the struct body
+        if let TokenTree::Group(ref g) = tt {
+            out.push(rebuild_group(g, quote_negative_literals(g.stream())));
+            continue;
+        }
+        let is_pound = match tt {
+            TokenTree::Punct(ref p) => p.as_char() == '#',
+            _ => false,
+        };
+        out.push(tt);
+        if !is_pound {
+            continue;
+        }
+        let attr = match iter.peek() {
+            Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Bracket => g.clone(),
+            _ => continue,
+        };
+        iter.next();
+        let mut tokens: Vec<TokenTree> = attr.stream().into_iter().collect();
+        let is_port = match tokens.first() {
+            Some(TokenTree::Ident(i)) => i.to_string() == "port",
+            _ => false,
+        };
+        if is_port && tokens.len() == 2 {
+            if let TokenTree::Group(ref args) = tokens[1].clone() {
+                tokens[1] = rebuild_group(args, quote_negative_values(args.stream()));
+            }
+        }
+        out.push(rebuild_group(&attr, tokens.into_iter().collect()));
+    }
+    out.into_iter().collect()
+}
+
+fn quote_negative_values(input: TokenStream) -> TokenStream {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    let mut out = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        if let TokenTree::Group(ref g) = tokens[i] {
+            out.push(rebuild_group(g, quote_negative_values(g.stream())));
+            i += 1;
+            continue;
+        }
+        let after_eq = match out.last() {
+            Some(TokenTree::Punct(p)) => p.as_char() == '=',
+            _ => false,
+        };
+        if after_eq && i + 1 < tokens.len() {
+            if let (TokenTree::Punct(minus), TokenTree::Literal(lit)) = (&tokens[i], &tokens[i + 1]) {
+                if minus.as_char() == '-' {
+                    let mut quoted = Literal::string(&format!("-{}", lit));
+                    quoted.set_span(lit.span());
+                    out.push(TokenTree::Literal(quoted));
+                    i += 2;
+                    continue;
+                }
+            }
+        }
+        out.push(tokens[i].clone());
+        i += 1;
+    }
+    out.into_iter().collect()
+}
+
+fn rebuild_group(group: &Group, stream: TokenStream) -> TokenTree {
+    let mut g = Group::new(group.delimiter(), stream);
+    g.set_span(group.span());
+    TokenTree::Group(g)
+}
+
+fn find_plugin_type(attrs: &Vec<syn::Attribute>) -> Option<syn::Ty> {
+    for a in attrs {
+        match a.value {
+            syn::MetaItem::NameValue(ref n, syn::Lit::Str(ref ty, _)) => {
+                if n == "Plugin" {
+                    return Some(syn::parse_type(ty).expect("invalid plugin type in #[Plugin = \"...\"]"));
+                }
+            },
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The path of the lv2 crate, `::lv2` unless given with `#[Lv2Crate = "..."]`.
+fn find_lv2_crate(attrs: &Vec<syn::Attribute>) -> syn::Ident {
+    for a in attrs {
+        match a.value {
+            syn::MetaItem::NameValue(ref n, syn::Lit::Str(ref path, _)) => {
+                if n == "Lv2Crate" {
+                    return syn::Ident::new(path.clone());
+                }
+            },
+            _ => {}
+        }
+    }
+    syn::Ident::new("::lv2")
+}
+
+#[derive(Default)]
+struct PortAttrs {
+    index: Option<u64>,
+    symbol: Option<String>,
+    name: Option<String>,
+    symbols: Vec<String>,
+    names: Vec<String>,
+    meta: Option<String>,
+    minimum: Option<f32>,
+    maximum: Option<f32>,
+    default: Option<f32>,
+    unit: Option<String>,
+    properties: Vec<quote::Tokens>,
+    designation: Option<String>,
+    supports: Vec<String>,
+    scale_points: Vec<(String, f32)>,
+}
+
+fn lit_str(lit: &syn::Lit, key: &str) -> String {
+    match *lit {
+        syn::Lit::Str(ref s, _) => s.clone(),
+        _ => panic!("#[port({} = ...)] expects a string", key),
+    }
+}
+
+fn lit_f32(lit: &syn::Lit, key: &str) -> f32 {
+    match *lit {
+        syn::Lit::Float(ref f, _) => f.parse().ok(),
+        syn::Lit::Int(i, _) => Some(i as f32),
+        syn::Lit::Str(ref s, _) => s.parse().ok(),
+        _ => None,
+    }.unwrap_or_else(|| panic!("#[port({} = ...)] expects a number", key))
+}
+
+fn lit_strs(items: &Vec<syn::NestedMetaItem>, key: &str) -> Vec<String> {
+    items.iter().map(|item| match *item {
+        syn::NestedMetaItem::Literal(ref lit) => lit_str(lit, key),
+        _ => panic!("#[port({}(...))] expects a list of strings", key),
+    }).collect()
+}
+
+fn parse_port_attrs(attrs: &Vec<syn::Attribute>, krate: &syn::Ident) -> PortAttrs {
+    let mut pa = PortAttrs::default();
+    for a in attrs {
+        let items = match a.value {
+            syn::MetaItem::List(ref n, ref items) if n == "port" => items,
+            _ => continue,
+        };
+        for item in items {
+            match *item {
+                syn::NestedMetaItem::MetaItem(syn::MetaItem::NameValue(ref key, ref lit)) => {
+                    match key.as_ref() {
+                        "index" => match *lit {
+                            syn::Lit::Int(i, _) => pa.index = Some(i),
+                            _ => panic!("#[port(index = ...)] expects an integer"),
+                        },
+                        "symbol" => pa.symbol = Some(lit_str(lit, "symbol")),
+                        "name" => pa.name = Some(lit_str(lit, "name")),
+                        "meta" => pa.meta = Some(lit_str(lit, "meta")),
+                        "unit" => {
+                            let unit = lit_str(lit, "unit");
+                            pa.unit = Some(if unit.contains(':') {
+                                unit
+                            } else {
+                                format!("http://lv2plug.in/ns/extensions/units#{}", unit)
+                            });
+                        },
+                        "property" => pa.properties.push(uri_tokens(&lit_str(lit, "property"))),
+                        "designation" => {
+                            let designation = lit_str(lit, "designation");
+                            pa.designation = Some(if designation.contains(':') {
+                                designation
+                            } else {
+                                format!("http://lv2plug.in/ns/lv2core#{}", designation)
+                            });
+                        },
+                        "supports" => pa.supports.push(lit_str(lit, "supports")),
+                        "min" => pa.minimum = Some(lit_f32(lit, "min")),
+                        "max" => pa.maximum = Some(lit_f32(lit, "max")),
+                        "default" => pa.default = Some(lit_f32(lit, "default")),
+                        k => panic!("unknown port attribute: {}", k),
+                    }
+                },
+                syn::NestedMetaItem::MetaItem(syn::MetaItem::List(ref key, ref items)) => {
+                    match key.as_ref() {
+                        "symbols" => pa.symbols = lit_strs(items, "symbols"),
+                        "names" => pa.names = lit_strs(items, "names"),
+                        "scale_point" => {
+                            let mut label = None;
+                            let mut value = None;
+                            for item in items {
+                                match *item {
+                                    syn::NestedMetaItem::MetaItem(
+                                        syn::MetaItem::NameValue(ref key, ref lit)
+                                    ) if key == "label" => label = Some(lit_str(lit, "label")),
+                                    syn::NestedMetaItem::MetaItem(
+                                        syn::MetaItem::NameValue(ref key, ref lit)
+                                    ) if key == "value" => value = Some(lit_f32(lit, "value")),
+                                    _ => panic!("#[port(scale_point(...))] expects label and value"),
+                                }
+                            }
+                            pa.scale_points.push((
+                                label.expect("scale_point without label"),
+                                value.expect("scale_point without value"),
+                            ));
+                        },
+                        k => panic!("unknown port attribute: {}", k),
+                    }
+                },
+                syn::NestedMetaItem::MetaItem(syn::MetaItem::Word(ref w)) => {
+                    let prop = match w.as_ref() {
+                        "toggled" => "TOGGLED",
+                        "integer" => "INTEGER",
+                        "enumeration" => "ENUMERATION",
+                        "sample_rate" => "SAMPLERATE",
+                        k => panic!("unknown port attribute: {}", k),
+                    };
+                    let prop = syn::Ident::new(prop);
+                    pa.properties.push(quote! { #krate::core::inst::#prop });
+                },
+                _ => panic!("invalid port attribute"),
+            }
+        }
+    }
+    pa
+}
+
+/// A URI given either as a string or as a `::path` to a constant.
+fn uri_tokens(uri: &str) -> quote::Tokens {
+    if uri.starts_with("::") {
+        let path = syn::Ident::new(uri.to_string());
+        quote! { #path }
+    }
+    else {
+        quote! { #uri }
+    }
+}
+
+fn option_f32(val: Option<f32>) -> quote::Tokens {
+    match val {
+        Some(v) => quote! { Some(#v) },
+        None => quote! { None },
+    }
+}
+
+fn impl_port_collection(ast: &syn::DeriveInput) -> quote::Tokens {
+    let name = &ast.ident;
+    let vis = &ast.vis;
+    let raw_name = syn::Ident::new(format!("{}Raw", name));
+    let plugin = find_plugin_type(&ast.attrs)
+            .expect("#[Plugin = \"...\"] must be defined with #[derive(PortCollection)]");
+    let krate = find_lv2_crate(&ast.attrs);
+    if ast.generics.lifetimes.len() != 1 || !ast.generics.ty_params.is_empty() {
+        panic!("#[derive(PortCollection)] expects a struct with a single lifetime parameter");
+    }
+    let lt = &ast.generics.lifetimes[0].lifetime;
+    let fields = match ast.body {
+        syn::Body::Struct(syn::VariantData::Struct(ref fields)) => fields,
+        _ => panic!("#[derive(PortCollection)] expects a struct with named fields"),
+    };
+
+    let mut metas: Vec<quote::Tokens> = Vec::new();
+    let mut index_consts: Vec<syn::Ident> = Vec::new();
+    let mut index_decls = Vec::new();
+    let mut raw_fields = Vec::new();
+    let mut new_raws = Vec::new();
+    let mut connects = Vec::new();
+    let mut checks = Vec::new();
+    let mut converts = Vec::new();
+    let mut silences = Vec::new();
+    let mut ranges = Vec::new();
+    let mut symbol_fields = Vec::new();
+    let mut infos = Vec::new();
+
+    let mut explicit_indices = Vec::new();
+    let mut symbols: Vec<String> = Vec::new();
+
+    for (i, field) in fields.iter().enumerate() {
+        let fname = field.ident.clone().unwrap();
+        let ty = &field.ty;
+        let pa = parse_port_attrs(&field.attrs, &krate);
+
+        let meta = match pa.meta {
+            Some(ref meta) => {
+                let meta = syn::parse_type(meta).expect("invalid type in #[port(meta = \"...\")]");
+                quote! { #meta }
+            },
+            None => quote! { <#ty as #krate::meta::PortField<#lt>>::Meta },
+        };
+
+        let index_const = syn::Ident::new(format!("__LV2_PORT_INDEX_{}", i));
+        let index = match pa.index {
+            Some(idx) => {
+                if explicit_indices.contains(&idx) {
+                    panic!("duplicate port index {}", idx);
+                }
+                explicit_indices.push(idx);
+                let idx = idx as usize;
+                quote! { #idx }
+            },
+            None if i == 0 => quote! { 0usize },
+            None => {
+                let prev_const = &index_consts[i - 1];
+                let prev_meta = &metas[i - 1];
+                quote! { Self::#prev_const + <#prev_meta as #krate::meta::Port<#lt>>::PORT_COUNT }
+            },
+        };
+        index_decls.push(quote! {
+            #[doc(hidden)]
+            pub const #index_const: usize = #index;
+        });
+
+        let symbol = pa.symbol.clone().unwrap_or_else(|| fname.as_ref().to_string());
+        let mut field_symbols = pa.symbols.clone();
+        if field_symbols.is_empty() {
+            field_symbols.push(symbol.clone());
+        }
+        for s in field_symbols {
+            if symbols.contains(&s) {
+                panic!("duplicate port symbol \"{}\"", s);
+            }
+            symbols.push(s);
+        }
+
+        let pname = pa.name.clone().unwrap_or_else(|| symbol.clone());
+        let fsymbols = &pa.symbols;
+        let fnames = &pa.names;
+        let minimum = option_f32(pa.minimum);
+        let maximum = option_f32(pa.maximum);
+        let default = option_f32(pa.default);
+        let unit = match pa.unit {
+            Some(ref unit) => quote! { Some(#unit) },
+            None => quote! { None },
+        };
+        let properties = &pa.properties;
+        let designation = match pa.designation {
+            Some(ref d) => {
+                let d = uri_tokens(d);
+                quote! { Some(#d) }
+            },
+            None => quote! { None },
+        };
+        let supports: Vec<_> = pa.supports.iter().map(|s| uri_tokens(s)).collect();
+        let sp_labels: Vec<_> = pa.scale_points.iter().map(|sp| sp.0.clone()).collect();
+        let sp_values: Vec<_> = pa.scale_points.iter().map(|sp| sp.1).collect();
+
+        infos.push(quote! {
+            infos.extend(#krate::meta::FieldInfo {
+                index: <#name<#lt>>::#index_const,
+                symbol: #symbol,
+                name: #pname,
+                symbols: &[ #(#fsymbols),* ],
+                names: &[ #(#fnames),* ],
+                minimum: #minimum,
+                maximum: #maximum,
+                default: #default,
+                unit: #unit,
+                properties: &[ #(#properties),* ],
+                designation: #designation,
+                statements: &[ #( ("http://lv2plug.in/ns/ext/atom#supports", #supports) ),* ],
+                scale_points: &[ #( (#sp_labels, #sp_values) ),* ],
+            }.port_infos::<#meta>());
+        });
+
+        raw_fields.push(quote! {
+            pub #fname: <#meta as #krate::meta::Port<#lt>>::FieldRaw
+        });
+        new_raws.push(quote! {
+            #fname: <#meta as #krate::meta::Port<#lt>>::new_raw()
+        });
+        connects.push(quote! {
+            let index = port.wrapping_sub(<#name<#lt>>::#index_const);
+            if index < <#meta as #krate::meta::Port<#lt>>::PORT_COUNT {
+                <#meta as #krate::meta::Port<#lt>>::connect(&mut ports_raw.#fname, index, data);
+                return;
+            }
+        });
+        let fname_str = fname.as_ref().to_string();
+        checks.push(quote! {
+            #krate::meta::check_connected::<#meta>(
+                ports_raw.#fname, <#name<#lt>>::#index_const, #fname_str
+            );
+        });
+        converts.push(quote! {
+            #fname: <#meta as #krate::meta::Port<#lt>>::convert(ports_raw.#fname, sample_count)
+        });
+        silences.push(quote! {
+            <#meta as #krate::meta::Port<#lt>>::silence(ports_raw.#fname, sample_count);
+        });
+        ranges.push(quote! {
+            (Self::#index_const, <#meta as #krate::meta::Port<#lt>>::PORT_COUNT)
+        });
+        symbol_fields.push(quote! {
+            (#symbol, &[ #(#fsymbols),* ], <#meta as #krate::meta::Port<#lt>>::PORT_COUNT)
+        });
+
+        metas.push(meta);
+        index_consts.push(index_const);
+    }
+
+    quote! {
+        #[derive(Copy, Clone)]
+        #vis struct #raw_name<#lt> {
+            #( #raw_fields ),*
+        }
+
+        impl<#lt> #name<#lt> {
+            #( #index_decls )*
+
+            #[doc(hidden)]
+            pub const __LV2_PORT_CHECK: () = #krate::meta::check_port_indices(&[
+                #( #ranges ),*
+            ]);
+
+            // also covers the `{symbol}_{i}` names of multi-port fields,
+            // which depend on the port count known only to the compiler
+            #[doc(hidden)]
+            pub const __LV2_SYMBOL_CHECK: () = #krate::meta::check_port_symbols(&[
+                #( #symbol_fields ),*
+            ]);
+        }
+
+        unsafe impl<#lt> #krate::Ported<#lt> for #plugin {
+            type PortsRaw = #raw_name<#lt>;
+            type Ports = #name<#lt>;
+
+            fn new_ports_raw() -> Self::PortsRaw {
+                let () = <#name<#lt>>::__LV2_PORT_CHECK;
+                let () = <#name<#lt>>::__LV2_SYMBOL_CHECK;
+                #raw_name {
+                    #( #new_raws ),*
+                }
+            }
+
+            fn connect_port(port: usize, data: *mut (), ports_raw: &mut Self::PortsRaw) {
+                #( #connects )*
+            }
+
+            fn convert_ports(ports_raw: Self::PortsRaw, sample_count: usize) -> Self::Ports {
+                #( #checks )*
+                #name {
+                    #( #converts ),*
+                }
+            }
+
+            fn silence_outputs(ports_raw: Self::PortsRaw, sample_count: usize) {
+                #( #silences )*
+            }
+
+            fn port_infos() -> Vec<#krate::PortInfo> {
+                let mut infos = Vec::new();
+                #( #infos )*
+                infos.sort_by_key(|info| info.index);
+                infos
+            }
+        }
+    }
+}
@@ -2,6 +2,8 @@
 #[macro_use]
 extern crate lv2;
 
+use lv2::PortCollection;
+
 use std::f32;
 
 fn db_to_coef(db: f32) -> f32 {
@@ -12,31 +14,55 @@ fn db_to_coef(db: f32) -> f32 {
     }
 }
 
-mod ports {
-    use lv2;
-
-    lv2_ports!(super::Amp => {
-        0 => gain: lv2::meta::InputControl,
-        1 => input: lv2::meta::InputAudio,
-        2 => output: lv2::meta::OutputAudio
-    });
+#[derive(PortCollection)]
+#[Plugin = "Amp"]
+struct Ports<'h> {
+    #[port(name = "Gain", min = -90.0, max = 24.0, default = 0.0, unit = "db",
+           scale_point(label = "+5", value = 5.0),
+           scale_point(label = "0", value = 0.0),
+           scale_point(label = "-5", value = -5.0),
+           scale_point(label = "-10", value = -10.0))]
+    gain: &'h f32,
+    #[port(symbols("in", "out"), names("In", "Out"))]
+    io: lv2::InPlace<'h>,
 }
 
 struct Amp {}
 
 impl<'h> lv2::Plugin<'h> for Amp {
-    fn new(_sample_rate: f64, _bundle_path: &str, _features: lv2::FeatureList<'h>) -> Option<Self> {
+    type Features = ();
+
+    fn new(_sample_rate: f64, _bundle_path: &str, _features: ()) -> Option<Self> {
         Some(Amp {})
     }
 
-    fn run(&mut self, ports: &mut Self::Ports, sample_count: usize) {
+    fn run(&mut self, ports: &mut Self::Ports, _sample_count: usize) {
         let gain = db_to_coef(*ports.gain);
-        for sample in 0..sample_count {
-            ports.output[sample] = ports.input[sample] * gain;
-        }
+        ports.io.process(|s| s * gain);
+    }
+}
+
+impl lv2::Metadata for Amp {
+    fn uri() -> &'static str {
+        "https://github.com/rtbo/lv2-rs/plugins/eg-amp"
+    }
+    fn name() -> &'static str {
+        "Rust Amplifier"
+    }
+    fn classes() -> &'static [&'static str] {
+        &[lv2::class::AMPLIFIERPLUGIN]
+    }
+    fn license() -> Option<&'static str> {
+        Some("http://opensource.org/licenses/isc")
+    }
+    fn project() -> Option<&'static str> {
+        Some("http://lv2plug.in/ns/lv2")
+    }
+    fn optional_features() -> &'static [&'static str] {
+        &[lv2::inst::HARDRTCAPABLE]
     }
 }
 
 lv2_descriptor! {
-    0 => DESCRIPTOR { "https://github.com/rtbo/lv2-rs/plugins/eg-amp" => Amp }
+    0 => DESCRIPTOR { Amp }
 }
@@ -2,9 +2,11 @@
 #[macro_use]
 extern crate lv2;
 
+use lv2::atom;
 use lv2::log;
 use lv2::midi;
 use lv2::urid::{self, URID};
+use lv2::PortCollection;
 
 use std::f64;
 
@@ -115,46 +117,43 @@ struct Synth {
     pulses: [f64; 128],
 }
 
-mod ports {
-    use lv2;
-    use lv2::atom;
+#[derive(PortCollection)]
+#[Plugin = "Synth"]
+struct Ports<'h> {
+    #[port(symbol = "control", name = "Control", designation = "control",
+           supports = "::lv2::midi::class::MIDIEVENT")]
+    input: &'h atom::Sequence,
+    #[port(symbol = "out", name = "Out")]
+    output: &'h mut [f32],
+}
 
-    lv2_ports!(super::Synth => {
-        0 => input: atom::meta::InputSequence,
-        1 => output: lv2::meta::OutputAudio
-    });
+lv2_features! {
+    pub struct Features<'h> {
+        required map: urid::Map<'h>,
+        optional log: log::Log<'h>,
+    }
 }
 
 impl<'h> lv2::Plugin<'h> for Synth {
 
-    fn new(sample_rate: f64, _bundle_path: &str, features: lv2::FeatureList<'h>) -> Option<Self> {
+    type Features = Features<'h>;
 
-        lv2_features_query!(features, (log <= log::Log), (map <= urid::Map));
+    fn new(sample_rate: f64, _bundle_path: &str, features: Features<'h>) -> Option<Self> {
 
-        match map {
-            Some(ref map) => {
-                if let Some(ref log) = log {
-                    let logger = log::Logger::new_with_map(log, map);
-                    lv2_log!(@note, logger, "will instantiate Sine Synth");
-                }
-                let mut synth = Synth {
-                    sample_rate: sample_rate,
-                    midi_event: map.map(midi::class::MIDIEVENT),
-                    voices: [None; 32],
-                    pulses: [0_f64; 128],
-                };
-                for key in 0..128 {
-                    synth.pulses[key] = key_pulse(key as u8, sample_rate);
-                }
-                Some(synth)
-            },
-            None => {
-                if let Some(ref log) = log {
-                    log.println(0, format!("can't instanciate Sine Synth"));
-                }
-                None
-            }
+        if let Some(ref log) = features.log {
+            let logger = log::Logger::new_with_map(log, &features.map);
+            lv2_log!(@note, logger, "will instantiate Sine Synth");
+        }
+        let mut synth = Synth {
+            sample_rate: sample_rate,
+            midi_event: features.map.map(midi::class::MIDIEVENT),
+            voices: [None; 32],
+            pulses: [0_f64; 128],
+        };
+        for key in 0..128 {
+            synth.pulses[key] = key_pulse(key as u8, sample_rate);
         }
+        Some(synth)
     }
 
     fn run(&mut self, ports: &mut Self::Ports, sample_count: usize) {
@@ -205,7 +204,7 @@ impl Synth {
         }
     }
 
-    fn run_voices(&mut self, ports: &mut <Synth as lv2::Ported>::Ports, start: usize, end:usize) {
+    fn run_voices(&mut self, ports: &mut Ports, start: usize, end:usize) {
         for s in start .. end {
             ports.output[s] = 0_f32;
         }
@@ -222,6 +221,24 @@ impl Synth {
 }
 
 
+impl lv2::Metadata for Synth {
+    fn uri() -> &'static str {
+        "https://github.com/rtbo/lv2-rs/plugins/eg-synth"
+    }
+    fn name() -> &'static str {
+        "Rust Sine Synth"
+    }
+    fn classes() -> &'static [&'static str] {
+        &[lv2::class::INSTRUMENTPLUGIN]
+    }
+    fn license() -> Option<&'static str> {
+        Some("http://opensource.org/licenses/isc")
+    }
+    fn project() -> Option<&'static str> {
+        Some("http://github.com/rtbo/lv2-rs")
+    }
+}
+
 lv2_descriptor! {
-    0 => DESCRIPTOR { "https://github.com/rtbo/lv2-rs/plugins/eg-synth" => Synth }
+    0 => DESCRIPTOR { Synth }
 }
@@ -0,0 +1,464 @@
+use ::atom::types::{Scalar, Types};
+use ::atom::util::pad_size;
+use urid::URID;
+
+use std::error;
+use std::fmt;
+use std::mem;
+use std::path::Path;
+use std::slice;
+
+/// Error returned when writing atoms.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The atom does not fit in the remaining space of the buffer.
+    /// Nothing was written.
+    Overflow,
+    /// A sequence event is timed before the previous one.
+    NonMonotonicTime,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Overflow => f.write_str("atom buffer overflow"),
+            Error::NonMonotonicTime => f.write_str("sequence event times are not monotonic"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// An open container atom (tuple, object or sequence), closed with `Forge::pop`.
+#[must_use = "frames must be closed with Forge::pop"]
+#[derive(Debug)]
+pub struct Frame {
+    header: usize,
+    depth: usize,
+}
+
+/// Writes atoms into a byte buffer, like `LV2_Atom_Forge`.
+/// Every atom is padded to 64 bits. The buffer should be 64-bit aligned
+/// for the atoms to be readable in place.
+pub struct Forge<'a> {
+    buf: &'a mut [u8],
+    offset: usize,
+    depth: usize,
+    types: Types,
+}
+
+impl<'a> Forge<'a> {
+    pub fn new(buf: &'a mut [u8], types: &Types) -> Forge<'a> {
+        Forge {
+            buf: buf,
+            offset: 0,
+            depth: 0,
+            types: *types,
+        }
+    }
+
+    pub fn types(&self) -> &Types {
+        &self.types
+    }
+
+    /// Number of bytes written.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.offset
+    }
+
+    /// The bytes written so far.
+    pub fn written(&self) -> &[u8] {
+        &self.buf[.. self.offset]
+    }
+
+    /// Writes the given chunks and pads the output to 64 bits.
+    fn write(&mut self, chunks: &[&[u8]]) -> Result<usize, Error> {
+        let start = self.offset;
+        let len: usize = chunks.iter().map(|c| c.len()).sum();
+        let end = pad_size(start + len);
+        if end > self.buf.len() {
+            return Err(Error::Overflow);
+        }
+        let mut pos = start;
+        for chunk in chunks {
+            self.buf[pos .. pos + chunk.len()].copy_from_slice(chunk);
+            pos += chunk.len();
+        }
+        for b in &mut self.buf[pos .. end] {
+            *b = 0;
+        }
+        self.offset = end;
+        Ok(start)
+    }
+
+    fn header(size: usize, type_urid: URID) -> [u8; 8] {
+        let mut h = [0u8; 8];
+        h[.. 4].copy_from_slice(&(size as u32).to_ne_bytes());
+        h[4 ..].copy_from_slice(&type_urid.to_ne_bytes());
+        h
+    }
+
+    /// Writes an atom of type `type_urid` with the given body.
+    pub fn atom(&mut self, type_urid: URID, body: &[u8]) -> Result<(), Error> {
+        let header = Forge::header(body.len(), type_urid);
+        self.write(&[&header, body]).map(|_| ())
+    }
+
+    pub fn int(&mut self, value: i32) -> Result<(), Error> {
+        let t = self.types.int;
+        self.atom(t, &value.to_ne_bytes())
+    }
+
+    pub fn long(&mut self, value: i64) -> Result<(), Error> {
+        let t = self.types.long;
+        self.atom(t, &value.to_ne_bytes())
+    }
+
+    pub fn float(&mut self, value: f32) -> Result<(), Error> {
+        let t = self.types.float;
+        self.atom(t, &value.to_ne_bytes())
+    }
+
+    pub fn double(&mut self, value: f64) -> Result<(), Error> {
+        let t = self.types.double;
+        self.atom(t, &value.to_ne_bytes())
+    }
+
+    pub fn bool(&mut self, value: bool) -> Result<(), Error> {
+        let t = self.types.bool;
+        self.atom(t, &(value as i32).to_ne_bytes())
+    }
+
+    pub fn urid(&mut self, value: URID) -> Result<(), Error> {
+        let t = self.types.urid;
+        self.atom(t, &value.to_ne_bytes())
+    }
+
+    /// Writes a NUL terminated string atom of type `type_urid`.
+    fn string_atom(&mut self, type_urid: URID, value: &str) -> Result<(), Error> {
+        let header = Forge::header(value.len() + 1, type_urid);
+        self.write(&[&header, value.as_bytes(), &[0]]).map(|_| ())
+    }
+
+    pub fn string(&mut self, value: &str) -> Result<(), Error> {
+        let t = self.types.string;
+        self.string_atom(t, value)
+    }
+
+    pub fn uri(&mut self, value: &str) -> Result<(), Error> {
+        let t = self.types.uri;
+        self.string_atom(t, value)
+    }
+
+    /// Writes a path atom. Characters which are not valid UTF-8 are replaced.
+    pub fn path(&mut self, value: &Path) -> Result<(), Error> {
+        let t = self.types.path;
+        self.string_atom(t, &value.to_string_lossy())
+    }
+
+    /// Writes a chunk of raw bytes.
+    pub fn chunk(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let t = self.types.chunk;
+        self.atom(t, bytes)
+    }
+
+    /// Writes a literal, with `datatype` and `lang` set to 0 when unused.
+    pub fn literal(&mut self, value: &str, datatype: URID, lang: URID) -> Result<(), Error> {
+        let header = Forge::header(8 + value.len() + 1, self.types.literal);
+        self.write(&[&header, &datatype.to_ne_bytes(), &lang.to_ne_bytes(),
+                     value.as_bytes(), &[0]]).map(|_| ())
+    }
+
+    /// Writes a vector of scalars.
+    pub fn vector<T: Scalar>(&mut self, items: &[T]) -> Result<(), Error> {
+        let t = self.types.vector;
+        self.vector_atom(t, items)
+    }
+
+    /// Writes a sound, a vector of audio samples.
+    pub fn sound(&mut self, samples: &[f32]) -> Result<(), Error> {
+        let t = self.types.sound;
+        self.vector_atom(t, samples)
+    }
+
+    fn vector_atom<T: Scalar>(&mut self, type_urid: URID, items: &[T]) -> Result<(), Error> {
+        let child_size = mem::size_of::<T>();
+        let bytes = unsafe {
+            slice::from_raw_parts(items.as_ptr() as *const u8, mem::size_of_val(items))
+        };
+        let header = Forge::header(8 + bytes.len(), type_urid);
+        let child_type = T::child_type(&self.types);
+        self.write(&[&header, &(child_size as u32).to_ne_bytes(), &child_type.to_ne_bytes(),
+                     bytes]).map(|_| ())
+    }
+
+    fn push(&mut self, type_urid: URID, body: &[u8]) -> Result<Frame, Error> {
+        let header = Forge::header(body.len(), type_urid);
+        let offset = self.write(&[&header, body])?;
+        self.depth += 1;
+        Ok(Frame { header: offset, depth: self.depth })
+    }
+
+    /// Opens a tuple. Its elements are the atoms written until the frame is popped.
+    pub fn tuple(&mut self) -> Result<Frame, Error> {
+        let t = self.types.tuple;
+        self.push(t, &[])
+    }
+
+    /// Opens an object. Its properties are written with `key` or `property`
+    /// followed by the value atom.
+    pub fn object(&mut self, id: URID, otype: URID) -> Result<Frame, Error> {
+        let t = self.types.object;
+        let mut body = [0u8; 8];
+        body[.. 4].copy_from_slice(&id.to_ne_bytes());
+        body[4 ..].copy_from_slice(&otype.to_ne_bytes());
+        self.push(t, &body)
+    }
+
+    /// Writes the head of an object property, to be followed by its value.
+    pub fn key(&mut self, key: URID) -> Result<(), Error> {
+        self.property(key, 0)
+    }
+
+    pub fn property(&mut self, key: URID, context: URID) -> Result<(), Error> {
+        self.write(&[&key.to_ne_bytes(), &context.to_ne_bytes()]).map(|_| ())
+    }
+
+    /// Opens a sequence of events timed in `unit`, `atom:frameTime` if 0.
+    /// Events are written with `frame_time` or `beat_time` followed by the event atom.
+    pub fn sequence(&mut self, unit: URID) -> Result<Frame, Error> {
+        let t = self.types.sequence;
+        let mut body = [0u8; 8];
+        body[.. 4].copy_from_slice(&unit.to_ne_bytes());
+        self.push(t, &body)
+    }
+
+    /// Writes the time stamp of a sequence event, to be followed by the event atom.
+    pub fn frame_time(&mut self, frames: i64) -> Result<(), Error> {
+        self.write(&[&frames.to_ne_bytes()]).map(|_| ())
+    }
+
+    pub fn beat_time(&mut self, beats: f64) -> Result<(), Error> {
+        self.write(&[&beats.to_ne_bytes()]).map(|_| ())
+    }
+
+    /// The current position, to which the forge can be rewound.
+    pub(crate) fn mark(&self) -> (usize, usize) {
+        (self.offset, self.depth)
+    }
+
+    /// Discards what was written after `mark`, including open frames.
+    pub(crate) fn rewind(&mut self, mark: (usize, usize)) {
+        debug_assert!(mark.0 <= self.offset && mark.1 <= self.depth);
+        self.offset = mark.0;
+        self.depth = mark.1;
+    }
+
+    /// Sets the size of the atom of `frame` to what was written so far.
+    pub(crate) fn update_size(&mut self, frame: &Frame) {
+        let size = (self.offset - frame.header - 8) as u32;
+        self.buf[frame.header .. frame.header + 4].copy_from_slice(&size.to_ne_bytes());
+    }
+
+    /// Closes `frame`, which must be the innermost open frame,
+    /// and sets the size of its atom.
+    pub fn pop(&mut self, frame: Frame) {
+        assert_eq!(frame.depth, self.depth, "atom forge frames popped out of order");
+        self.depth -= 1;
+        self.update_size(&frame);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::atom::testing::Buf;
+
+    fn word(bytes: &[u8], offset: usize) -> u32 {
+        let mut w = [0u8; 4];
+        w.copy_from_slice(&bytes[offset .. offset + 4]);
+        u32::from_ne_bytes(w)
+    }
+
+    #[test]
+    fn scalars() {
+        let types = Types::test();
+        let mut buf = Buf::filled(0xff);
+        let mut forge = Forge::new(&mut buf.0[.. 256], &types);
+        forge.int(-3).unwrap();
+        assert_eq!(forge.offset(), 16);
+        forge.long(1 << 40).unwrap();
+        forge.float(0.5).unwrap();
+        forge.double(2.5).unwrap();
+        forge.bool(true).unwrap();
+        forge.urid(42).unwrap();
+        assert_eq!(forge.offset(), 6 * 16);
+
+        let out = forge.written();
+        let expected = [(4, types.int), (8, types.long), (4, types.float),
+                        (8, types.double), (4, types.bool), (4, types.urid)];
+        for (i, &(size, type_urid)) in expected.iter().enumerate() {
+            let at = i * 16;
+            assert_eq!(word(out, at), size);
+            assert_eq!(word(out, at + 4), type_urid);
+            assert!(out[at + 8 + size as usize .. at + 16].iter().all(|&b| b == 0));
+        }
+        assert_eq!(word(out, 8) as i32, -3);
+        assert_eq!(word(out, 88), 42);
+    }
+
+    #[test]
+    fn strings() {
+        let types = Types::test();
+        let mut buf = Buf::filled(0xff);
+        let mut forge = Forge::new(&mut buf.0[.. 256], &types);
+        forge.string("ab").unwrap();
+        forge.uri("urn:a:b").unwrap();
+        forge.path(Path::new("/tmp")).unwrap();
+        forge.literal("hi", 7, 0).unwrap();
+
+        let out = forge.written();
+        // "ab\0" padded to 8
+        assert_eq!(word(out, 0), 3);
+        assert_eq!(word(out, 4), types.string);
+        assert_eq!(&out[8 .. 16], b"ab\0\0\0\0\0\0");
+        // "urn:a:b\0" fills the body exactly
+        assert_eq!(word(out, 16), 8);
+        assert_eq!(word(out, 20), types.uri);
+        assert_eq!(&out[24 .. 32], b"urn:a:b\0");
+        assert_eq!(word(out, 32), 5);
+        assert_eq!(word(out, 36), types.path);
+        assert_eq!(&out[40 .. 48], b"/tmp\0\0\0\0");
+        // datatype, lang, then the string
+        assert_eq!(word(out, 48), 8 + 3);
+        assert_eq!(word(out, 52), types.literal);
+        assert_eq!(word(out, 56), 7);
+        assert_eq!(word(out, 60), 0);
+        assert_eq!(&out[64 .. 72], b"hi\0\0\0\0\0\0");
+        assert_eq!(out.len(), 72);
+    }
+    #[test]
+    fn chunks_and_vectors() {
+        let types = Types::test();
+        let mut buf = Buf::filled(0xff);
+        let mut forge = Forge::new(&mut buf.0[.. 256], &types);
+        forge.chunk(&[1, 2, 3]).unwrap();
+        forge.vector(&[1i32, 2, 3]).unwrap();
+        forge.sound(&[0.25f32]).unwrap();
+
+        let out = forge.written();
+        assert_eq!(word(out, 0), 3);
+        assert_eq!(word(out, 4), types.chunk);
+        assert_eq!(&out[8 .. 16], &[1, 2, 3, 0, 0, 0, 0, 0]);
+        // child size and type, then 12 bytes of items padded to 16
+        assert_eq!(word(out, 16), 8 + 12);
+        assert_eq!(word(out, 20), types.vector);
+        assert_eq!(word(out, 24), 4);
+        assert_eq!(word(out, 28), types.int);
+        assert_eq!(word(out, 32), 1);
+        assert_eq!(word(out, 40), 3);
+        assert_eq!(word(out, 44), 0);
+        assert_eq!(word(out, 48), 8 + 4);
+        assert_eq!(word(out, 52), types.sound);
+        assert_eq!(word(out, 56), 4);
+        assert_eq!(word(out, 60), types.float);
+        assert_eq!(word(out, 64), 0.25f32.to_bits());
+        assert_eq!(out.len(), 72);
+    }
+
+    #[test]
+    fn containers() {
+        let types = Types::test();
+        let mut buf = Buf::filled(0xff);
+        let mut forge = Forge::new(&mut buf.0[.. 256], &types);
+        let tuple = forge.tuple().unwrap();
+        forge.int(1).unwrap();
+        let object = forge.object(0, 30).unwrap();
+        forge.key(31).unwrap();
+        forge.string("x").unwrap();
+        forge.pop(object);
+        let seq = forge.sequence(0).unwrap();
+        forge.frame_time(5).unwrap();
+        forge.bool(false).unwrap();
+        forge.pop(seq);
+        forge.pop(tuple);
+
+        let out = forge.written();
+        assert_eq!(out.len(), 8 + 16 + 40 + 40);
+        assert_eq!(word(out, 0), 16 + 40 + 40);
+        assert_eq!(word(out, 4), types.tuple);
+        // object: id and type, key and context, then the value
+        assert_eq!(word(out, 24), 8 + 8 + 16);
+        assert_eq!(word(out, 28), types.object);
+        assert_eq!(word(out, 36), 30);
+        assert_eq!(word(out, 40), 31);
+        // sequence: unit and padding, time stamp, then the event
+        assert_eq!(word(out, 64), 8 + 8 + 16);
+        assert_eq!(word(out, 68), types.sequence);
+        assert_eq!(word(out, 80), 5);
+    }
+
+    #[test]
+    fn overflow() {
+        let types = Types::test();
+        let mut buf = Buf::filled(0xff);
+        let mut forge = Forge::new(&mut buf.0[.. 24], &types);
+        forge.int(1).unwrap();
+        assert_eq!(forge.int(2), Err(Error::Overflow));
+        assert_eq!(forge.string("1234567"), Err(Error::Overflow));
+        assert_eq!(forge.offset(), 16);
+        assert_eq!(forge.remaining(), 8);
+        assert!(forge.object(0, 1).is_err());
+        forge.key(3).unwrap();
+        assert_eq!(forge.remaining(), 0);
+        assert_eq!(forge.frame_time(0), Err(Error::Overflow));
+    }
+
+    #[test]
+    fn failed_push_keeps_depth() {
+        let types = Types::test();
+        let mut buf = Buf::filled(0xff);
+        let mut forge = Forge::new(&mut buf.0[.. 16], &types);
+        let outer = forge.tuple().unwrap();
+        assert!(forge.object(0, 1).is_err());
+        forge.pop(outer);
+        assert_eq!(word(forge.written(), 0), 0);
+    }
+
+    #[test]
+    fn rewind() {
+        let types = Types::test();
+        let mut buf = Buf::filled(0xff);
+        let mut forge = Forge::new(&mut buf.0[.. 256], &types);
+        let outer = forge.tuple().unwrap();
+        let mark = forge.mark();
+        let _inner = forge.tuple().unwrap();
+        forge.long(7).unwrap();
+        forge.rewind(mark);
+        assert_eq!(forge.mark(), (8, 1));
+        forge.int(9).unwrap();
+        forge.pop(outer);
+
+        let out = forge.written();
+        assert_eq!(out.len(), 24);
+        assert_eq!(word(out, 0), 16);
+        assert_eq!(word(out, 12), types.int);
+        assert_eq!(word(out, 16), 9);
+    }
+
+    #[test]
+    #[should_panic(expected = "popped out of order")]
+    fn pop_out_of_order() {
+        let types = Types::test();
+        let mut buf = Buf::filled(0xff);
+        let mut forge = Forge::new(&mut buf.0[.. 256], &types);
+        let outer = forge.tuple().unwrap();
+        let inner = forge.tuple().unwrap();
+        forge.pop(outer);
+        forge.pop(inner);
+    }
+}
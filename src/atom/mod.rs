@@ -1,55 +1,27 @@
 
 use urid;
 
+use std::marker::PhantomData;
 use std::mem;
+use std::path;
 use std::slice;
-use std::str;
-use std::ffi::{CStr};
 
+mod types;
+mod uris;
 mod util;
-
-pub const URI: &'static str = "http://lv2plug.in/ns/ext/atom";
-
-pub mod class {
-    pub const ATOM: &'static str = "http://lv2plug.in/ns/ext/atom#Atom";
-    pub const ATOMPORT: &'static str = "http://lv2plug.in/ns/ext/atom#AtomPort";
-    pub const BLANK: &'static str = "http://lv2plug.in/ns/ext/atom#Blank";
-    pub const BOOL: &'static str = "http://lv2plug.in/ns/ext/atom#Bool";
-    pub const CHUNK: &'static str = "http://lv2plug.in/ns/ext/atom#Chunk";
-    pub const DOUBLE: &'static str = "http://lv2plug.in/ns/ext/atom#Double";
-    pub const EVENT: &'static str = "http://lv2plug.in/ns/ext/atom#Event";
-    pub const FLOAT: &'static str = "http://lv2plug.in/ns/ext/atom#Float";
-    pub const INT: &'static str = "http://lv2plug.in/ns/ext/atom#Int";
-    pub const LITERAL: &'static str = "http://lv2plug.in/ns/ext/atom#Literal";
-    pub const LONG: &'static str = "http://lv2plug.in/ns/ext/atom#Long";
-    pub const NUMBER: &'static str = "http://lv2plug.in/ns/ext/atom#Number";
-    pub const OBJECT: &'static str = "http://lv2plug.in/ns/ext/atom#Object";
-    pub const PATH: &'static str = "http://lv2plug.in/ns/ext/atom#Path";
-    pub const PROPERTY: &'static str = "http://lv2plug.in/ns/ext/atom#Property";
-    pub const RESOURCE: &'static str = "http://lv2plug.in/ns/ext/atom#Resource";
-    pub const SEQUENCE: &'static str = "http://lv2plug.in/ns/ext/atom#Sequence";
-    pub const SOUND: &'static str = "http://lv2plug.in/ns/ext/atom#Sound";
-    pub const STRING: &'static str = "http://lv2plug.in/ns/ext/atom#String";
-    pub const TUPLE: &'static str = "http://lv2plug.in/ns/ext/atom#Tuple";
-    pub const URI: &'static str = "http://lv2plug.in/ns/ext/atom#URI";
-    pub const URID: &'static str = "http://lv2plug.in/ns/ext/atom#URID";
-    pub const VECTOR: &'static str = "http://lv2plug.in/ns/ext/atom#Vector";
-}
-
-pub mod prop {
-    pub const BEATTIME: &'static str = "http://lv2plug.in/ns/ext/atom#beatTime";
-    pub const BUFFERTYPE: &'static str = "http://lv2plug.in/ns/ext/atom#bufferType";
-    pub const CHILDTYPE: &'static str = "http://lv2plug.in/ns/ext/atom#childType";
-    pub const FRAMETIME: &'static str = "http://lv2plug.in/ns/ext/atom#frameTime";
-    pub const SUPPORTS: &'static str = "http://lv2plug.in/ns/ext/atom#supports";
-    pub const TIMEUNIT: &'static str = "http://lv2plug.in/ns/ext/atom#timeUnit";
-}
-
-pub mod inst {
-    pub const ATOMTRANSFER: &'static str = "http://lv2plug.in/ns/ext/atom#atomTransfer";
-    pub const EVENTTRANSFER: &'static str = "http://lv2plug.in/ns/ext/atom#eventTransfer";
-}
-
+mod output;
+pub mod forge;
+pub mod read;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod turtle;
+#[cfg(test)]
+pub(crate) mod testing;
+
+pub use self::forge::{Forge, Frame};
+pub use self::output::{SequenceOutput, SequenceWriter};
+pub use self::types::{AtomType, Scalar, Types};
+pub use self::uris::{class, inst, prop, URI};
 
 pub unsafe fn contents<'a, T: Atom>(atom: &'a T) -> &'a [u8] {
     let ptr = atom as *const T as *const u8;
@@ -87,6 +59,7 @@ where
     unsafe fn contents(&self) -> &T;
 }
 
+#[repr(C)]
 #[derive(Copy, Clone)]
 pub struct Header {
     size: u32,
@@ -108,8 +81,19 @@ impl Header {
         let ptr = self as *mut Header;
         slice::from_raw_parts_mut(ptr.offset(1) as *mut u8, self.size())
     }
+    /// The atom as `T`, if it has the type of `T` and a body large enough.
+    pub fn downcast<T: AtomType>(&self, types: &Types) -> Option<&T> {
+        let body_size = mem::size_of::<T>() - mem::size_of::<Header>();
+        if T::is_type(self.type_urid, types) && self.size() >= body_size {
+            Some(unsafe { &*(self as *const Header as *const T) })
+        }
+        else {
+            None
+        }
+    }
 }
 
+#[repr(C)]
 #[derive(Copy, Clone, Atom)]
 #[AtomURI = "class::INT"]
 pub struct Int {
@@ -125,6 +109,7 @@ impl Value<i32> for Int {
 
 
 
+#[repr(C)]
 #[derive(Copy, Clone, Atom)]
 #[AtomURI = "class::LONG"]
 pub struct Long {
@@ -138,6 +123,7 @@ impl Value<i64> for Long {
     }
 }
 
+#[repr(C)]
 #[derive(Copy, Clone, Atom)]
 #[AtomURI = "class::FLOAT"]
 pub struct Float {
@@ -151,6 +137,7 @@ impl Value<f32> for Float {
     }
 }
 
+#[repr(C)]
 #[derive(Copy, Clone, Atom)]
 #[AtomURI = "class::DOUBLE"]
 pub struct Double {
@@ -164,6 +151,7 @@ impl Value<f64> for Double {
     }
 }
 
+#[repr(C)]
 #[derive(Copy, Clone, Atom)]
 #[AtomURI = "class::BOOL"]
 pub struct Bool {
@@ -177,6 +165,7 @@ impl Value<bool> for Bool {
     }
 }
 
+#[repr(C)]
 #[derive(Copy, Clone, Atom)]
 #[AtomURI = "class::URID"]
 pub struct URID {
@@ -191,6 +180,7 @@ impl Value<urid::URID> for URID {
 }
 
 
+#[repr(C)]
 #[derive(Atom)]
 #[AtomURI = "class::STRING"]
 pub struct String {
@@ -198,15 +188,21 @@ pub struct String {
     // content
 }
 
+impl String {
+    /// The string, up to its NUL terminator.
+    pub fn as_str(&self) -> Result<&str, read::Error> {
+        read::string(unsafe { util::body(self) })
+    }
+}
+
 impl Contents<str> for String {
+    /// Panics if the string is malformed, see `as_str`.
     unsafe fn contents(&self) -> &str {
-        let ptr = self as *const String as *const i8;
-        let ptr = ptr.offset(mem::size_of::<String>() as isize);
-        let cstr = CStr::from_ptr(ptr);
-        cstr.to_str().unwrap()
+        self.as_str().unwrap()
     }
 }
 
+#[repr(C)]
 #[allow(dead_code)]
 pub struct LiteralBody {
     datatype: urid::URID,
@@ -214,6 +210,7 @@ pub struct LiteralBody {
     // content
 }
 
+#[repr(C)]
 #[derive(Atom)]
 #[AtomURI = "class::LITERAL"]
 #[allow(dead_code)]
@@ -222,15 +219,73 @@ pub struct Literal {
     body: LiteralBody,
 }
 
+impl Literal {
+    /// URID of the literal datatype, or 0 if none.
+    pub fn datatype(&self) -> urid::URID {
+        self.body.datatype
+    }
+    /// URID of the literal language, or 0 if none.
+    pub fn lang(&self) -> urid::URID {
+        self.body.lang
+    }
+    /// The literal value, up to its NUL terminator.
+    pub fn as_str(&self) -> Result<&str, read::Error> {
+        read::string(unsafe { util::body(self) })
+    }
+}
+
 impl Contents<str> for Literal {
+    /// Panics if the literal is malformed, see `as_str`.
     unsafe fn contents(&self) -> &str {
-        let ptr = self as *const Literal as *const i8;
-        let ptr = ptr.offset(mem::size_of::<Literal>() as isize);
-        let cstr = CStr::from_ptr(ptr);
-        cstr.to_str().unwrap()
+        self.as_str().unwrap()
+    }
+}
+
+#[repr(C)]
+#[derive(Atom)]
+#[AtomURI = "class::URI"]
+pub struct URI {
+    header: Header,
+    // content
+}
+
+impl URI {
+    /// The URI, up to its NUL terminator.
+    pub fn as_str(&self) -> Result<&str, read::Error> {
+        read::string(unsafe { util::body(self) })
+    }
+}
+
+#[repr(C)]
+#[derive(Atom)]
+#[AtomURI = "class::PATH"]
+pub struct Path {
+    header: Header,
+    // content
+}
+
+impl Path {
+    /// The path, up to its NUL terminator.
+    pub fn as_path(&self) -> Result<&path::Path, read::Error> {
+        read::string(unsafe { util::body(self) }).map(path::Path::new)
     }
 }
 
+#[repr(C)]
+#[derive(Atom)]
+#[AtomURI = "class::CHUNK"]
+pub struct Chunk {
+    header: Header,
+    // content
+}
+
+impl Chunk {
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { util::body(self) }
+    }
+}
+
+#[repr(C)]
 #[derive(Atom)]
 #[AtomURI = "class::TUPLE"]
 pub struct Tuple {
@@ -238,6 +293,38 @@ pub struct Tuple {
     // content
 }
 
+impl Tuple {
+    /// Iterates over the headers of the tuple elements.
+    /// The iteration ends at the first element that does not fit in the tuple.
+    pub fn iter<'a>(&'a self) -> TupleIter<'a> {
+        TupleIter {
+            walker: unsafe { util::walk(self, 0) },
+            marker: PhantomData,
+        }
+    }
+    /// Checks that every element fits in the tuple.
+    pub fn check(&self) -> Result<(), read::Error> {
+        unsafe { util::walk(self, 0) }.check()
+    }
+}
+
+pub struct TupleIter<'a> {
+    walker: util::Walker,
+    marker: PhantomData<&'a Tuple>,
+}
+
+impl<'a> Iterator for TupleIter<'a>
+{
+    type Item = &'a Header;
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.walker.next() {
+            Some(Ok(atom)) => Some(unsafe { &*(atom as *const Header) }),
+            _ => None,
+        }
+    }
+}
+
+#[repr(C)]
 #[allow(dead_code)]
 pub struct VectorBody {
     child_size: u32,
@@ -245,6 +332,7 @@ pub struct VectorBody {
     // content
 }
 
+#[repr(C)]
 #[derive(Atom)]
 #[AtomURI = "class::VECTOR"]
 #[allow(dead_code)]
@@ -253,6 +341,53 @@ pub struct Vector {
     body: VectorBody,
 }
 
+impl Vector {
+    pub fn child_size(&self) -> usize {
+        self.body.child_size as usize
+    }
+    pub fn child_type(&self) -> urid::URID {
+        self.body.child_type
+    }
+    /// The elements as a slice of `T`, or `None` if the vector holds
+    /// elements of another type.
+    pub fn as_slice<T: Scalar>(&self, types: &Types) -> Option<&[T]> {
+        if self.body.child_type != T::child_type(types)
+                || self.body.child_size as usize != mem::size_of::<T>() {
+            return None;
+        }
+        let len = self.size().saturating_sub(mem::size_of::<VectorBody>()) / mem::size_of::<T>();
+        unsafe {
+            let ptr = (self as *const Vector).offset(1) as *const T;
+            Some(slice::from_raw_parts(ptr, len))
+        }
+    }
+}
+
+/// An audio waveform, laid out as a vector of floats.
+#[repr(C)]
+#[derive(Atom)]
+#[AtomURI = "class::SOUND"]
+pub struct Sound {
+    header: Header,
+    body: VectorBody,
+}
+
+impl Sound {
+    /// The samples, or `None` if the elements are not `atom:Float`.
+    pub fn as_slice(&self, types: &Types) -> Option<&[f32]> {
+        if self.body.child_type != types.float
+                || self.body.child_size as usize != mem::size_of::<f32>() {
+            return None;
+        }
+        let len = self.size().saturating_sub(mem::size_of::<VectorBody>()) / mem::size_of::<f32>();
+        unsafe {
+            let ptr = (self as *const Sound).offset(1) as *const f32;
+            Some(slice::from_raw_parts(ptr, len))
+        }
+    }
+}
+
+#[repr(C)]
 #[allow(dead_code)]
 pub struct PropertyBody {
     key: urid::URID,
@@ -261,6 +396,7 @@ pub struct PropertyBody {
     // value body
 }
 
+#[repr(C)]
 #[derive(Atom)]
 #[AtomURI = "class::PROPERTY"]
 #[allow(dead_code)]
@@ -269,6 +405,31 @@ pub struct Property {
     body: PropertyBody,
 }
 
+impl PropertyBody {
+    pub fn key(&self) -> urid::URID {
+        self.key
+    }
+    pub fn context(&self) -> urid::URID {
+        self.context
+    }
+    pub fn value(&self) -> &Header {
+        &self.value
+    }
+}
+
+impl Property {
+    pub fn key(&self) -> urid::URID {
+        self.body.key
+    }
+    pub fn context(&self) -> urid::URID {
+        self.body.context
+    }
+    pub fn value(&self) -> &Header {
+        &self.body.value
+    }
+}
+
+#[repr(C)]
 #[allow(dead_code)]
 pub struct ObjectBody {
     id: urid::URID,
@@ -276,6 +437,7 @@ pub struct ObjectBody {
     // content
 }
 
+#[repr(C)]
 #[derive(Atom)]
 #[AtomURI = "class::OBJECT"]
 #[allow(dead_code)]
@@ -284,18 +446,176 @@ pub struct Object {
     body: ObjectBody,
 }
 
+impl Object {
+    /// URID of the object subject, or 0 for a blank node.
+    pub fn id(&self) -> urid::URID {
+        self.body.id
+    }
+    /// URID of the object type.
+    pub fn otype(&self) -> urid::URID {
+        self.body.otype
+    }
+    /// Iterates over the properties as `(key, context, value)`.
+    /// The iteration ends at the first property that does not fit in the object.
+    pub fn iter<'a>(&'a self) -> ObjectIter<'a> {
+        ObjectIter {
+            walker: unsafe { util::walk(self, 8) },
+            marker: PhantomData,
+        }
+    }
+    /// Checks that every property fits in the object.
+    pub fn check(&self) -> Result<(), read::Error> {
+        unsafe { util::walk(self, 8) }.check()
+    }
+    /// Gets the values of several properties in a single pass, like
+    /// `lv2_atom_object_get_typed`. Each slot receives the value of the first
+    /// property with its key and the type of the slot, or `None` if the object
+    /// has no such property. Returns the number of slots filled.
+    pub fn get<'a>(&'a self, types: &Types, query: &mut [(urid::URID, &mut dyn PropertySlot<'a>)])
+            -> usize {
+        for &mut (_, ref mut slot) in query.iter_mut() {
+            slot.clear();
+        }
+        let mut found = 0;
+        for (key, _, value) in self.iter() {
+            for &mut (k, ref mut slot) in query.iter_mut() {
+                if k == key && !slot.is_filled() && slot.fill(value, types) {
+                    found += 1;
+                }
+            }
+            if found == query.len() {
+                break;
+            }
+        }
+        found
+    }
+    /// Untyped variant of `get`, like `lv2_atom_object_get`. Each slot
+    /// receives the value of the first property with its key, whatever its type.
+    pub fn get_raw<'a>(&'a self, query: &mut [(urid::URID, &mut Option<&'a Header>)]) -> usize {
+        for &mut (_, ref mut slot) in query.iter_mut() {
+            **slot = None;
+        }
+        let mut found = 0;
+        for (key, _, value) in self.iter() {
+            for &mut (k, ref mut slot) in query.iter_mut() {
+                if k == key && slot.is_none() {
+                    **slot = Some(value);
+                    found += 1;
+                }
+            }
+            if found == query.len() {
+                break;
+            }
+        }
+        found
+    }
+}
+
+/// A slot receiving a property value in `Object::get`.
+pub trait PropertySlot<'a> {
+    /// Stores `value` if it has the type of the slot. Returns whether it did.
+    fn fill(&mut self, value: &'a Header, types: &Types) -> bool;
+    fn clear(&mut self);
+    fn is_filled(&self) -> bool;
+}
+
+impl<'a, T: AtomType> PropertySlot<'a> for Option<&'a T> {
+    fn fill(&mut self, value: &'a Header, types: &Types) -> bool {
+        *self = value.downcast::<T>(types);
+        self.is_some()
+    }
+    fn clear(&mut self) {
+        *self = None;
+    }
+    fn is_filled(&self) -> bool {
+        self.is_some()
+    }
+}
+
+pub struct ObjectIter<'a> {
+    walker: util::Walker,
+    marker: PhantomData<&'a Object>,
+}
+
+impl<'a> Iterator for ObjectIter<'a>
+{
+    type Item = (urid::URID, urid::URID, &'a Header);
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.walker.next() {
+            Some(Ok(prop)) => {
+                let prop = unsafe { &*(prop as *const PropertyBody) };
+                Some((prop.key, prop.context, &prop.value))
+            },
+            _ => None,
+        }
+    }
+}
+
+#[repr(C)]
 pub struct Event {
     frames: i64,
     body: Header,
     // content
 }
 
+/// Unit of the event time stamps of a sequence.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimeUnit {
+    /// Audio frames, `atom:frameTime`.
+    Frames,
+    /// Beats, `atom:beatTime`.
+    Beats,
+}
+
+/// Time stamp of an event, relative to the start of the run cycle.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Time {
+    Frames(i64),
+    Beats(f64),
+}
+
+impl Time {
+    /// The time in frames, converting beats at the given tempo.
+    pub fn to_frames(self, bpm: f64, sample_rate: f64) -> i64 {
+        match self {
+            Time::Frames(frames) => frames,
+            Time::Beats(beats) => beats_to_frames(beats, bpm, sample_rate).round() as i64,
+        }
+    }
+    /// The time in beats, converting frames at the given tempo.
+    pub fn to_beats(self, bpm: f64, sample_rate: f64) -> f64 {
+        match self {
+            Time::Frames(frames) => frames_to_beats(frames as f64, bpm, sample_rate),
+            Time::Beats(beats) => beats,
+        }
+    }
+}
+
+/// Converts a duration in beats to frames, at `bpm` beats per minute.
+pub fn beats_to_frames(beats: f64, bpm: f64, sample_rate: f64) -> f64 {
+    beats * 60.0 / bpm * sample_rate
+}
+
+/// Converts a duration in frames to beats, at `bpm` beats per minute.
+pub fn frames_to_beats(frames: f64, bpm: f64, sample_rate: f64) -> f64 {
+    frames / sample_rate * bpm / 60.0
+}
+
 impl Event {
+    /// The time stamp, valid if the sequence is timed in frames.
     pub unsafe fn time_frames(&self) -> i64 {
         self.frames
     }
+    /// The time stamp, valid if the sequence is timed in beats.
     pub unsafe fn time_beats(&self) -> f64 {
-        mem::transmute(self.frames)
+        f64::from_bits(self.frames as u64)
+    }
+    /// The time stamp read in `unit`, which must be the unit of the sequence.
+    fn time(&self, unit: TimeUnit) -> Time {
+        match unit {
+            TimeUnit::Frames => Time::Frames(self.frames),
+            TimeUnit::Beats => Time::Beats(f64::from_bits(self.frames as u64)),
+        }
     }
     pub fn type_urid(&self) -> urid::URID {
         self.body.type_urid
@@ -303,6 +623,14 @@ impl Event {
     pub fn size(&self) -> usize {
         self.body.size as usize
     }
+    /// The header of the event atom.
+    pub fn atom(&self) -> &Header {
+        &self.body
+    }
+    /// The event atom as `T`, see `Header::downcast`.
+    pub fn downcast<T: AtomType>(&self, types: &Types) -> Option<&T> {
+        self.body.downcast(types)
+    }
     pub unsafe fn contents(&self) -> &[u8] {
         slice::from_raw_parts(
             (self as *const Event as *const u8).offset(mem::size_of::<Event>() as isize),
@@ -311,12 +639,14 @@ impl Event {
     }
 }
 
+#[repr(C)]
 #[allow(dead_code)]
 pub struct SequenceBody {
     unit: urid::URID,
     pad: u32,
 }
 
+#[repr(C)]
 #[derive(Atom)]
 #[AtomURI = "class::SEQUENCE"]
 #[allow(dead_code)]
@@ -326,54 +656,251 @@ pub struct Sequence {
 }
 
 impl Sequence {
+    /// Iterates over the events.
+    /// The iteration ends at the first event that does not fit in the sequence.
     pub fn iter<'a>(&'a self) -> SequenceIter<'a> {
         SequenceIter {
-            seq: self,
-            event: unsafe { util::sequence_begin(&self.body) }
+            walker: unsafe { util::walk(self, 8) },
+            marker: PhantomData,
+        }
+    }
+    /// Checks that every event fits in the sequence.
+    pub fn check(&self) -> Result<(), read::Error> {
+        unsafe { util::walk(self, 8) }.check()
+    }
+    /// The unit of the event time stamps, or `None` if unknown.
+    /// A unit of 0 stands for frames.
+    pub fn time_unit(&self, types: &Types) -> Option<TimeUnit> {
+        let unit = self.body.unit;
+        if unit == 0 || unit == types.frame_time {
+            Some(TimeUnit::Frames)
+        }
+        else if unit == types.beat_time {
+            Some(TimeUnit::Beats)
+        }
+        else {
+            None
+        }
+    }
+    /// Iterates over the events with their time stamps.
+    /// A sequence timed in an unknown unit yields no event.
+    pub fn timed<'a>(&'a self, types: &Types) -> TimedIter<'a> {
+        TimedIter {
+            unit: self.time_unit(types),
+            iter: self.iter(),
         }
     }
 }
 
 pub struct SequenceIter<'a> {
-    seq: &'a Sequence,
-    event: *const Event,
+    walker: util::Walker,
+    marker: PhantomData<&'a Sequence>,
 }
 
 impl<'a> Iterator for SequenceIter<'a>
 {
     type Item = &'a Event;
     fn next(&mut self) -> Option<Self::Item> {
-        unsafe {
-            if util::sequence_is_end(&self.seq.body, self.seq.size(), self.event) {
-                None
-            }
-            else {
-                let ev = self.event;
-                self.event = util::sequence_next(self.event);
-                Some(mem::transmute(ev))
-            }
+        match self.walker.next() {
+            Some(Ok(ev)) => Some(unsafe { &*(ev as *const Event) }),
+            _ => None,
         }
     }
 }
 
+pub struct TimedIter<'a> {
+    unit: Option<TimeUnit>,
+    iter: SequenceIter<'a>,
+}
+
+impl<'a> Iterator for TimedIter<'a>
+{
+    type Item = (Time, &'a Event);
+    fn next(&mut self) -> Option<Self::Item> {
+        let unit = self.unit?;
+        self.iter.next().map(|ev| (ev.time(unit), ev))
+    }
+}
+
 pub mod meta {
+    use ::core;
     use ::core::meta;
-    use ::atom::Sequence;
+    use ::atom::{self, Header, Sequence, SequenceOutput};
     use std::mem;
     use std::ptr;
 
+    impl<'h> meta::PortField<'h> for &'h Sequence {
+        type Meta = InputSequence;
+    }
+
     pub enum InputSequence {}
     unsafe impl<'h> meta::Port<'h> for InputSequence {
         type FieldRaw = *const Sequence;
         type Field = &'h Sequence;
+        fn classes(_index: usize) -> &'static [&'static str] {
+            &[core::class::INPUTPORT, atom::class::ATOMPORT]
+        }
+        fn statements() -> &'static [(&'static str, &'static str)] {
+            &[(atom::prop::BUFFERTYPE, atom::class::SEQUENCE)]
+        }
         fn new_raw() -> Self::FieldRaw {
             ptr::null()
         }
-        fn cast_raw(data: *mut ()) -> Self::FieldRaw {
-            data as Self::FieldRaw
+        fn connect(raw: &mut Self::FieldRaw, _index: usize, data: *mut ()) {
+            *raw = data as Self::FieldRaw;
+        }
+        fn is_connected(raw: Self::FieldRaw) -> bool {
+            !raw.is_null()
         }
         fn convert(raw: Self::FieldRaw, _sample_count: usize) -> Self::Field {
             unsafe { mem::transmute(raw) }
         }
     }
+
+    impl<'h> meta::PortField<'h> for SequenceOutput<'h> {
+        type Meta = OutputSequence;
+    }
+
+    pub enum OutputSequence {}
+    unsafe impl<'h> meta::Port<'h> for OutputSequence {
+        type FieldRaw = *mut Sequence;
+        type Field = SequenceOutput<'h>;
+        fn classes(_index: usize) -> &'static [&'static str] {
+            &[core::class::OUTPUTPORT, atom::class::ATOMPORT]
+        }
+        fn statements() -> &'static [(&'static str, &'static str)] {
+            &[(atom::prop::BUFFERTYPE, atom::class::SEQUENCE)]
+        }
+        fn new_raw() -> Self::FieldRaw {
+            ptr::null_mut()
+        }
+        fn connect(raw: &mut Self::FieldRaw, _index: usize, data: *mut ()) {
+            *raw = data as Self::FieldRaw;
+        }
+        fn is_connected(raw: Self::FieldRaw) -> bool {
+            !raw.is_null()
+        }
+        fn convert(raw: Self::FieldRaw, _sample_count: usize) -> Self::Field {
+            // from_raw gives an output without capacity if raw is null
+            unsafe { SequenceOutput::from_raw(raw as *mut Header) }
+        }
+        fn silence(raw: Self::FieldRaw, _sample_count: usize) {
+            if raw.is_null() {
+                return;
+            }
+            unsafe { SequenceOutput::from_raw(raw as *mut Header); }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::testing::forged;
+
+    // an object with keys 1 to 4: an int, a string, a float and another int
+    fn object(forge: &mut Forge) {
+        let frame = forge.object(0, 30).unwrap();
+        forge.key(1).unwrap();
+        forge.int(7).unwrap();
+        forge.key(2).unwrap();
+        forge.string("x").unwrap();
+        forge.key(3).unwrap();
+        forge.float(0.5).unwrap();
+        forge.key(1).unwrap();
+        forge.int(8).unwrap();
+        forge.pop(frame);
+    }
+
+    #[test]
+    fn object_get() {
+        let types = Types::test();
+        let forged = forged(&types, object);
+        let object = forged.atom(0).downcast::<Object>(&types).unwrap();
+
+        let mut int: Option<&Int> = None;
+        let mut string: Option<&String> = None;
+        let mut float: Option<&Float> = None;
+        assert_eq!(object.get(&types, &mut [(1, &mut int), (2, &mut string), (3, &mut float)]), 3);
+        assert_eq!(int.unwrap().value(), 7);
+        assert_eq!(string.unwrap().as_str(), Ok("x"));
+        assert_eq!(float.unwrap().value(), 0.5);
+
+        // slots are cleared, and left empty for missing keys
+        assert_eq!(object.get(&types, &mut [(4, &mut float)]), 0);
+        assert!(float.is_none());
+    }
+
+    #[test]
+    fn object_get_checks_types() {
+        let types = Types::test();
+        let forged = forged(&types, object);
+        let object = forged.atom(0).downcast::<Object>(&types).unwrap();
+
+        let mut long: Option<&Long> = None;
+        let mut string: Option<&String> = None;
+        assert_eq!(object.get(&types, &mut [(1, &mut long), (3, &mut string)]), 0);
+        assert!(long.is_none() && string.is_none());
+    }
+
+    #[test]
+    fn object_get_raw() {
+        let types = Types::test();
+        let forged = forged(&types, object);
+        let object = forged.atom(0).downcast::<Object>(&types).unwrap();
+
+        let (mut first, mut third, mut missing) = (None, None, None);
+        assert_eq!(object.get_raw(&mut [(1, &mut first), (3, &mut third), (4, &mut missing)]), 2);
+        assert_eq!(first.unwrap().downcast::<Int>(&types).unwrap().value(), 7);
+        assert_eq!(third.unwrap().type_urid(), types.float);
+        assert!(missing.is_none());
+    }
+
+    #[test]
+    fn sound_samples() {
+        let types = Types::test();
+        let forged = forged(&types, |forge| {
+            forge.sound(&[0.5, -1.0]).unwrap();
+            // 32-bit elements which are not floats
+            let mut body = Vec::new();
+            for w in &[4, types.int, 1, 2] {
+                body.extend_from_slice(&w.to_ne_bytes());
+            }
+            forge.atom(types.sound, &body).unwrap();
+        });
+        let sound = forged.atom(0).downcast::<Sound>(&types).unwrap();
+        assert_eq!(sound.as_slice(&types), Some(&[0.5, -1.0][..]));
+        let ints = forged.atom(24).downcast::<Sound>(&types).unwrap();
+        assert_eq!(ints.as_slice(&types), None);
+    }
+
+    #[test]
+    fn sequence_times() {
+        let types = Types::test();
+        let forged = forged(&types, |forge| {
+            let frame = forge.sequence(0).unwrap();
+            forge.frame_time(3).unwrap();
+            forge.int(1).unwrap();
+            forge.pop(frame);
+            let frame = forge.sequence(types.beat_time).unwrap();
+            forge.beat_time(1.5).unwrap();
+            forge.int(2).unwrap();
+            forge.pop(frame);
+            let frame = forge.sequence(99).unwrap();
+            forge.frame_time(3).unwrap();
+            forge.int(3).unwrap();
+            forge.pop(frame);
+        });
+        let sequence = |offset: usize| forged.atom(offset).downcast::<Sequence>(&types).unwrap();
+        let times = |seq: &Sequence| seq.timed(&types).map(|(time, _)| time).collect::<Vec<_>>();
+
+        assert_eq!(sequence(0).time_unit(&types), Some(TimeUnit::Frames));
+        assert_eq!(times(sequence(0)), vec![Time::Frames(3)]);
+        assert_eq!(sequence(40).time_unit(&types), Some(TimeUnit::Beats));
+        assert_eq!(times(sequence(40)), vec![Time::Beats(1.5)]);
+        // events of an unknown unit are not misread
+        assert_eq!(sequence(80).time_unit(&types), None);
+        assert_eq!(times(sequence(80)), vec![]);
+        assert_eq!(sequence(80).iter().count(), 1);
+    }
 }
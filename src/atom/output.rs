@@ -0,0 +1,134 @@
+use ::atom::forge::{Error, Forge, Frame};
+use ::atom::types::Types;
+use ::atom::Header;
+use urid::URID;
+
+use std::mem;
+use std::ptr;
+use std::slice;
+
+/// The buffer of an output sequence port.
+///
+/// The host signals the capacity of the buffer in the size field of its atom
+/// header. The capacity is read when the port is converted, then the size
+/// field is overwritten with the size of an empty sequence body, or with 0 if
+/// the buffer cannot hold one.
+pub struct SequenceOutput<'h> {
+    buf: &'h mut [u8],
+}
+
+impl<'h> SequenceOutput<'h> {
+    /// Reads the capacity from `header` and resets the sequence.
+    /// A null `header` gives an output without capacity.
+    pub unsafe fn from_raw(header: *mut Header) -> SequenceOutput<'h> {
+        if header.is_null() {
+            return SequenceOutput { buf: &mut [] };
+        }
+        let capacity = (*header).size();
+        let len = mem::size_of::<Header>() + capacity;
+        if capacity >= 8 {
+            (*header).size = 8;
+            ptr::write_bytes(header.offset(1) as *mut u8, 0, 8);
+        }
+        else {
+            (*header).size = 0;
+        }
+        SequenceOutput {
+            buf: slice::from_raw_parts_mut(header as *mut u8, len),
+        }
+    }
+
+    /// Space available for the sequence body, in bytes.
+    pub fn capacity(&self) -> usize {
+        self.buf.len().saturating_sub(mem::size_of::<Header>())
+    }
+
+    /// Starts an empty sequence timed in audio frames and returns a writer
+    /// appending events to it.
+    pub fn writer<'a>(&'a mut self, types: &Types) -> SequenceWriter<'a> {
+        let mut forge = Forge::new(&mut *self.buf, types);
+        let frame = forge.sequence(0).ok();
+        SequenceWriter {
+            forge: forge,
+            frame: frame,
+            last: 0,
+        }
+    }
+}
+
+/// Appends events to an output sequence.
+/// The sequence is valid after every call, so the writer needs no closing.
+pub struct SequenceWriter<'a> {
+    forge: Forge<'a>,
+    frame: Option<Frame>,
+    last: i64,
+}
+
+impl<'a> SequenceWriter<'a> {
+    pub fn types(&self) -> &Types {
+        self.forge.types()
+    }
+
+    /// Space left for events, in bytes.
+    pub fn remaining(&self) -> usize {
+        self.forge.remaining()
+    }
+
+    /// Appends an event at `frames`, whose atom is written by `f`.
+    /// The time must not be before the one of the previous event.
+    /// If `f` fails, the sequence is left as it was before the call.
+    pub fn event<F>(&mut self, frames: i64, f: F) -> Result<(), Error>
+        where F: FnOnce(&mut Forge) -> Result<(), Error>
+    {
+        if frames < self.last {
+            return Err(Error::NonMonotonicTime);
+        }
+        let frame = match self.frame {
+            Some(ref frame) => frame,
+            None => return Err(Error::Overflow),
+        };
+        let forge = &mut self.forge;
+        let start = forge.mark();
+        let res = forge.frame_time(frames).and_then(|_| f(forge));
+        if res.is_err() || forge.offset() == start.0 + 8 {
+            forge.rewind(start);
+            return res;
+        }
+        forge.update_size(frame);
+        self.last = frames;
+        Ok(())
+    }
+
+    /// Appends an event at `frames` made of an atom of type `type_urid`.
+    pub fn atom(&mut self, frames: i64, type_urid: URID, body: &[u8]) -> Result<(), Error> {
+        self.event(frames, |forge| forge.atom(type_urid, body))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::atom::testing::words;
+
+    fn size(buf: &[u64]) -> usize {
+        unsafe { (*(buf.as_ptr() as *const Header)).size() }
+    }
+
+    #[test]
+    fn from_raw() {
+        let mut buf = words(&[16, 13, 1, 1, 1, 1]);
+        let out = unsafe { SequenceOutput::from_raw(buf.as_mut_ptr() as *mut Header) };
+        assert_eq!(out.capacity(), 16);
+        assert_eq!(size(&buf), 8);
+        assert_eq!(buf[1], 0);
+
+        let mut buf = words(&[4, 13, 1]);
+        let out = unsafe { SequenceOutput::from_raw(buf.as_mut_ptr() as *mut Header) };
+        assert_eq!(out.capacity(), 4);
+        assert_eq!(size(&buf), 0);
+
+        let out = unsafe { SequenceOutput::from_raw(ptr::null_mut()) };
+        assert_eq!(out.capacity(), 0);
+    }
+}
@@ -0,0 +1,126 @@
+use ::atom::Header;
+
+use std::error;
+use std::fmt;
+use std::mem;
+use std::str;
+
+/// Error returned when reading malformed atoms.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The buffer is not aligned to 64 bits.
+    Misaligned,
+    /// An atom extends beyond its buffer or container.
+    Truncated,
+    /// A string atom is not terminated by a NUL byte.
+    MissingNul,
+    /// A string atom is not valid UTF-8.
+    Utf8(str::Utf8Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Misaligned => f.write_str("atom buffer is not 64-bit aligned"),
+            Error::Truncated => f.write_str("atom extends beyond its container"),
+            Error::MissingNul => f.write_str("string atom is not NUL terminated"),
+            Error::Utf8(ref err) => write!(f, "string atom is not valid UTF-8: {}", err),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<str::Utf8Error> for Error {
+    fn from(err: str::Utf8Error) -> Error {
+        Error::Utf8(err)
+    }
+}
+
+/// Reads the atom at the start of `bytes`, checking that it fits in the slice.
+pub fn atom(bytes: &[u8]) -> Result<&Header, Error> {
+    if bytes.as_ptr() as usize % 8 != 0 {
+        return Err(Error::Misaligned);
+    }
+    if bytes.len() < mem::size_of::<Header>() {
+        return Err(Error::Truncated);
+    }
+    let header = unsafe { &*(bytes.as_ptr() as *const Header) };
+    if header.size() > bytes.len() - mem::size_of::<Header>() {
+        return Err(Error::Truncated);
+    }
+    Ok(header)
+}
+
+/// Reads a string body, which must contain a NUL terminator.
+pub fn string(body: &[u8]) -> Result<&str, Error> {
+    match body.iter().position(|&b| b == 0) {
+        Some(len) => Ok(str::from_utf8(&body[.. len])?),
+        None => Err(Error::MissingNul),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::atom::testing::words;
+    use std::slice;
+
+    fn bytes(buf: &[u64], len: usize) -> &[u8] {
+        assert!(len <= buf.len() * 8);
+        unsafe { slice::from_raw_parts(buf.as_ptr() as *const u8, len) }
+    }
+
+    #[test]
+    fn atom_fits() {
+        let buf = words(&[4, 1, 7, 0]);
+        let header = atom(bytes(&buf, 16)).unwrap();
+        assert_eq!(header.size(), 4);
+        assert_eq!(header.type_urid(), 1);
+        // the padding is not required
+        assert!(atom(bytes(&buf, 12)).is_ok());
+    }
+
+    #[test]
+    fn atom_misaligned() {
+        let buf = words(&[0, 0, 0, 0]);
+        assert_eq!(atom(&bytes(&buf, 16)[4 ..]).err(), Some(Error::Misaligned));
+    }
+
+    #[test]
+    fn atom_truncated_header() {
+        let buf = words(&[0, 1]);
+        assert_eq!(atom(bytes(&buf, 0)).err(), Some(Error::Truncated));
+        assert_eq!(atom(bytes(&buf, 7)).err(), Some(Error::Truncated));
+    }
+
+    #[test]
+    fn atom_oversized() {
+        let buf = words(&[9, 1, 0, 0]);
+        assert_eq!(atom(bytes(&buf, 16)).err(), Some(Error::Truncated));
+        let buf = words(&[!0, 1]);
+        assert_eq!(atom(bytes(&buf, 8)).err(), Some(Error::Truncated));
+    }
+
+    #[test]
+    fn string_terminated() {
+        assert_eq!(string(b"abc\0").unwrap(), "abc");
+        assert_eq!(string(b"\0").unwrap(), "");
+        // the padding after the terminator is ignored
+        assert_eq!(string(b"ab\0\0\xff\xff").unwrap(), "ab");
+    }
+
+    #[test]
+    fn string_missing_nul() {
+        assert_eq!(string(b"").unwrap_err(), Error::MissingNul);
+        assert_eq!(string(b"abc").unwrap_err(), Error::MissingNul);
+    }
+
+    #[test]
+    fn string_invalid_utf8() {
+        match string(b"a\xc3\0") {
+            Err(Error::Utf8(err)) => assert_eq!(err.valid_up_to(), 1),
+            res => panic!("unexpected {:?}", res),
+        }
+    }
+}
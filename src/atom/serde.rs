@@ -0,0 +1,857 @@
+//! Conversion of atoms to and from the serde data model.
+//!
+//! `tree` serializes an atom and its children, with URIDs unmapped to URIs.
+//! `to_forge` writes any serializable value as atoms, structs and maps
+//! becoming objects whose keys are mapped from the field names.
+
+use ::atom::{self, forge, util, Forge, Frame, Header, Types, Value};
+use turtle::rdf;
+use urid::{Map, Unmap, URID};
+
+use serde::ser::{self, Serialize, SerializeMap, SerializeSeq, Serializer};
+
+use std::error;
+use std::fmt;
+
+/// An atom tree, serialized with `tree`.
+pub struct Tree<'a> {
+    atom: &'a Header,
+    types: &'a Types,
+    unmap: &'a Unmap<'a>,
+}
+
+/// Serializes `atom` and its children, with URIDs unmapped to URIs.
+///
+/// Scalars and strings map to their serde counterparts, tuples, vectors and
+/// sequences to sequences, and objects to maps keyed by property URIs with
+/// their `@id` and `@type`. Other atoms, and sequences timed in an unknown
+/// unit, are maps of their `type` and `body`.
+pub fn tree<'a>(atom: &'a Header, types: &'a Types, unmap: &'a Unmap<'a>) -> Tree<'a> {
+    Tree {
+        atom: atom,
+        types: types,
+        unmap: unmap,
+    }
+}
+
+impl<'a> Tree<'a> {
+    fn child(&self, atom: &'a Header) -> Tree<'a> {
+        tree(atom, self.types, self.unmap)
+    }
+
+    /// The URI of `urid`, or its number if it cannot be unmapped.
+    fn uri(&self, urid: URID) -> ::std::string::String {
+        match self.unmap.unmap(urid) {
+            Some(uri) => uri.to_string(),
+            None => urid.to_string(),
+        }
+    }
+}
+
+fn string<E: ser::Error>(s: Result<&str, atom::read::Error>) -> Result<&str, E> {
+    s.map_err(E::custom)
+}
+
+impl<'a> Serialize for Tree<'a> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let (atom, types) = (self.atom, self.types);
+        if let Some(v) = atom.downcast::<atom::Int>(types) {
+            s.serialize_i32(v.value())
+        }
+        else if let Some(v) = atom.downcast::<atom::Long>(types) {
+            s.serialize_i64(v.value())
+        }
+        else if let Some(v) = atom.downcast::<atom::Float>(types) {
+            s.serialize_f32(v.value())
+        }
+        else if let Some(v) = atom.downcast::<atom::Double>(types) {
+            s.serialize_f64(v.value())
+        }
+        else if let Some(v) = atom.downcast::<atom::Bool>(types) {
+            s.serialize_bool(v.value())
+        }
+        else if let Some(v) = atom.downcast::<atom::URID>(types) {
+            s.serialize_str(&self.uri(v.value()))
+        }
+        else if let Some(v) = atom.downcast::<atom::String>(types) {
+            s.serialize_str(string(v.as_str())?)
+        }
+        else if let Some(v) = atom.downcast::<atom::URI>(types) {
+            s.serialize_str(string(v.as_str())?)
+        }
+        else if let Some(v) = atom.downcast::<atom::Path>(types) {
+            s.serialize_str(string(atom::read::string(unsafe { util::body(v) }))?)
+        }
+        else if let Some(v) = atom.downcast::<atom::Literal>(types) {
+            let value = string(v.as_str())?;
+            if v.datatype() == 0 && v.lang() == 0 {
+                return s.serialize_str(value);
+            }
+            let mut map = s.serialize_map(None)?;
+            map.serialize_entry("value", value)?;
+            if v.datatype() != 0 {
+                map.serialize_entry("datatype", &self.uri(v.datatype()))?;
+            }
+            if v.lang() != 0 {
+                map.serialize_entry("lang", &self.uri(v.lang()))?;
+            }
+            map.end()
+        }
+        else if let Some(v) = atom.downcast::<atom::Chunk>(types) {
+            s.serialize_bytes(v.as_bytes())
+        }
+        else if let Some(v) = atom.downcast::<atom::Sound>(types) {
+            match v.as_slice(types) {
+                Some(samples) => s.collect_seq(samples),
+                None => self.serialize_raw(s),
+            }
+        }
+        else if let Some(v) = atom.downcast::<atom::Vector>(types) {
+            if let Some(items) = v.as_slice::<i32>(types) {
+                s.collect_seq(items)
+            }
+            else if let Some(items) = v.as_slice::<i64>(types) {
+                s.collect_seq(items)
+            }
+            else if let Some(items) = v.as_slice::<f32>(types) {
+                s.collect_seq(items)
+            }
+            else if let Some(items) = v.as_slice::<f64>(types) {
+                s.collect_seq(items)
+            }
+            else {
+                self.serialize_raw(s)
+            }
+        }
+        else if let Some(v) = atom.downcast::<atom::Tuple>(types) {
+            s.collect_seq(v.iter().map(|elem| self.child(elem)))
+        }
+        else if let Some(v) = atom.downcast::<atom::Object>(types) {
+            let mut map = s.serialize_map(None)?;
+            if v.id() != 0 {
+                map.serialize_entry("@id", &self.uri(v.id()))?;
+            }
+            if v.otype() != 0 {
+                map.serialize_entry("@type", &self.uri(v.otype()))?;
+            }
+            for (key, _, value) in v.iter() {
+                map.serialize_entry(&self.uri(key), &self.child(value))?;
+            }
+            map.end()
+        }
+        else if let Some(v) = atom.downcast::<atom::Property>(types) {
+            let mut map = s.serialize_map(None)?;
+            map.serialize_entry("key", &self.uri(v.key()))?;
+            if v.context() != 0 {
+                map.serialize_entry("context", &self.uri(v.context()))?;
+            }
+            map.serialize_entry("value", &self.child(v.value()))?;
+            map.end()
+        }
+        else if let Some(v) = atom.downcast::<atom::Sequence>(types) {
+            if v.time_unit(types).is_none() {
+                return self.serialize_raw(s);
+            }
+            let mut seq = s.serialize_seq(None)?;
+            for (time, ev) in v.timed(types) {
+                seq.serialize_element(&EventTree {
+                    time: time,
+                    atom: self.child(ev.atom()),
+                })?;
+            }
+            seq.end()
+        }
+        else {
+            self.serialize_raw(s)
+        }
+    }
+}
+
+impl<'a> Tree<'a> {
+    fn serialize_raw<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let mut map = s.serialize_map(Some(2))?;
+        map.serialize_entry("type", &self.uri(self.atom.type_urid()))?;
+        map.serialize_entry("body", &Bytes(unsafe { self.atom.contents() }))?;
+        map.end()
+    }
+}
+
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Serialize for Bytes<'a> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_bytes(self.0)
+    }
+}
+
+struct EventTree<'a> {
+    time: atom::Time,
+    atom: Tree<'a>,
+}
+
+impl<'a> Serialize for EventTree<'a> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        let mut map = s.serialize_map(Some(2))?;
+        match self.time {
+            atom::Time::Frames(frames) => map.serialize_entry("frames", &frames)?,
+            atom::Time::Beats(beats) => map.serialize_entry("beats", &beats)?,
+        }
+        map.serialize_entry("atom", &self.atom)?;
+        map.end()
+    }
+}
+
+/// Error returned by `to_forge`.
+#[derive(Debug)]
+pub enum Error {
+    Forge(forge::Error),
+    /// The value cannot be represented as atoms.
+    Message(::std::string::String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Forge(ref err) => err.fmt(f),
+            Error::Message(ref msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error::Message(msg.to_string())
+    }
+}
+
+impl From<forge::Error> for Error {
+    fn from(err: forge::Error) -> Error {
+        Error::Forge(err)
+    }
+}
+
+/// Writes `value` as atoms.
+///
+/// Structs and maps become objects. Their type and keys are the struct, field
+/// and key names, mapped after prefixing them with `base` unless they already
+/// are URIs (contain a colon). Unit enum variants become URIDs mapped the same
+/// way, and other variants objects of the variant type with an `rdf:value`.
+/// Sequences and tuples become tuples, bytes become chunks, and `None` and
+/// `()` are omitted. On error, the forge is left as it was before the call.
+pub fn to_forge<T>(forge: &mut Forge, map: &Map, base: &str, value: &T) -> Result<(), Error>
+    where T: Serialize + ?Sized
+{
+    let mark = forge.mark();
+    let res = value.serialize(&mut AtomSerializer {
+        forge: forge,
+        map: map,
+        base: base,
+    });
+    if res.is_err() {
+        forge.rewind(mark);
+    }
+    res
+}
+
+struct AtomSerializer<'a, 'b: 'a> {
+    forge: &'a mut Forge<'b>,
+    map: &'a Map<'a>,
+    base: &'a str,
+}
+
+impl<'a, 'b> AtomSerializer<'a, 'b> {
+    fn urid(&self, name: &str) -> URID {
+        if name.contains(':') {
+            self.map.map(name)
+        }
+        else {
+            self.map.map(&format!("{}{}", self.base, name))
+        }
+    }
+
+    fn object(&mut self, name: &str) -> Result<Frame, Error> {
+        let otype = self.urid(name);
+        Ok(self.forge.object(0, otype)?)
+    }
+
+    /// Opens an object of the variant type, holding the value in `rdf:value`.
+    fn variant(&mut self, variant: &str) -> Result<Frame, Error> {
+        let frame = self.object(variant)?;
+        let key = self.map.map(rdf::VALUE);
+        self.forge.key(key)?;
+        Ok(frame)
+    }
+}
+
+/// Container being serialized, with the frames to pop when it ends.
+struct Compound<'s, 'a: 's, 'b: 'a> {
+    ser: &'s mut AtomSerializer<'a, 'b>,
+    frame: Frame,
+    outer: Option<Frame>,
+    /// Position before the last map key, to drop it if the value is omitted.
+    key_mark: (usize, usize),
+}
+
+impl<'s, 'a, 'b> Compound<'s, 'a, 'b> {
+    fn new(ser: &'s mut AtomSerializer<'a, 'b>, frame: Frame, outer: Option<Frame>)
+            -> Compound<'s, 'a, 'b> {
+        let mark = ser.forge.mark();
+        Compound {
+            ser: ser,
+            frame: frame,
+            outer: outer,
+            key_mark: mark,
+        }
+    }
+
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn key(&mut self, name: &str) -> Result<(), Error> {
+        self.key_mark = self.ser.forge.mark();
+        let key = self.ser.urid(name);
+        Ok(self.ser.forge.key(key)?)
+    }
+
+    fn value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let offset = self.ser.forge.offset();
+        value.serialize(&mut *self.ser)?;
+        if self.ser.forge.offset() == offset {
+            self.ser.forge.rewind(self.key_mark);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.ser.forge.pop(self.frame);
+        if let Some(outer) = self.outer {
+            self.ser.forge.pop(outer);
+        }
+        Ok(())
+    }
+}
+
+impl<'s, 'a, 'b> Serializer for &'s mut AtomSerializer<'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'s, 'a, 'b>;
+    type SerializeTuple = Compound<'s, 'a, 'b>;
+    type SerializeTupleStruct = Compound<'s, 'a, 'b>;
+    type SerializeTupleVariant = Compound<'s, 'a, 'b>;
+    type SerializeMap = Compound<'s, 'a, 'b>;
+    type SerializeStruct = Compound<'s, 'a, 'b>;
+    type SerializeStructVariant = Compound<'s, 'a, 'b>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        Ok(self.forge.bool(v)?)
+    }
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        Ok(self.forge.int(v as i32)?)
+    }
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        Ok(self.forge.int(v as i32)?)
+    }
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        Ok(self.forge.int(v)?)
+    }
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        Ok(self.forge.long(v)?)
+    }
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        Ok(self.forge.int(v as i32)?)
+    }
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        Ok(self.forge.int(v as i32)?)
+    }
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        Ok(self.forge.long(v as i64)?)
+    }
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        if v > i64::MAX as u64 {
+            return Err(ser::Error::custom(format!("{} does not fit in an atom:Long", v)));
+        }
+        Ok(self.forge.long(v as i64)?)
+    }
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        Ok(self.forge.float(v)?)
+    }
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        Ok(self.forge.double(v)?)
+    }
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        let mut buf = [0u8; 4];
+        Ok(self.forge.string(v.encode_utf8(&mut buf))?)
+    }
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        Ok(self.forge.string(v)?)
+    }
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        Ok(self.forge.chunk(v)?)
+    }
+    fn serialize_none(self) -> Result<(), Error> {
+        Ok(())
+    }
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result<(), Error> {
+        Ok(())
+    }
+    fn serialize_unit_struct(self, name: &'static str) -> Result<(), Error> {
+        let frame = self.object(name)?;
+        self.forge.pop(frame);
+        Ok(())
+    }
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str)
+            -> Result<(), Error> {
+        let urid = self.urid(variant);
+        Ok(self.forge.urid(urid)?)
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T)
+            -> Result<(), Error> {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32,
+            variant: &'static str, value: &T) -> Result<(), Error> {
+        let frame = self.variant(variant)?;
+        value.serialize(&mut *self)?;
+        self.forge.pop(frame);
+        Ok(())
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'s, 'a, 'b>, Error> {
+        let frame = self.forge.tuple()?;
+        Ok(Compound::new(self, frame, None))
+    }
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'s, 'a, 'b>, Error> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize)
+            -> Result<Compound<'s, 'a, 'b>, Error> {
+        self.serialize_seq(Some(len))
+    }
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, variant: &'static str,
+            _len: usize) -> Result<Compound<'s, 'a, 'b>, Error> {
+        let outer = self.variant(variant)?;
+        let frame = self.forge.tuple()?;
+        Ok(Compound::new(self, frame, Some(outer)))
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'s, 'a, 'b>, Error> {
+        let frame = self.forge.object(0, 0)?;
+        Ok(Compound::new(self, frame, None))
+    }
+    fn serialize_struct(self, name: &'static str, _len: usize)
+            -> Result<Compound<'s, 'a, 'b>, Error> {
+        let frame = self.object(name)?;
+        Ok(Compound::new(self, frame, None))
+    }
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, variant: &'static str,
+            _len: usize) -> Result<Compound<'s, 'a, 'b>, Error> {
+        let frame = self.object(variant)?;
+        Ok(Compound::new(self, frame, None))
+    }
+}
+
+impl<'s, 'a, 'b> ser::SerializeSeq for Compound<'s, 'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self)
+    }
+}
+
+impl<'s, 'a, 'b> ser::SerializeTuple for Compound<'s, 'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self)
+    }
+}
+
+impl<'s, 'a, 'b> ser::SerializeTupleStruct for Compound<'s, 'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self)
+    }
+}
+
+impl<'s, 'a, 'b> ser::SerializeTupleVariant for Compound<'s, 'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self)
+    }
+}
+
+impl<'s, 'a, 'b> ser::SerializeMap for Compound<'s, 'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        let name = key.serialize(KeySerializer)?;
+        self.key(&name)
+    }
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.value(value)
+    }
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self)
+    }
+}
+
+impl<'s, 'a, 'b> ser::SerializeStruct for Compound<'s, 'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T)
+            -> Result<(), Error> {
+        self.key(key)?;
+        self.value(value)
+    }
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self)
+    }
+}
+
+impl<'s, 'a, 'b> ser::SerializeStructVariant for Compound<'s, 'a, 'b> {
+    type Ok = ();
+    type Error = Error;
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T)
+            -> Result<(), Error> {
+        self.key(key)?;
+        self.value(value)
+    }
+    fn end(self) -> Result<(), Error> {
+        Compound::end(self)
+    }
+}
+
+/// Serializes map keys, which must be strings.
+struct KeySerializer;
+
+fn key_error<T>() -> Result<T, Error> {
+    Err(ser::Error::custom("atom object keys must be strings"))
+}
+
+impl Serializer for KeySerializer {
+    type Ok = ::std::string::String;
+    type Error = Error;
+    type SerializeSeq = ser::Impossible<Self::Ok, Error>;
+    type SerializeTuple = ser::Impossible<Self::Ok, Error>;
+    type SerializeTupleStruct = ser::Impossible<Self::Ok, Error>;
+    type SerializeTupleVariant = ser::Impossible<Self::Ok, Error>;
+    type SerializeMap = ser::Impossible<Self::Ok, Error>;
+    type SerializeStruct = ser::Impossible<Self::Ok, Error>;
+    type SerializeStructVariant = ser::Impossible<Self::Ok, Error>;
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Error> {
+        Ok(v.to_string())
+    }
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Error> {
+        Ok(v.to_string())
+    }
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str)
+            -> Result<Self::Ok, Error> {
+        Ok(variant.to_string())
+    }
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T)
+            -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<Self::Ok, Error> { key_error() }
+    fn serialize_i8(self, _v: i8) -> Result<Self::Ok, Error> { key_error() }
+    fn serialize_i16(self, _v: i16) -> Result<Self::Ok, Error> { key_error() }
+    fn serialize_i32(self, _v: i32) -> Result<Self::Ok, Error> { key_error() }
+    fn serialize_i64(self, _v: i64) -> Result<Self::Ok, Error> { key_error() }
+    fn serialize_u8(self, _v: u8) -> Result<Self::Ok, Error> { key_error() }
+    fn serialize_u16(self, _v: u16) -> Result<Self::Ok, Error> { key_error() }
+    fn serialize_u32(self, _v: u32) -> Result<Self::Ok, Error> { key_error() }
+    fn serialize_u64(self, _v: u64) -> Result<Self::Ok, Error> { key_error() }
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok, Error> { key_error() }
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok, Error> { key_error() }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, Error> { key_error() }
+    fn serialize_none(self) -> Result<Self::Ok, Error> { key_error() }
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<Self::Ok, Error> {
+        key_error()
+    }
+    fn serialize_unit(self) -> Result<Self::Ok, Error> { key_error() }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Error> {
+        key_error()
+    }
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, _index: u32,
+            _variant: &'static str, _value: &T) -> Result<Self::Ok, Error> {
+        key_error()
+    }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        key_error()
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        key_error()
+    }
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize)
+            -> Result<Self::SerializeTupleStruct, Error> {
+        key_error()
+    }
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str,
+            _len: usize) -> Result<Self::SerializeTupleVariant, Error> {
+        key_error()
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        key_error()
+    }
+    fn serialize_struct(self, _name: &'static str, _len: usize)
+            -> Result<Self::SerializeStruct, Error> {
+        key_error()
+    }
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, _variant: &'static str,
+            _len: usize) -> Result<Self::SerializeStructVariant, Error> {
+        key_error()
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use ::atom::testing;
+    use urid::tests::with_host;
+
+    use serde::Serialize;
+    use serde_json::{self, json};
+    use std::collections::BTreeMap;
+
+    const BASE: &'static str = "urn:test:serde#";
+
+    #[derive(Serialize)]
+    enum Mode {
+        Fast,
+        Slow(f64),
+        Range(i32, i32),
+        Custom { depth: u8 },
+    }
+
+    #[derive(Serialize)]
+    struct Patch {
+        gain: f32,
+        name: &'static str,
+        mode: Mode,
+        label: Option<&'static str>,
+        steps: Vec<i64>,
+        #[serde(rename = "urn:test:other#flag")]
+        flag: bool,
+    }
+
+    fn uri(map: &Map, name: &str) -> URID {
+        map.map(&format!("{}{}", BASE, name))
+    }
+
+    /// Forges `value` and calls `check` with the written atom.
+    fn forged<T, F>(map: &Map, value: &T, check: F)
+        where T: Serialize + ?Sized, F: FnOnce(&Header, &Types)
+    {
+        let types = Types::new(map);
+        let forged = testing::forged(&types, |forge| to_forge(forge, map, BASE, value).unwrap());
+        check(forged.atom(0), &types);
+    }
+
+    fn properties(object: &atom::Object) -> Vec<(URID, &Header)> {
+        object.iter().map(|(key, _, value)| (key, value)).collect()
+    }
+
+    #[test]
+    fn struct_to_object() {
+        let patch = Patch {
+            gain: 0.5,
+            name: "lead",
+            mode: Mode::Fast,
+            label: None,
+            steps: vec![1, -2],
+            flag: true,
+        };
+        with_host(|map, _| forged(map, &patch, |atom, types| {
+            let object = atom.downcast::<atom::Object>(types).unwrap();
+            assert_eq!(object.id(), 0);
+            assert_eq!(object.otype(), uri(map, "Patch"));
+            let props = properties(object);
+            // the label is omitted
+            let keys: Vec<URID> = props.iter().map(|p| p.0).collect();
+            assert_eq!(keys, vec![uri(map, "gain"), uri(map, "name"), uri(map, "mode"),
+                                  uri(map, "steps"), map.map("urn:test:other#flag")]);
+            assert_eq!(props[0].1.downcast::<atom::Float>(types).unwrap().value(), 0.5);
+            assert_eq!(props[1].1.downcast::<atom::String>(types).unwrap().as_str(), Ok("lead"));
+            assert_eq!(props[2].1.downcast::<atom::URID>(types).unwrap().value(), uri(map, "Fast"));
+            let steps: Vec<i64> = props[3].1.downcast::<atom::Tuple>(types).unwrap().iter()
+                .map(|a| a.downcast::<atom::Long>(types).unwrap().value())
+                .collect();
+            assert_eq!(steps, vec![1, -2]);
+            assert!(props[4].1.downcast::<atom::Bool>(types).unwrap().value());
+        }));
+    }
+
+    #[test]
+    fn sequences_to_tuples() {
+        with_host(|map, _| forged(map, &(1u8, "a", vec![2.5f64]), |atom, types| {
+            let items: Vec<&Header> = atom.downcast::<atom::Tuple>(types).unwrap().iter().collect();
+            assert_eq!(items.len(), 3);
+            assert_eq!(items[0].downcast::<atom::Int>(types).unwrap().value(), 1);
+            assert_eq!(items[1].downcast::<atom::String>(types).unwrap().as_str(), Ok("a"));
+            let inner: Vec<&Header> = items[2].downcast::<atom::Tuple>(types).unwrap().iter().collect();
+            assert_eq!(inner.len(), 1);
+            assert_eq!(inner[0].downcast::<atom::Double>(types).unwrap().value(), 2.5);
+        }));
+    }
+
+    #[test]
+    fn enum_variants() {
+        with_host(|map, _| {
+            let value = map.map(rdf::VALUE);
+            forged(map, &Mode::Slow(0.25), |atom, types| {
+                let object = atom.downcast::<atom::Object>(types).unwrap();
+                assert_eq!(object.otype(), uri(map, "Slow"));
+                let props = properties(object);
+                assert_eq!(props.len(), 1);
+                assert_eq!(props[0].0, value);
+                assert_eq!(props[0].1.downcast::<atom::Double>(types).unwrap().value(), 0.25);
+            });
+            forged(map, &Mode::Range(3, 4), |atom, types| {
+                let object = atom.downcast::<atom::Object>(types).unwrap();
+                assert_eq!(object.otype(), uri(map, "Range"));
+                let props = properties(object);
+                assert_eq!(props[0].0, value);
+                let items: Vec<i32> = props[0].1.downcast::<atom::Tuple>(types).unwrap().iter()
+                    .map(|a| a.downcast::<atom::Int>(types).unwrap().value())
+                    .collect();
+                assert_eq!(items, vec![3, 4]);
+            });
+            forged(map, &Mode::Custom { depth: 7 }, |atom, types| {
+                let object = atom.downcast::<atom::Object>(types).unwrap();
+                assert_eq!(object.otype(), uri(map, "Custom"));
+                let props = properties(object);
+                assert_eq!(props[0].0, uri(map, "depth"));
+                assert_eq!(props[0].1.downcast::<atom::Int>(types).unwrap().value(), 7);
+            });
+        });
+    }
+
+    #[test]
+    fn maps_to_objects() {
+        let mut value = BTreeMap::new();
+        value.insert("a", Some(1i32));
+        value.insert("b", None);
+        with_host(|map, _| forged(map, &value, |atom, types| {
+            let object = atom.downcast::<atom::Object>(types).unwrap();
+            assert_eq!(object.otype(), 0);
+            let props = properties(object);
+            assert_eq!(props.len(), 1);
+            assert_eq!(props[0].0, uri(map, "a"));
+        }));
+    }
+
+    #[test]
+    fn errors_rewind() {
+        let mut keys = BTreeMap::new();
+        keys.insert(1, 2);
+        with_host(|map, _| {
+            let types = Types::new(map);
+            let mut buf = testing::Buf::new();
+            let mut forge = Forge::new(&mut buf.0[.. 32], &types);
+            forge.int(1).unwrap();
+            match to_forge(&mut forge, map, BASE, &vec![u64::MAX]) {
+                Err(Error::Message(_)) => {},
+                res => panic!("unexpected {:?}", res),
+            }
+            match to_forge(&mut forge, map, BASE, &keys) {
+                Err(Error::Message(ref msg)) => assert_eq!(msg, "atom object keys must be strings"),
+                res => panic!("unexpected {:?}", res),
+            }
+            match to_forge(&mut forge, map, BASE, &vec![1i32, 2]) {
+                Err(Error::Forge(forge::Error::Overflow)) => {},
+                res => panic!("unexpected {:?}", res),
+            }
+            assert_eq!(forge.offset(), 16);
+        });
+    }
+
+    #[test]
+    fn tree_to_json() {
+        with_host(|map, unmap| {
+            let types = Types::new(map);
+            let forged = testing::forged(&types, |forge| {
+                let object = forge.object(uri(map, "obj"), uri(map, "Set")).unwrap();
+                forge.key(uri(map, "i")).unwrap();
+                forge.int(-3).unwrap();
+                forge.key(uri(map, "s")).unwrap();
+                forge.string("x").unwrap();
+                forge.key(uri(map, "u")).unwrap();
+                forge.urid(uri(map, "target")).unwrap();
+                forge.key(uri(map, "v")).unwrap();
+                forge.vector(&[1.5f32, 2.0]).unwrap();
+                forge.key(uri(map, "t")).unwrap();
+                let tuple = forge.tuple().unwrap();
+                forge.bool(true).unwrap();
+                forge.long(1 << 40).unwrap();
+                forge.pop(tuple);
+                forge.key(uri(map, "seq")).unwrap();
+                let seq = forge.sequence(0).unwrap();
+                forge.frame_time(4).unwrap();
+                forge.double(0.5).unwrap();
+                forge.pop(seq);
+                forge.key(uri(map, "c")).unwrap();
+                forge.chunk(&[1, 2]).unwrap();
+                forge.pop(object);
+            });
+            let json = serde_json::to_value(tree(forged.atom(0), &types, unmap)).unwrap();
+            assert_eq!(json, json!({
+                "@id": "urn:test:serde#obj",
+                "@type": "urn:test:serde#Set",
+                "urn:test:serde#i": -3,
+                "urn:test:serde#s": "x",
+                "urn:test:serde#u": "urn:test:serde#target",
+                "urn:test:serde#v": [1.5, 2.0],
+                "urn:test:serde#t": [true, 1u64 << 40],
+                "urn:test:serde#seq": [{ "frames": 4, "atom": 0.5 }],
+                "urn:test:serde#c": [1, 2],
+            }));
+        });
+    }
+
+    #[test]
+    fn round_trip() {
+        let patch = Patch {
+            gain: 1.0,
+            name: "pad",
+            mode: Mode::Slow(2.0),
+            label: Some("soft"),
+            steps: vec![],
+            flag: false,
+        };
+        with_host(|map, unmap| forged(map, &patch, |atom, types| {
+            let json = serde_json::to_value(tree(atom, types, unmap)).unwrap();
+            assert_eq!(json, json!({
+                "@type": "urn:test:serde#Patch",
+                "urn:test:serde#gain": 1.0,
+                "urn:test:serde#name": "pad",
+                "urn:test:serde#mode": {
+                    "@type": "urn:test:serde#Slow",
+                    (rdf::VALUE): 2.0,
+                },
+                "urn:test:serde#label": "soft",
+                "urn:test:serde#steps": [],
+                "urn:test:other#flag": false,
+            }));
+        }));
+    }
+}
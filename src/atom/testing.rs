@@ -0,0 +1,56 @@
+//! Helpers shared by the atom tests.
+
+use super::{read, Forge, Header, Types};
+
+use std::slice;
+
+/// A 64-bit aligned buffer holding the given 32-bit words.
+pub fn words(words: &[u32]) -> Vec<u64> {
+    let mut buf = vec![0u64; words.len().div_ceil(2)];
+    let bytes = unsafe { slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u8, buf.len() * 8) };
+    for (i, w) in words.iter().enumerate() {
+        bytes[i * 4 .. i * 4 + 4].copy_from_slice(&w.to_ne_bytes());
+    }
+    buf
+}
+
+/// A 64-bit aligned buffer to forge atoms into.
+#[repr(align(8))]
+pub struct Buf(pub [u8; 1024]);
+
+impl Buf {
+    pub fn new() -> Buf {
+        Buf([0; 1024])
+    }
+    /// A buffer filled with `byte`, to check the padding written by forges.
+    pub fn filled(byte: u8) -> Buf {
+        Buf([byte; 1024])
+    }
+}
+
+/// Atoms written by `forged`.
+pub struct Forged {
+    buf: Buf,
+    len: usize,
+}
+
+impl Forged {
+    pub fn bytes(&self) -> &[u8] {
+        &self.buf.0[.. self.len]
+    }
+    /// The atom written at `offset`.
+    pub fn atom(&self, offset: usize) -> &Header {
+        read::atom(&self.bytes()[offset ..]).unwrap()
+    }
+}
+
+/// Forges atoms with `f` into a new buffer.
+pub fn forged<F: FnOnce(&mut Forge)>(types: &Types, f: F) -> Forged {
+    let mut buf = Buf::new();
+    let len = {
+        let mut forge = Forge::new(&mut buf.0, types);
+        f(&mut forge);
+        forge.offset()
+    };
+    Forged { buf: buf, len: len }
+}
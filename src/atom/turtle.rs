@@ -0,0 +1,681 @@
+//! Conversion of atoms to and from Turtle, like sratom.
+//!
+//! Numbers and booleans are literals with their xsd datatype, strings are
+//! plain literals and URIDs are IRIs. Objects are nodes, with the object id
+//! as subject and the object type as `rdf:type`. Tuples, vectors and
+//! sequences are nodes of their atom type, with the elements in an
+//! `rdf:value` list, sequence events being nodes with an `atom:frameTime` or
+//! `atom:beatTime` and an `rdf:value`.
+
+use ::atom::{self, class, forge, prop, read, Forge, Header, Time, TimeUnit, Types, Value};
+use turtle::{self, rdf, xsd, Document, Graph, Node, Object, Term};
+use urid::{Map, Unmap, URID};
+
+use std::error;
+use std::fmt;
+use std::path;
+
+/// Prefix of the language URIs of literals.
+const LANG_PREFIX: &'static str = "http://lexvo.org/id/iso639-1/";
+
+/// Nesting depth above which atoms are not converted.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug)]
+pub enum Error {
+    Parse(turtle::ParseError),
+    Forge(forge::Error),
+    Read(read::Error),
+    /// A URID which the host cannot unmap.
+    Unmapped(URID),
+    /// The Turtle does not describe a valid atom.
+    Invalid(::std::string::String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Parse(ref err) => err.fmt(f),
+            Error::Forge(ref err) => err.fmt(f),
+            Error::Read(ref err) => err.fmt(f),
+            Error::Unmapped(urid) => write!(f, "URID {} cannot be unmapped", urid),
+            Error::Invalid(ref msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<turtle::ParseError> for Error {
+    fn from(err: turtle::ParseError) -> Error {
+        Error::Parse(err)
+    }
+}
+
+impl From<forge::Error> for Error {
+    fn from(err: forge::Error) -> Error {
+        Error::Forge(err)
+    }
+}
+
+impl From<read::Error> for Error {
+    fn from(err: read::Error) -> Error {
+        Error::Read(err)
+    }
+}
+
+fn invalid<T>(msg: ::std::string::String) -> Result<T, Error> {
+    Err(Error::Invalid(msg))
+}
+
+/// A document with the prefixes used by atoms.
+pub fn document() -> Document {
+    let mut doc = Document::new();
+    doc.prefix("atom", "http://lv2plug.in/ns/ext/atom#")
+        .prefix("rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#")
+        .prefix("xsd", "http://www.w3.org/2001/XMLSchema#");
+    doc
+}
+
+/// Writes `atom` as the object of the statement `<subject> <predicate>`.
+pub fn to_turtle(atom: &Header, types: &Types, unmap: &Unmap, subject: &str, predicate: &str)
+        -> Result<::std::string::String, Error> {
+    let object = to_object(atom, types, unmap)?;
+    let mut doc = document();
+    doc.push(Node::new(Term::iri(subject)).with(predicate, object));
+    Ok(doc.to_string())
+}
+
+/// The RDF object describing `atom`.
+pub fn to_object(atom: &Header, types: &Types, unmap: &Unmap) -> Result<Object, Error> {
+    Writer { types: types, unmap: unmap }.object(atom, 0)
+}
+
+struct Writer<'a> {
+    types: &'a Types,
+    unmap: &'a Unmap<'a>,
+}
+
+/// Lexical form of a floating point number in xsd.
+fn float_repr<F: Into<f64> + fmt::Debug + Copy>(value: F) -> ::std::string::String {
+    let v: f64 = value.into();
+    if v.is_nan() {
+        "NaN".to_string()
+    }
+    else if v.is_infinite() {
+        if v > 0.0 { "INF".to_string() } else { "-INF".to_string() }
+    }
+    else {
+        format!("{:?}", value)
+    }
+}
+
+impl<'a> Writer<'a> {
+    fn uri(&self, urid: URID) -> Result<&'a str, Error> {
+        self.unmap.unmap(urid).ok_or(Error::Unmapped(urid))
+    }
+
+    fn typed_node(&self, otype: &str, value: Object) -> Object {
+        Node::blank().with(rdf::TYPE, Term::iri(otype)).with(rdf::VALUE, value).into()
+    }
+
+    fn object(&self, atom: &Header, depth: usize) -> Result<Object, Error> {
+        if depth > MAX_DEPTH {
+            return invalid("atoms are nested too deeply".to_string());
+        }
+        let types = self.types;
+        let term = if let Some(v) = atom.downcast::<atom::Int>(types) {
+            Term::typed(v.value().to_string(), xsd::INT)
+        }
+        else if let Some(v) = atom.downcast::<atom::Long>(types) {
+            Term::typed(v.value().to_string(), xsd::LONG)
+        }
+        else if let Some(v) = atom.downcast::<atom::Float>(types) {
+            Term::typed(float_repr(v.value()), xsd::FLOAT)
+        }
+        else if let Some(v) = atom.downcast::<atom::Double>(types) {
+            Term::typed(float_repr(v.value()), xsd::DOUBLE)
+        }
+        else if let Some(v) = atom.downcast::<atom::Bool>(types) {
+            Term::boolean(v.value())
+        }
+        else if let Some(v) = atom.downcast::<atom::URID>(types) {
+            Term::iri(self.uri(v.value())?)
+        }
+        else if let Some(v) = atom.downcast::<atom::String>(types) {
+            Term::string(v.as_str()?)
+        }
+        else if let Some(v) = atom.downcast::<atom::URI>(types) {
+            Term::typed(v.as_str()?, xsd::ANYURI)
+        }
+        else if let Some(v) = atom.downcast::<atom::Path>(types) {
+            let path = v.as_path()?.to_string_lossy().into_owned();
+            Term::typed(path, class::PATH)
+        }
+        else if let Some(v) = atom.downcast::<atom::Literal>(types) {
+            let value = v.as_str()?.to_string();
+            let datatype = match v.datatype() {
+                0 => None,
+                dt => Some(self.uri(dt)?.to_string()),
+            };
+            let lang = match v.lang() {
+                0 => None,
+                lang => {
+                    let uri = self.uri(lang)?;
+                    let tag = uri.strip_prefix(LANG_PREFIX)
+                        .unwrap_or_else(|| uri.rsplit(['/', '#']).next().unwrap_or(uri));
+                    Some(tag.to_string())
+                },
+            };
+            Term::Literal { value: value, datatype: datatype, lang: lang }
+        }
+        else if let Some(v) = atom.downcast::<atom::Chunk>(types) {
+            Term::typed(base64_encode(v.as_bytes()), xsd::BASE64BINARY)
+        }
+        else if let Some(v) = atom.downcast::<atom::Tuple>(types) {
+            let items = v.iter().map(|elem| self.object(elem, depth + 1))
+                .collect::<Result<Vec<_>, _>>()?;
+            return Ok(self.typed_node(class::TUPLE, Object::List(items)));
+        }
+        else if let Some(v) = atom.downcast::<atom::Vector>(types) {
+            return self.vector(atom, v);
+        }
+        else if atom.downcast::<atom::Sound>(types).is_some() {
+            // a sound is laid out as a vector
+            let v = unsafe { &*(atom as *const Header as *const atom::Vector) };
+            return self.vector(atom, v);
+        }
+        else if let Some(v) = atom.downcast::<atom::Object>(types) {
+            let mut node = match v.id() {
+                0 => Node::blank(),
+                id => Node::new(Term::iri(self.uri(id)?)),
+            };
+            if v.otype() != 0 {
+                node.add(rdf::TYPE, Term::iri(self.uri(v.otype())?));
+            }
+            for (key, _, value) in v.iter() {
+                let key = self.uri(key)?;
+                node.add(key, self.object(value, depth + 1)?);
+            }
+            return Ok(node.into());
+        }
+        else if let Some(v) = atom.downcast::<atom::Sequence>(types) {
+            if v.time_unit(types).is_none() {
+                return invalid("sequence in an unknown time unit".to_string());
+            }
+            let mut events = Vec::new();
+            for (time, ev) in v.timed(types) {
+                let time = match time {
+                    Time::Frames(frames) => (prop::FRAMETIME, Term::typed(frames.to_string(), xsd::LONG)),
+                    Time::Beats(beats) => (prop::BEATTIME, Term::typed(float_repr(beats), xsd::DOUBLE)),
+                };
+                events.push(Node::blank()
+                    .with(time.0, time.1)
+                    .with(rdf::VALUE, self.object(ev.atom(), depth + 1)?)
+                    .into());
+            }
+            return Ok(self.typed_node(class::SEQUENCE, Object::List(events)));
+        }
+        else {
+            let body = base64_encode(unsafe { atom.contents() });
+            let value = Term::typed(body, xsd::BASE64BINARY);
+            return Ok(self.typed_node(self.uri(atom.type_urid())?, value.into()));
+        };
+        Ok(term.into())
+    }
+
+    fn vector(&self, atom: &Header, v: &atom::Vector) -> Result<Object, Error> {
+        let types = self.types;
+        let otype = if atom.type_urid() == types.sound { class::SOUND } else { class::VECTOR };
+        let (child_type, items): (&str, Vec<Object>) =
+            if let Some(items) = v.as_slice::<i32>(types) {
+                (class::INT, items.iter().map(|i| Term::typed(i.to_string(), xsd::INT).into()).collect())
+            }
+            else if let Some(items) = v.as_slice::<i64>(types) {
+                (class::LONG, items.iter().map(|i| Term::typed(i.to_string(), xsd::LONG).into()).collect())
+            }
+            else if let Some(items) = v.as_slice::<f32>(types) {
+                (class::FLOAT, items.iter()
+                    .map(|&f| Term::typed(float_repr(f), xsd::FLOAT).into()).collect())
+            }
+            else if let Some(items) = v.as_slice::<f64>(types) {
+                (class::DOUBLE, items.iter()
+                    .map(|&f| Term::typed(float_repr(f), xsd::DOUBLE).into()).collect())
+            }
+            else {
+                return invalid(format!("vector of unsupported child type {}", v.child_type()));
+            };
+        Ok(Node::blank()
+            .with(rdf::TYPE, Term::iri(otype))
+            .with(prop::CHILDTYPE, Term::iri(child_type))
+            .with(rdf::VALUE, Object::List(items))
+            .into())
+    }
+}
+
+/// Parses `text` and forges the object of the statement `<subject> <predicate>`.
+pub fn from_turtle(text: &str, subject: &str, predicate: &str, forge: &mut Forge, map: &Map)
+        -> Result<(), Error> {
+    let graph = Graph::parse(text)?;
+    let subject = Term::iri(subject);
+    let object = graph.object(&subject, predicate)
+        .ok_or_else(|| Error::Invalid(format!("no statement <{}> <{}>", subject.as_iri().unwrap(), predicate)))?;
+    from_term(&graph, object, forge, map)
+}
+
+/// Forges the atom described by `term` in `graph`.
+/// On error, the forge is left as it was before the call.
+pub fn from_term(graph: &Graph, term: &Term, forge: &mut Forge, map: &Map) -> Result<(), Error> {
+    let mark = forge.mark();
+    let res = Reader { graph: graph, map: map }.atom(term, forge, 0);
+    if res.is_err() {
+        forge.rewind(mark);
+    }
+    res
+}
+
+struct Reader<'a> {
+    graph: &'a Graph,
+    map: &'a Map<'a>,
+}
+
+fn number<T: ::std::str::FromStr>(value: &str, datatype: &str) -> Result<T, Error> {
+    let parsed = match value {
+        "INF" => "inf",
+        "-INF" => "-inf",
+        v => v,
+    };
+    parsed.parse().or_else(|_| invalid(format!("invalid {} \"{}\"", datatype, value)))
+}
+
+impl<'a> Reader<'a> {
+    fn list(&self, node: &Term) -> Result<Vec<&'a Term>, Error> {
+        self.graph.object(node, rdf::VALUE)
+            .and_then(|head| self.graph.list(head))
+            .ok_or_else(|| Error::Invalid("missing rdf:value list".to_string()))
+    }
+
+    fn literal(&self, term: &Term, forge: &mut Forge) -> Result<(), Error> {
+        let (value, datatype, lang) = match *term {
+            Term::Literal { ref value, ref datatype, ref lang } => (value, datatype, lang),
+            _ => unreachable!(),
+        };
+        if let Some(ref lang) = *lang {
+            let lang = self.map.map(&format!("{}{}", LANG_PREFIX, lang));
+            return Ok(forge.literal(value, 0, lang)?);
+        }
+        let datatype = match *datatype {
+            Some(ref dt) => dt.as_str(),
+            None => return Ok(forge.string(value)?),
+        };
+        match datatype {
+            xsd::INT => forge.int(number(value, datatype)?)?,
+            xsd::LONG | xsd::INTEGER => forge.long(number(value, datatype)?)?,
+            xsd::FLOAT => forge.float(number(value, datatype)?)?,
+            xsd::DOUBLE | xsd::DECIMAL => forge.double(number(value, datatype)?)?,
+            xsd::BOOLEAN => match value.as_str() {
+                "true" | "1" => forge.bool(true)?,
+                "false" | "0" => forge.bool(false)?,
+                _ => return invalid(format!("invalid boolean \"{}\"", value)),
+            },
+            xsd::STRING => forge.string(value)?,
+            xsd::ANYURI => forge.uri(value)?,
+            xsd::BASE64BINARY => forge.chunk(&base64_decode(value)?)?,
+            class::PATH => forge.path(path::Path::new(value))?,
+            dt => {
+                let dt = self.map.map(dt);
+                forge.literal(value, dt, 0)?
+            },
+        }
+        Ok(())
+    }
+
+    fn atom(&self, term: &Term, forge: &mut Forge, depth: usize) -> Result<(), Error> {
+        if depth > MAX_DEPTH {
+            return invalid("nodes are nested too deeply".to_string());
+        }
+        if let Term::Literal { .. } = *term {
+            return self.literal(term, forge);
+        }
+        let described = self.graph.triples.iter().any(|t| t.subject == *term);
+        if let (false, Some(iri)) = (described, term.as_iri()) {
+            let urid = self.map.map(iri);
+            return Ok(forge.urid(urid)?);
+        }
+
+        let otype = self.graph.object(term, rdf::TYPE).and_then(|t| t.as_iri());
+        match otype {
+            Some(class::TUPLE) => {
+                let frame = forge.tuple()?;
+                for item in self.list(term)? {
+                    self.atom(item, forge, depth + 1)?;
+                }
+                forge.pop(frame);
+                Ok(())
+            },
+            Some(class::VECTOR) | Some(class::SOUND) => self.vector(term, otype.unwrap(), forge),
+            Some(class::SEQUENCE) => self.sequence(term, forge, depth),
+            _ => {
+                if let Some(raw) = self.raw(term, otype)? {
+                    return Ok(forge.atom(self.map.map(otype.unwrap()), &raw)?);
+                }
+                let id = term.as_iri().map_or(0, |iri| self.map.map(iri));
+                let otype = otype.map_or(0, |t| self.map.map(t));
+                let frame = forge.object(id, otype)?;
+                for triple in self.graph.triples.iter().filter(|t| t.subject == *term) {
+                    if triple.predicate == rdf::TYPE {
+                        continue;
+                    }
+                    let key = self.map.map(&triple.predicate);
+                    forge.key(key)?;
+                    self.atom(&triple.object, forge, depth + 1)?;
+                }
+                forge.pop(frame);
+                Ok(())
+            },
+        }
+    }
+
+    /// The body of an atom of a type unknown to atoms, written as a blank
+    /// node with its type and a base64 `rdf:value` only.
+    fn raw(&self, term: &Term, otype: Option<&str>) -> Result<Option<Vec<u8>>, Error> {
+        if otype.is_none() || term.as_iri().is_some() {
+            return Ok(None);
+        }
+        let count = self.graph.triples.iter().filter(|t| t.subject == *term).count();
+        match self.graph.object(term, rdf::VALUE) {
+            Some(&Term::Literal { ref value, datatype: Some(ref dt), .. })
+                    if count == 2 && dt == xsd::BASE64BINARY => base64_decode(value).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn vector(&self, term: &Term, otype: &str, forge: &mut Forge) -> Result<(), Error> {
+        let child_type = self.graph.object(term, prop::CHILDTYPE).and_then(|t| t.as_iri())
+            .ok_or_else(|| Error::Invalid("vector without atom:childType".to_string()))?;
+        let items = self.list(term)?;
+        let values = items.iter()
+            .map(|item| item.as_str()
+                .ok_or_else(|| Error::Invalid("vector element is not a literal".to_string())))
+            .collect::<Result<Vec<_>, _>>()?;
+        if otype == class::SOUND {
+            let samples = values.iter().map(|v| number(v, class::FLOAT))
+                .collect::<Result<Vec<f32>, _>>()?;
+            return Ok(forge.sound(&samples)?);
+        }
+        match child_type {
+            class::INT => forge.vector(&values.iter().map(|v| number(v, child_type))
+                .collect::<Result<Vec<i32>, _>>()?)?,
+            class::LONG => forge.vector(&values.iter().map(|v| number(v, child_type))
+                .collect::<Result<Vec<i64>, _>>()?)?,
+            class::FLOAT => forge.vector(&values.iter().map(|v| number(v, child_type))
+                .collect::<Result<Vec<f32>, _>>()?)?,
+            class::DOUBLE => forge.vector(&values.iter().map(|v| number(v, child_type))
+                .collect::<Result<Vec<f64>, _>>()?)?,
+            _ => return invalid(format!("vector of unsupported child type <{}>", child_type)),
+        }
+        Ok(())
+    }
+
+    fn sequence(&self, term: &Term, forge: &mut Forge, depth: usize) -> Result<(), Error> {
+        let events = self.list(term)?;
+        let unit = match events.first() {
+            Some(ev) if self.graph.object(ev, prop::BEATTIME).is_some() => TimeUnit::Beats,
+            _ => TimeUnit::Frames,
+        };
+        let unit_urid = match unit {
+            TimeUnit::Frames => 0,
+            TimeUnit::Beats => forge.types().beat_time,
+        };
+        let frame = forge.sequence(unit_urid)?;
+        for ev in events {
+            match unit {
+                TimeUnit::Frames => {
+                    let frames = self.graph.object(ev, prop::FRAMETIME).and_then(|t| t.as_i64())
+                        .ok_or_else(|| Error::Invalid("event without atom:frameTime".to_string()))?;
+                    forge.frame_time(frames)?;
+                },
+                TimeUnit::Beats => {
+                    let beats = self.graph.object(ev, prop::BEATTIME).and_then(|t| t.as_f64())
+                        .ok_or_else(|| Error::Invalid("event without atom:beatTime".to_string()))?;
+                    forge.beat_time(beats)?;
+                },
+            }
+            let value = self.graph.object(ev, rdf::VALUE)
+                .ok_or_else(|| Error::Invalid("event without rdf:value".to_string()))?;
+            self.atom(value, forge, depth + 1)?;
+        }
+        forge.pop(frame);
+        Ok(())
+    }
+}
+
+const BASE64: &'static [u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> ::std::string::String {
+    let mut out = ::std::string::String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0 .. 4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char);
+            }
+            else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn base64_decode(text: &str) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let mut n = 0u32;
+    let mut bits = 0;
+    for c in text.bytes().filter(|c| !c.is_ascii_whitespace()).take_while(|&c| c != b'=') {
+        let v = match BASE64.iter().position(|&b| b == c) {
+            Some(v) => v as u32,
+            None => return invalid(format!("invalid base64 character '{}'", c as char)),
+        };
+        n = n << 6 | v;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((n >> bits) as u8);
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::atom::testing;
+    use urid::tests::with_host;
+
+    const S: &'static str = "urn:test:turtle#s";
+    const P: &'static str = "urn:test:turtle#p";
+
+    /// Writes the atom forged by `f` as Turtle, parses it back and checks
+    /// that the same atom is forged. Returns the statement, without the prefixes.
+    fn turtle<F: FnOnce(&mut Forge, &Map)>(f: F) -> ::std::string::String {
+        with_host(|map, unmap| {
+            let types = Types::new(map);
+            let forged = testing::forged(&types, |forge| f(forge, map));
+            let text = to_turtle(forged.atom(0), &types, unmap, S, P).unwrap();
+
+            let parsed = testing::forged(&types, |forge| {
+                from_turtle(&text, S, P, forge, map).unwrap();
+            });
+            assert_eq!(parsed.bytes(), forged.bytes(), "{}", text);
+
+            let start = text.find("\n<").unwrap() + 1;
+            text[start ..].to_string()
+        })
+    }
+
+    fn statement(object: &str) -> ::std::string::String {
+        format!("<{}>\n\t<{}> {} .\n", S, P, object)
+    }
+
+    #[test]
+    fn prefixes() {
+        with_host(|map, unmap| {
+            let types = Types::new(map);
+            let forged = testing::forged(&types, |forge| {
+                forge.path(path::Path::new("/a")).unwrap();
+            });
+            let text = to_turtle(forged.atom(0), &types, unmap, S, P).unwrap();
+            assert_eq!(text, format!("@prefix atom: <http://lv2plug.in/ns/ext/atom#> .\n\
+                                      \n{}", statement("\"/a\"^^atom:Path")));
+        });
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(turtle(|f, _| f.int(-3).unwrap()), statement("\"-3\"^^xsd:int"));
+        assert_eq!(turtle(|f, _| f.long(1 << 40).unwrap()), statement("\"1099511627776\"^^xsd:long"));
+        assert_eq!(turtle(|f, _| f.float(0.5).unwrap()), statement("\"0.5\"^^xsd:float"));
+        assert_eq!(turtle(|f, _| f.float(f32::INFINITY).unwrap()),
+                   statement("\"INF\"^^xsd:float"));
+        assert_eq!(turtle(|f, _| f.double(2.5).unwrap()), statement("\"2.5\"^^xsd:double"));
+        assert_eq!(turtle(|f, _| f.double(1e300).unwrap()), statement("1e300"));
+        assert_eq!(turtle(|f, _| f.bool(true).unwrap()), statement("true"));
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(turtle(|f, _| f.string("say \"hi\"").unwrap()), statement("\"say \\\"hi\\\"\""));
+        assert_eq!(turtle(|f, _| f.uri("urn:x").unwrap()), statement("\"urn:x\"^^xsd:anyURI"));
+        assert_eq!(turtle(|f, _| f.path(path::Path::new("/tmp/a")).unwrap()),
+                   statement("\"/tmp/a\"^^atom:Path"));
+        assert_eq!(turtle(|f, _| f.chunk(&[1, 2, 3, 4]).unwrap()),
+                   statement("\"AQIDBA==\"^^xsd:base64Binary"));
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(turtle(|f, m| {
+            let lang = m.map("http://lexvo.org/id/iso639-1/fr");
+            f.literal("chat", 0, lang).unwrap()
+        }), statement("\"chat\"@fr"));
+        assert_eq!(turtle(|f, m| {
+            let dt = m.map("urn:test:turtle#dt");
+            f.literal("v", dt, 0).unwrap()
+        }), statement("\"v\"^^<urn:test:turtle#dt>"));
+    }
+
+    #[test]
+    fn urids() {
+        assert_eq!(turtle(|f, m| f.urid(m.map("urn:test:turtle#x")).unwrap()),
+                   statement("<urn:test:turtle#x>"));
+    }
+
+    #[test]
+    fn tuples() {
+        assert_eq!(turtle(|f, _| {
+            let frame = f.tuple().unwrap();
+            f.int(1).unwrap();
+            f.string("x").unwrap();
+            f.pop(frame);
+        }), statement("[\n\
+                       \t\ta atom:Tuple ;\n\
+                       \t\trdf:value ( \"1\"^^xsd:int \"x\" )\n\
+                       \t]"));
+    }
+
+    #[test]
+    fn vectors() {
+        assert_eq!(turtle(|f, _| f.vector(&[1i64, 2]).unwrap()),
+                   statement("[\n\
+                              \t\ta atom:Vector ;\n\
+                              \t\tatom:childType atom:Long ;\n\
+                              \t\trdf:value ( \"1\"^^xsd:long \"2\"^^xsd:long )\n\
+                              \t]"));
+        assert_eq!(turtle(|f, _| f.sound(&[0.25]).unwrap()),
+                   statement("[\n\
+                              \t\ta atom:Sound ;\n\
+                              \t\tatom:childType atom:Float ;\n\
+                              \t\trdf:value ( \"0.25\"^^xsd:float )\n\
+                              \t]"));
+    }
+
+    #[test]
+    fn objects() {
+        assert_eq!(turtle(|f, m| {
+            let frame = f.object(0, m.map("urn:test:turtle#T")).unwrap();
+            f.key(m.map("urn:test:turtle#k")).unwrap();
+            f.int(1).unwrap();
+            f.pop(frame);
+        }), statement("[\n\
+                       \t\ta <urn:test:turtle#T> ;\n\
+                       \t\t<urn:test:turtle#k> \"1\"^^xsd:int\n\
+                       \t]"));
+        assert_eq!(turtle(|f, m| {
+            let frame = f.object(m.map("urn:test:turtle#o"), 0).unwrap();
+            f.key(m.map("urn:test:turtle#k")).unwrap();
+            f.bool(false).unwrap();
+            f.pop(frame);
+        }), format!("{}\n<urn:test:turtle#o>\n\t<urn:test:turtle#k> false .\n",
+                    statement("<urn:test:turtle#o>")));
+    }
+
+    #[test]
+    fn sequences() {
+        assert_eq!(turtle(|f, _| {
+            let frame = f.sequence(0).unwrap();
+            f.frame_time(3).unwrap();
+            f.int(1).unwrap();
+            f.pop(frame);
+        }), statement("[\n\
+                       \t\ta atom:Sequence ;\n\
+                       \t\trdf:value ( [\n\
+                       \t\t\tatom:frameTime \"3\"^^xsd:long ;\n\
+                       \t\t\trdf:value \"1\"^^xsd:int\n\
+                       \t\t] )\n\
+                       \t]"));
+        assert_eq!(turtle(|f, _| {
+            let beats = f.types().beat_time;
+            let frame = f.sequence(beats).unwrap();
+            f.beat_time(1.5).unwrap();
+            f.bool(true).unwrap();
+            f.pop(frame);
+        }), statement("[\n\
+                       \t\ta atom:Sequence ;\n\
+                       \t\trdf:value ( [\n\
+                       \t\t\tatom:beatTime \"1.5\"^^xsd:double ;\n\
+                       \t\t\trdf:value true\n\
+                       \t\t] )\n\
+                       \t]"));
+    }
+
+    #[test]
+    fn unknown_types() {
+        assert_eq!(turtle(|f, m| f.atom(m.map("urn:test:turtle#Raw"), &[0xff, 0]).unwrap()),
+                   statement("[\n\
+                              \t\ta <urn:test:turtle#Raw> ;\n\
+                              \t\trdf:value \"/wA=\"^^xsd:base64Binary\n\
+                              \t]"));
+    }
+
+    #[test]
+    fn invalid_turtle() {
+        with_host(|map, _| {
+            let types = Types::new(map);
+            let mut buf = testing::Buf::new();
+            let mut forge = Forge::new(&mut buf.0, &types);
+            let text = format!("<{}> <{}> \"x\"^^<http://www.w3.org/2001/XMLSchema#int> .", S, P);
+            match from_turtle(&text, S, P, &mut forge, map) {
+                Err(Error::Invalid(ref msg)) => assert_eq!(msg, "invalid http://www.w3.org/2001/XMLSchema#int \"x\""),
+                res => panic!("unexpected {:?}", res),
+            }
+            match from_turtle("<a> <b> .", S, P, &mut forge, map) {
+                Err(Error::Parse(_)) => {},
+                res => panic!("unexpected {:?}", res),
+            }
+            assert_eq!(forge.offset(), 0);
+        });
+    }
+}
@@ -0,0 +1,154 @@
+use ::atom::{self, class, prop, Atom};
+use urid::{self, URID};
+
+/// URIDs of the atom types, mapped once with `Types::new`.
+#[derive(Copy, Clone, Debug)]
+pub struct Types {
+    pub blank: URID,
+    pub bool: URID,
+    pub chunk: URID,
+    pub double: URID,
+    pub float: URID,
+    pub int: URID,
+    pub literal: URID,
+    pub long: URID,
+    pub object: URID,
+    pub path: URID,
+    pub property: URID,
+    pub resource: URID,
+    pub sequence: URID,
+    pub sound: URID,
+    pub string: URID,
+    pub tuple: URID,
+    pub uri: URID,
+    pub urid: URID,
+    pub vector: URID,
+    pub frame_time: URID,
+    pub beat_time: URID,
+}
+
+impl Types {
+    pub fn new(map: &urid::Map) -> Types {
+        Types {
+            blank: map.map(class::BLANK),
+            bool: map.map(class::BOOL),
+            chunk: map.map(class::CHUNK),
+            double: map.map(class::DOUBLE),
+            float: map.map(class::FLOAT),
+            int: map.map(class::INT),
+            literal: map.map(class::LITERAL),
+            long: map.map(class::LONG),
+            object: map.map(class::OBJECT),
+            path: map.map(class::PATH),
+            property: map.map(class::PROPERTY),
+            resource: map.map(class::RESOURCE),
+            sequence: map.map(class::SEQUENCE),
+            sound: map.map(class::SOUND),
+            string: map.map(class::STRING),
+            tuple: map.map(class::TUPLE),
+            uri: map.map(class::URI),
+            urid: map.map(class::URID),
+            vector: map.map(class::VECTOR),
+            frame_time: map.map(prop::FRAMETIME),
+            beat_time: map.map(prop::BEATTIME),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Types {
+    /// Distinct URIDs for all types, for tests without a host map.
+    pub(crate) fn test() -> Types {
+        Types {
+            blank: 1,
+            bool: 2,
+            chunk: 3,
+            double: 4,
+            float: 5,
+            int: 6,
+            literal: 7,
+            long: 8,
+            object: 9,
+            path: 10,
+            property: 11,
+            resource: 12,
+            sequence: 13,
+            sound: 14,
+            string: 15,
+            tuple: 16,
+            uri: 17,
+            urid: 18,
+            vector: 19,
+            frame_time: 20,
+            beat_time: 21,
+        }
+    }
+}
+
+/// Plain values stored in atom vectors.
+pub unsafe trait Scalar: Copy {
+    /// The atom type of the vector elements.
+    fn child_type(types: &Types) -> URID;
+}
+
+unsafe impl Scalar for i32 {
+    fn child_type(types: &Types) -> URID {
+        types.int
+    }
+}
+
+unsafe impl Scalar for i64 {
+    fn child_type(types: &Types) -> URID {
+        types.long
+    }
+}
+
+unsafe impl Scalar for f32 {
+    fn child_type(types: &Types) -> URID {
+        types.float
+    }
+}
+
+unsafe impl Scalar for f64 {
+    fn child_type(types: &Types) -> URID {
+        types.double
+    }
+}
+
+/// Atom structs which can be read in place from a header of their type.
+pub unsafe trait AtomType: Atom + Sized {
+    /// Whether `type_urid` is a type of atom laid out as `Self`.
+    fn is_type(type_urid: URID, types: &Types) -> bool;
+}
+
+macro_rules! atom_type {
+    ($($atom:ident => $($field:ident)|+;)*) => {
+        $(
+            unsafe impl AtomType for atom::$atom {
+                fn is_type(type_urid: URID, types: &Types) -> bool {
+                    $(type_urid == types.$field)||+
+                }
+            }
+        )*
+    }
+}
+
+atom_type! {
+    Int => int;
+    Long => long;
+    Float => float;
+    Double => double;
+    Bool => bool;
+    URID => urid;
+    String => string;
+    Literal => literal;
+    URI => uri;
+    Path => path;
+    Chunk => chunk;
+    Tuple => tuple;
+    Vector => vector;
+    Sound => sound;
+    Property => property;
+    Object => object | resource | blank;
+    Sequence => sequence;
+}
@@ -0,0 +1,43 @@
+//! The atom URIs, also available without the `atom` feature.
+
+pub const URI: &'static str = "http://lv2plug.in/ns/ext/atom";
+
+pub mod class {
+    pub const ATOM: &'static str = "http://lv2plug.in/ns/ext/atom#Atom";
+    pub const ATOMPORT: &'static str = "http://lv2plug.in/ns/ext/atom#AtomPort";
+    pub const BLANK: &'static str = "http://lv2plug.in/ns/ext/atom#Blank";
+    pub const BOOL: &'static str = "http://lv2plug.in/ns/ext/atom#Bool";
+    pub const CHUNK: &'static str = "http://lv2plug.in/ns/ext/atom#Chunk";
+    pub const DOUBLE: &'static str = "http://lv2plug.in/ns/ext/atom#Double";
+    pub const EVENT: &'static str = "http://lv2plug.in/ns/ext/atom#Event";
+    pub const FLOAT: &'static str = "http://lv2plug.in/ns/ext/atom#Float";
+    pub const INT: &'static str = "http://lv2plug.in/ns/ext/atom#Int";
+    pub const LITERAL: &'static str = "http://lv2plug.in/ns/ext/atom#Literal";
+    pub const LONG: &'static str = "http://lv2plug.in/ns/ext/atom#Long";
+    pub const NUMBER: &'static str = "http://lv2plug.in/ns/ext/atom#Number";
+    pub const OBJECT: &'static str = "http://lv2plug.in/ns/ext/atom#Object";
+    pub const PATH: &'static str = "http://lv2plug.in/ns/ext/atom#Path";
+    pub const PROPERTY: &'static str = "http://lv2plug.in/ns/ext/atom#Property";
+    pub const RESOURCE: &'static str = "http://lv2plug.in/ns/ext/atom#Resource";
+    pub const SEQUENCE: &'static str = "http://lv2plug.in/ns/ext/atom#Sequence";
+    pub const SOUND: &'static str = "http://lv2plug.in/ns/ext/atom#Sound";
+    pub const STRING: &'static str = "http://lv2plug.in/ns/ext/atom#String";
+    pub const TUPLE: &'static str = "http://lv2plug.in/ns/ext/atom#Tuple";
+    pub const URI: &'static str = "http://lv2plug.in/ns/ext/atom#URI";
+    pub const URID: &'static str = "http://lv2plug.in/ns/ext/atom#URID";
+    pub const VECTOR: &'static str = "http://lv2plug.in/ns/ext/atom#Vector";
+}
+
+pub mod prop {
+    pub const BEATTIME: &'static str = "http://lv2plug.in/ns/ext/atom#beatTime";
+    pub const BUFFERTYPE: &'static str = "http://lv2plug.in/ns/ext/atom#bufferType";
+    pub const CHILDTYPE: &'static str = "http://lv2plug.in/ns/ext/atom#childType";
+    pub const FRAMETIME: &'static str = "http://lv2plug.in/ns/ext/atom#frameTime";
+    pub const SUPPORTS: &'static str = "http://lv2plug.in/ns/ext/atom#supports";
+    pub const TIMEUNIT: &'static str = "http://lv2plug.in/ns/ext/atom#timeUnit";
+}
+
+pub mod inst {
+    pub const ATOMTRANSFER: &'static str = "http://lv2plug.in/ns/ext/atom#atomTransfer";
+    pub const EVENTTRANSFER: &'static str = "http://lv2plug.in/ns/ext/atom#eventTransfer";
+}
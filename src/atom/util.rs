@@ -1,24 +1,150 @@
 
-use ::atom::*;
+use ::atom::{Atom, Header};
+use ::atom::read::Error;
 
 use std::mem;
+use std::slice;
 
 /// pad to 64 bits
 pub fn pad_size(size: usize) -> usize {
     (size + 7) & (!7)
 }
 
-pub unsafe fn sequence_begin(body: &SequenceBody) -> *const Event {
-    let ptr = body as *const SequenceBody;
-    ptr.offset(1) as *const Event
+/// Steps over the elements of a container body. Each element is made of
+/// `head` bytes followed by an atom, and is padded to 64 bits.
+pub struct Walker {
+    pos: usize,
+    end: usize,
+    head: usize,
 }
 
-pub unsafe fn sequence_is_end(body: &SequenceBody, size: usize, ev: *const Event) -> bool {
-    let ptr = body as *const SequenceBody as *const u8;
-	ev as *const u8 >= ptr.offset(size as isize)
+impl Walker {
+    /// Walks the bytes from `begin` to `end`, which must be readable.
+    pub unsafe fn new(begin: *const u8, end: *const u8, head: usize) -> Walker {
+        Walker {
+            pos: begin as usize,
+            end: end as usize,
+            head: head,
+        }
+    }
+
+    /// Walks to the end, returning the first error found.
+    pub fn check(mut self) -> Result<(), Error> {
+        while let Some(elem) = self.next() {
+            elem?;
+        }
+        Ok(())
+    }
+
+    /// Returns the next element, or an error if it does not fit in the container.
+    /// The walk ends after an error.
+    pub fn next(&mut self) -> Option<Result<*const u8, Error>> {
+        if self.pos >= self.end {
+            return None;
+        }
+        let avail = self.end - self.pos;
+        let min = self.head + mem::size_of::<Header>();
+        let size = if avail < min {
+            None
+        }
+        else {
+            let header = (self.pos + self.head) as *const Header;
+            Some(unsafe { (*header).size() }).filter(|&size| size <= avail - min)
+        };
+        match size {
+            Some(size) => {
+                let elem = self.pos as *const u8;
+                self.pos = self.end.min(self.pos + min + pad_size(size));
+                Some(Ok(elem))
+            },
+            None => {
+                self.pos = self.end;
+                Some(Err(Error::Truncated))
+            },
+        }
+    }
+}
+
+/// The body of `atom` following the fixed part of `T`.
+pub unsafe fn body<T: Atom>(atom: &T) -> &[u8] {
+    let ptr = atom as *const T as *const u8;
+    let fixed = mem::size_of::<T>() - mem::size_of::<Header>();
+    slice::from_raw_parts(ptr.add(mem::size_of::<T>()), atom.size().saturating_sub(fixed))
 }
 
-pub unsafe fn sequence_next(ev: *const Event) -> *const Event {
-	let ptr = ev as *const u8;
-	ptr.offset((mem::size_of::<Event>() + pad_size((*ev).size())) as isize) as *const Event
+/// Walks the elements of the container `atom`, each made of `head` bytes
+/// followed by an atom.
+pub unsafe fn walk<T: Atom>(atom: &T, head: usize) -> Walker {
+    let ptr = atom as *const T as *const u8;
+    Walker::new(ptr.add(mem::size_of::<T>()), ptr.add(mem::size_of::<Header>() + atom.size()), head)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::atom::testing::words;
+
+    fn walker(buf: &[u64], len: usize, head: usize) -> Walker {
+        assert!(len <= buf.len() * 8);
+        let begin = buf.as_ptr() as *const u8;
+        unsafe { Walker::new(begin, begin.add(len), head) }
+    }
+
+    fn offsets(buf: &[u64], mut walker: Walker) -> Vec<Result<usize, Error>> {
+        let begin = buf.as_ptr() as usize;
+        let mut res = Vec::new();
+        while let Some(elem) = walker.next() {
+            res.push(elem.map(|p| p as usize - begin));
+        }
+        res
+    }
+
+    #[test]
+    fn pad() {
+        assert_eq!(pad_size(0), 0);
+        assert_eq!(pad_size(1), 8);
+        assert_eq!(pad_size(8), 8);
+        assert_eq!(pad_size(9), 16);
+    }
+
+    #[test]
+    fn walk_elements() {
+        // an int, then an empty atom
+        let buf = words(&[4, 1, 7, 0, 0, 2]);
+        assert_eq!(offsets(&buf, walker(&buf, 24, 0)), vec![Ok(0), Ok(16)]);
+        assert!(walker(&buf, 24, 0).check().is_ok());
+        assert_eq!(offsets(&buf, walker(&buf, 0, 0)), vec![]);
+    }
+
+    #[test]
+    fn walk_with_head() {
+        // 8 byte time stamps before each atom
+        let buf = words(&[5, 0, 4, 1, 7, 0, 6, 0, 0, 2]);
+        assert_eq!(offsets(&buf, walker(&buf, 40, 8)), vec![Ok(0), Ok(24)]);
+    }
+
+    #[test]
+    fn walk_last_element_unpadded() {
+        let buf = words(&[4, 1, 7, 0]);
+        assert_eq!(offsets(&buf, walker(&buf, 12, 0)), vec![Ok(0)]);
+    }
+
+    #[test]
+    fn walk_truncated_header() {
+        let buf = words(&[0, 1, 0, 0]);
+        assert_eq!(offsets(&buf, walker(&buf, 12, 0)), vec![Ok(0), Err(Error::Truncated)]);
+        // the head fits but not the atom header
+        let buf = words(&[0, 0, 0, 0]);
+        assert_eq!(offsets(&buf, walker(&buf, 12, 8)), vec![Err(Error::Truncated)]);
+    }
+
+    #[test]
+    fn walk_oversized() {
+        // the error ends the walk, the following atom is not visited
+        let buf = words(&[20, 1, 0, 0, 0, 1]);
+        assert_eq!(offsets(&buf, walker(&buf, 24, 0)), vec![Err(Error::Truncated)]);
+        assert_eq!(walker(&buf, 24, 0).check(), Err(Error::Truncated));
+        let buf = words(&[!0, 1]);
+        assert_eq!(offsets(&buf, walker(&buf, 8, 0)), vec![Err(Error::Truncated)]);
+    }
 }
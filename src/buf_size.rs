@@ -0,0 +1,15 @@
+pub const URI: &'static str = "http://lv2plug.in/ns/ext/buf-size";
+
+pub mod inst {
+    pub const BOUNDEDBLOCKLENGTH:  &'static str = "http://lv2plug.in/ns/ext/buf-size#boundedBlockLength";
+    pub const COARSEBLOCKLENGTH:   &'static str = "http://lv2plug.in/ns/ext/buf-size#coarseBlockLength";
+    pub const FIXEDBLOCKLENGTH:    &'static str = "http://lv2plug.in/ns/ext/buf-size#fixedBlockLength";
+    pub const POWEROF2BLOCKLENGTH: &'static str = "http://lv2plug.in/ns/ext/buf-size#powerOf2BlockLength";
+}
+
+pub mod prop {
+    pub const MAXBLOCKLENGTH:      &'static str = "http://lv2plug.in/ns/ext/buf-size#maxBlockLength";
+    pub const MINBLOCKLENGTH:      &'static str = "http://lv2plug.in/ns/ext/buf-size#minBlockLength";
+    pub const NOMINALBLOCKLENGTH:  &'static str = "http://lv2plug.in/ns/ext/buf-size#nominalBlockLength";
+    pub const SEQUENCESIZE:        &'static str = "http://lv2plug.in/ns/ext/buf-size#sequenceSize";
+}
@@ -1,12 +1,21 @@
 
 use ffi::core::*;
+use log;
+use port_groups::GroupInfo;
+use urid;
 
 use libc::{c_char, c_void};
 
+use std::any::Any;
+use std::cell::UnsafeCell;
 use std::ffi::CStr;
 use std::mem;
 use std::marker;
+use std::panic::{self, AssertUnwindSafe};
 use std::ptr;
+use std::slice;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 
 pub trait Plugin<'h>
@@ -14,19 +23,219 @@ where
     Self: Ported<'h>,
     Self: marker::Sized,
 {
-    fn new(sample_rate: f64, bundle_path: &str, features: FeatureList<'h>) -> Option<Self>;
+    type Features: FeatureSet<'h>;
+    fn new(sample_rate: f64, bundle_path: &str, features: Self::Features) -> Option<Self>;
     fn activate(&mut self) {}
     fn run(&mut self, ports: &mut Self::Ports, sample_count: usize);
     fn deactivate(&mut self) {}
+
+    /// Returns the interface implementing the extension identified by `uri`,
+    /// or `None` if the plugin does not implement it.
+    /// Usually implemented with `lv2_extensions!`.
+    fn extension_data(_uri: &str) -> Option<*const c_void> {
+        None
+    }
+
+    /// URIs of the extensions returned by `extension_data`, listed in the
+    /// plugin description. Usually implemented with `lv2_extensions!`.
+    fn extension_uris() -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// State used by extension entry points that may run concurrently with
+    /// `run`, taken once after `new`.
+    /// Usually implemented with `lv2_extensions!`.
+    fn extension_shared(&self) -> Option<Arc<dyn Any + Send + Sync>> {
+        None
+    }
+}
+
+
+/// An extension interface that can be provided to the host by plugins of type `T`.
+pub unsafe trait Extension<'h, T: Plugin<'h>> {
+    fn uri() -> &'static str;
+    /// Pointer to the interface struct. Must be valid for the whole program.
+    fn interface() -> *const c_void;
+    /// State used by the interface functions instead of the plugin,
+    /// taken once after `Plugin::new`.
+    fn shared(_plugin: &T) -> Option<Arc<dyn Any + Send + Sync>> {
+        None
+    }
+}
+
+/// A list of extension interfaces, implemented for tuples of `Extension`.
+pub trait Extensions<'h, T: Plugin<'h>> {
+    fn interface(uri: &str) -> Option<*const c_void>;
+    fn uris() -> Vec<&'static str>;
+    /// The shared state of the first extension providing one.
+    fn shared(plugin: &T) -> Option<Arc<dyn Any + Send + Sync>>;
+}
+
+impl<'h, T: Plugin<'h>> Extensions<'h, T> for () {
+    fn interface(_uri: &str) -> Option<*const c_void> {
+        None
+    }
+    fn uris() -> Vec<&'static str> {
+        Vec::new()
+    }
+    fn shared(_plugin: &T) -> Option<Arc<dyn Any + Send + Sync>> {
+        None
+    }
+}
+
+macro_rules! impl_extensions_tuple {
+    ( $( $Ext:ident ),+ ) => {
+        impl<'h, T: Plugin<'h>, $( $Ext: Extension<'h, T> ),+> Extensions<'h, T> for ( $( $Ext, )+ ) {
+            fn interface(uri: &str) -> Option<*const c_void> {
+                $(
+                    if uri == $Ext::uri() {
+                        return Some($Ext::interface());
+                    }
+                )+
+                None
+            }
+            fn uris() -> Vec<&'static str> {
+                vec![ $( $Ext::uri() ),+ ]
+            }
+            fn shared(plugin: &T) -> Option<Arc<dyn Any + Send + Sync>> {
+                $(
+                    if let Some(shared) = $Ext::shared(plugin) {
+                        return Some(shared);
+                    }
+                )+
+                None
+            }
+        }
+    };
 }
 
+impl_extensions_tuple!(A);
+impl_extensions_tuple!(A, B);
+impl_extensions_tuple!(A, B, C);
+impl_extensions_tuple!(A, B, C, D);
+impl_extensions_tuple!(A, B, C, D, E);
+impl_extensions_tuple!(A, B, C, D, E, F);
+impl_extensions_tuple!(A, B, C, D, E, F, G);
+impl_extensions_tuple!(A, B, C, D, E, F, G, H);
 
+
+/// The instance handle given to the host.
+/// Entry points only take shared references to it: the plugin and its ports
+/// are behind `UnsafeCell`s, and entry points that may run concurrently with
+/// `run` only use the `shared` state.
 pub struct PluginInstance<'h, T>
 where
     T: Plugin<'h>,
 {
-    pub ports_raw: <T as Ported<'h>>::PortsRaw,
-    pub state: T,
+    ports_raw: UnsafeCell<<T as Ported<'h>>::PortsRaw>,
+    state: UnsafeCell<T>,
+    shared: Option<Arc<dyn Any + Send + Sync>>,
+    failed: AtomicBool,
+    log: Option<HostLog<'h>>,
+}
+
+impl<'h, T: Plugin<'h>> PluginInstance<'h, T> {
+    /// The instance behind a handle returned by `instantiate`.
+    pub(crate) unsafe fn from_handle<'a>(handle: LV2_Handle) -> &'a PluginInstance<'h, T> {
+        &*(handle as *const PluginInstance<'h, T>)
+    }
+
+    /// Whether the plugin panicked. A failed plugin is not called anymore.
+    pub fn failed(&self) -> bool {
+        self.failed.load(Ordering::Acquire)
+    }
+
+    fn fail(&self, entry: &str, payload: Box<dyn Any + Send>) {
+        self.failed.store(true, Ordering::Release);
+        report_panic(&self.log, entry, payload);
+    }
+
+    /// Calls `f` with the plugin from the host entry point `entry`.
+    /// Returns `None` if the plugin failed before or panics.
+    /// The host must not call `entry` concurrently with another entry point
+    /// using the plugin.
+    pub(crate) unsafe fn call<R, F>(&self, entry: &str, f: F) -> Option<R>
+        where F: FnOnce(&mut T) -> R
+    {
+        if self.failed() {
+            return None;
+        }
+        let state = &mut *self.state.get();
+        match panic::catch_unwind(AssertUnwindSafe(|| f(state))) {
+            Ok(res) => Some(res),
+            Err(payload) => {
+                self.fail(entry, payload);
+                None
+            },
+        }
+    }
+
+    /// Calls `f` with the state shared by the plugin with its extensions,
+    /// from a host entry point that may run concurrently with the others.
+    /// Returns `None` if the plugin failed before, has no shared state of
+    /// type `S` or panics.
+    pub(crate) fn call_shared<S, R, F>(&self, entry: &str, f: F) -> Option<R>
+        where S: Any, F: FnOnce(&S) -> R
+    {
+        if self.failed() {
+            return None;
+        }
+        let shared = match self.shared {
+            Some(ref shared) => (**shared).downcast_ref::<S>()?,
+            None => return None,
+        };
+        match panic::catch_unwind(AssertUnwindSafe(|| f(shared))) {
+            Ok(res) => Some(res),
+            Err(payload) => {
+                self.fail(entry, payload);
+                None
+            },
+        }
+    }
+}
+
+struct HostLog<'h> {
+    log: log::Log<'h>,
+    error: urid::URID,
+}
+
+impl<'h> HostLog<'h> {
+    fn from_features(features: FeatureList<'h>) -> Option<HostLog<'h>> {
+        let mut log = None;
+        let mut error = 0;
+        for f in features {
+            if f.uri() == <log::Log as Feature>::uri() {
+                log = Some(unsafe { <log::Log as Feature>::from_raw(&f) });
+            }
+            else if f.uri() == <urid::Map as Feature>::uri() {
+                let map = unsafe { <urid::Map as Feature>::from_raw(&f) };
+                error = map.map(log::class::ERROR);
+            }
+        }
+        log.map(|log| HostLog { log: log, error: error })
+    }
+
+    fn println(&self, msg: String) {
+        // interior nul bytes can't be passed to the host
+        self.log.println(self.error, msg.replace('\0', ""));
+    }
+}
+
+fn report_panic(log: &Option<HostLog>, entry: &str, payload: Box<dyn Any + Send>) {
+    let msg = if let Some(msg) = payload.downcast_ref::<&str>() {
+        *msg
+    }
+    else if let Some(msg) = payload.downcast_ref::<String>() {
+        &msg[..]
+    }
+    else {
+        "unknown panic payload"
+    };
+    if let Some(ref log) = *log {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+            log.println(format!("plugin panicked in {}: {}", entry, msg));
+        }));
+    }
 }
 
 pub trait Feature<'h>
@@ -35,6 +244,42 @@ pub trait Feature<'h>
     unsafe fn from_raw(raw: &RawFeature<'h>) -> Self;
 }
 
+/// A set of features resolved from the host feature list before the plugin is
+/// instantiated. Usually declared with `lv2_features!`.
+pub trait FeatureSet<'h>
+where
+    Self: marker::Sized,
+{
+    fn required_features() -> Vec<&'static str>;
+    fn optional_features() -> Vec<&'static str>;
+    /// Resolves the set, or returns the URIs of the missing required features.
+    fn resolve(features: FeatureList<'h>) -> Result<Self, Vec<&'static str>>;
+}
+
+impl<'h> FeatureSet<'h> for () {
+    fn required_features() -> Vec<&'static str> {
+        Vec::new()
+    }
+    fn optional_features() -> Vec<&'static str> {
+        Vec::new()
+    }
+    fn resolve(_features: FeatureList<'h>) -> Result<Self, Vec<&'static str>> {
+        Ok(())
+    }
+}
+
+impl<'h> FeatureSet<'h> for FeatureList<'h> {
+    fn required_features() -> Vec<&'static str> {
+        Vec::new()
+    }
+    fn optional_features() -> Vec<&'static str> {
+        Vec::new()
+    }
+    fn resolve(features: FeatureList<'h>) -> Result<Self, Vec<&'static str>> {
+        Ok(features)
+    }
+}
+
 pub struct RawFeature<'h> {
     pub raw: *const LV2_Feature,
     marker: marker::PhantomData<&'h ()>,
@@ -59,10 +304,30 @@ pub struct FeatureList<'h>
     marker: marker::PhantomData<&'h ()>,
 }
 
+impl<'h> FeatureList<'h> {
+    /// The features of the null terminated array `raw`, which may be null.
+    pub(crate) unsafe fn from_raw(raw: *const *const LV2_Feature) -> FeatureList<'h> {
+        FeatureList { raw: raw, marker: marker::PhantomData }
+    }
+
+    /// The first feature of type `F` in the list.
+    pub fn feature<F: Feature<'h>>(self) -> Option<F> {
+        for f in self {
+            if f.uri() == F::uri() {
+                return Some(unsafe { F::from_raw(&f) });
+            }
+        }
+        None
+    }
+}
+
 impl<'h> Iterator for FeatureList<'h>
 {
     type Item = RawFeature<'h>;
     fn next(&mut self) -> Option<Self::Item> {
+        if self.raw.is_null() {
+            return None;
+        }
         let raw = unsafe { *self.raw };
 
         if raw.is_null() {
@@ -85,24 +350,60 @@ pub extern "C" fn instantiate<'h, T: Plugin<'h>>(
     features: *const *const LV2_Feature,
 ) -> LV2_Handle {
 
-    let bundle_path = unsafe { CStr::from_ptr(bundle_path).to_str().unwrap() };
-    if let Some(state) = T::new(sample_rate, bundle_path,
-                                FeatureList{raw: features, marker: marker::PhantomData}) {
-        let ports = <T as Ported>::new_ports_raw();
-        let instance = Box::new(PluginInstance::<T> {
-            ports_raw: ports,
-            state: state
-        });
-        Box::into_raw(instance) as LV2_Handle
-    }
-    else {
-        ptr::null_mut()
+    let log = panic::catch_unwind(|| {
+        HostLog::from_features(FeatureList{raw: features, marker: marker::PhantomData})
+    }).unwrap_or(None);
+
+    let bundle_path = match unsafe { CStr::from_ptr(bundle_path).to_str() } {
+        Ok(path) => path,
+        Err(_) => {
+            if let Some(ref log) = log {
+                log.println("bundle path is not valid UTF-8".to_string());
+            }
+            return ptr::null_mut();
+        }
+    };
+
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        let features = FeatureList{raw: features, marker: marker::PhantomData};
+        match T::Features::resolve(features) {
+            Ok(features) => Ok(T::new(sample_rate, bundle_path, features).map(|state| {
+                let shared = state.extension_shared();
+                (state, shared)
+            })),
+            Err(missing) => Err(missing),
+        }
+    }));
+
+    match res {
+        Ok(Ok(Some((state, shared)))) => {
+            let ports = <T as Ported>::new_ports_raw();
+            let instance = Box::new(PluginInstance::<T> {
+                ports_raw: UnsafeCell::new(ports),
+                state: UnsafeCell::new(state),
+                shared: shared,
+                failed: AtomicBool::new(false),
+                log: log,
+            });
+            Box::into_raw(instance) as LV2_Handle
+        },
+        Ok(Ok(None)) => ptr::null_mut(),
+        Ok(Err(missing)) => {
+            if let Some(ref log) = log {
+                log.println(format!("missing required features: {}", missing.join(", ")));
+            }
+            ptr::null_mut()
+        },
+        Err(payload) => {
+            report_panic(&log, "instantiate", payload);
+            ptr::null_mut()
+        }
     }
 }
 
 pub extern "C" fn activate<'h, T: Plugin<'h>>(instance: LV2_Handle) {
-    let instance: &mut PluginInstance<T> = unsafe { mem::transmute(instance) };
-    instance.state.activate();
+    let instance = unsafe { PluginInstance::<T>::from_handle(instance) };
+    unsafe { instance.call("activate", |plugin| plugin.activate()) };
 }
 
 pub extern "C" fn connect_port<'h, T: Plugin<'h>>(
@@ -110,12 +411,18 @@ pub extern "C" fn connect_port<'h, T: Plugin<'h>>(
     port: u32,
     data_location: *mut c_void,
 ) {
-    let instance: &mut PluginInstance<T> = unsafe { mem::transmute(instance) };
-    <T as Ported>::connect_port(
-        port as usize,
-        data_location as *mut _,
-        &mut instance.ports_raw,
-    );
+    let instance = unsafe { PluginInstance::<T>::from_handle(instance) };
+    let ports_raw = unsafe { &mut *instance.ports_raw.get() };
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        <T as Ported>::connect_port(
+            port as usize,
+            data_location as *mut _,
+            ports_raw,
+        );
+    }));
+    if let Err(payload) = res {
+        instance.fail("connect_port", payload);
+    }
 }
 
 
@@ -123,22 +430,130 @@ pub extern "C" fn run<'h, T: 'h + Plugin<'h>>(instance: LV2_Handle, sample_count
 where
     T::PortsRaw: Copy,
 {
-    let instance: &mut PluginInstance<T> = unsafe { mem::transmute(instance) };
+    let instance = unsafe { PluginInstance::<T>::from_handle(instance) };
+    let ports_raw = unsafe { *instance.ports_raw.get() };
 
     let sample_count = sample_count as usize;
 
-    let mut ports = <T as Ported>::convert_ports(instance.ports_raw, sample_count);
-    instance.state.run(&mut ports, sample_count);
+    unsafe {
+        instance.call("run", |plugin| {
+            let mut ports = <T as Ported>::convert_ports(ports_raw, sample_count);
+            plugin.run(&mut ports, sample_count);
+        });
+    }
+
+    if instance.failed() {
+        <T as Ported>::silence_outputs(ports_raw, sample_count);
+    }
 }
 
 pub extern "C" fn deactivate<'h, T: Plugin<'h>>(instance: LV2_Handle) {
-    let instance: &mut PluginInstance<T> = unsafe { mem::transmute(instance) };
-    instance.state.deactivate();
+    let instance = unsafe { PluginInstance::<T>::from_handle(instance) };
+    unsafe { instance.call("deactivate", |plugin| plugin.deactivate()) };
 }
 
 pub extern "C" fn cleanup<'h, T: Plugin<'h>>(instance: LV2_Handle) {
-    unsafe {
-        let _ = Box::from_raw(instance as *mut PluginInstance<T>);
+    let instance = unsafe { Box::from_raw(instance as *mut PluginInstance<T>) };
+    let _ = panic::catch_unwind(AssertUnwindSafe(move || {
+        drop(instance);
+    }));
+}
+
+pub extern "C" fn extension_data<'h, T: Plugin<'h>>(uri: *const c_char) -> *const c_void {
+    let uri = unsafe { CStr::from_ptr(uri) };
+    let res = panic::catch_unwind(|| {
+        match uri.to_str() {
+            Ok(uri) => T::extension_data(uri).unwrap_or(ptr::null()),
+            Err(_) => ptr::null(),
+        }
+    });
+    res.unwrap_or(ptr::null())
+}
+
+
+/// Input and output audio buffers that the host may have connected to the
+/// same memory.
+pub enum InPlace<'h> {
+    Separate(&'h [f32], &'h mut [f32]),
+    Aliased(&'h mut [f32]),
+}
+
+impl<'h> InPlace<'h> {
+    /// Partially overlapping buffers are turned into an aliased buffer
+    /// by copying the input into the output.
+    pub unsafe fn new(input: *const f32, output: *mut f32, len: usize) -> InPlace<'h> {
+        let in_start = input as usize;
+        let out_start = output as usize;
+        let byte_len = len * mem::size_of::<f32>();
+        if in_start == out_start {
+            InPlace::Aliased(slice::from_raw_parts_mut(output, len))
+        }
+        else if in_start < out_start + byte_len && out_start < in_start + byte_len {
+            ptr::copy(input, output, len);
+            InPlace::Aliased(slice::from_raw_parts_mut(output, len))
+        }
+        else {
+            InPlace::Separate(slice::from_raw_parts(input, len),
+                              slice::from_raw_parts_mut(output, len))
+        }
+    }
+
+    pub fn is_aliased(&self) -> bool {
+        match *self {
+            InPlace::Aliased(_) => true,
+            InPlace::Separate(..) => false,
+        }
+    }
+
+    /// The input samples. When aliased, they are overwritten by writes to `output`.
+    pub fn input(&self) -> &[f32] {
+        match *self {
+            InPlace::Separate(input, _) => input,
+            InPlace::Aliased(ref buf) => buf,
+        }
+    }
+
+    pub fn output(&mut self) -> &mut [f32] {
+        match *self {
+            InPlace::Separate(_, ref mut output) => output,
+            InPlace::Aliased(ref mut buf) => buf,
+        }
+    }
+
+    /// Borrows input and output together if they don't alias.
+    pub fn split(&mut self) -> Option<(&[f32], &mut [f32])> {
+        match *self {
+            InPlace::Separate(input, ref mut output) => Some((input, output)),
+            InPlace::Aliased(_) => None,
+        }
+    }
+
+    /// Returns a single buffer initialized with the input samples, that is
+    /// to be overwritten with the output samples.
+    pub fn in_place(&mut self) -> &mut [f32] {
+        match *self {
+            InPlace::Separate(input, ref mut output) => {
+                output.copy_from_slice(input);
+                output
+            },
+            InPlace::Aliased(ref mut buf) => buf,
+        }
+    }
+
+    /// Computes each output sample from the input sample at the same frame.
+    pub fn process<F: FnMut(f32) -> f32>(&mut self, mut f: F) {
+        match *self {
+            InPlace::Separate(input, ref mut output) => {
+                for (o, i) in output.iter_mut().zip(input.iter()) {
+                    *o = f(*i);
+                }
+            },
+            InPlace::Aliased(ref mut buf) => {
+                for s in buf.iter_mut() {
+                    *s = f(*s);
+                }
+            },
+        }
     }
 }
 
@@ -149,32 +564,449 @@ pub unsafe trait Ported<'h> {
     fn new_ports_raw() -> Self::PortsRaw;
     fn connect_port(port: usize, data: *mut (), ports_raw: &mut Self::PortsRaw);
     fn convert_ports(ports_raw: Self::PortsRaw, sample_count: usize) -> Self::Ports;
+    /// Fills the output ports with silence.
+    fn silence_outputs(ports_raw: Self::PortsRaw, sample_count: usize);
+    /// Metadata of every port, in index order.
+    fn port_infos() -> Vec<PortInfo>;
+}
+
+/// Metadata of a single port.
+#[derive(Clone, Debug)]
+pub struct PortInfo {
+    pub index: usize,
+    pub symbol: String,
+    pub name: String,
+    pub classes: &'static [&'static str],
+    pub properties: Vec<&'static str>,
+    pub designation: Option<&'static str>,
+    /// Additional `(predicate, object)` statements, e.g. `atom:bufferType`.
+    pub statements: Vec<(&'static str, &'static str)>,
+    /// Symbol and description of the group the port belongs to.
+    pub group: Option<(String, GroupInfo)>,
+    pub minimum: Option<f32>,
+    pub maximum: Option<f32>,
+    pub default: Option<f32>,
+    pub unit: Option<&'static str>,
+    pub scale_points: Vec<(&'static str, f32)>,
 }
 
 
 pub mod meta {
 
+    use super::{class, inst, InPlace, PortInfo};
+    use port_groups::GroupInfo;
+
+    use std::marker;
     use std::ptr;
     use std::mem;
     use std::slice;
 
     pub unsafe trait Port<'h> {
-        type FieldRaw;
+        type FieldRaw: Copy;
         type Field;
+        /// Number of consecutive port indices covered by the field.
+        const PORT_COUNT: usize = 1;
+        /// The `rdf:type`s of the port at `index` relative to the field,
+        /// e.g. `lv2:InputPort` and `lv2:AudioPort`.
+        fn classes(index: usize) -> &'static [&'static str];
+        /// Whether the port is `lv2:connectionOptional`, which is added to
+        /// its `properties`.
+        const OPTIONAL: bool = false;
+        /// The `lv2:portProperty`s of the port.
+        fn properties() -> &'static [&'static str] {
+            &[]
+        }
+        /// The `lv2:designation` of the port at `index` relative to the field.
+        fn designation(_index: usize) -> Option<&'static str> {
+            None
+        }
+        /// The port group formed by the field, if any.
+        fn group() -> Option<GroupInfo> {
+            None
+        }
+        /// Additional `(predicate, object)` statements describing the ports.
+        fn statements() -> &'static [(&'static str, &'static str)] {
+            &[]
+        }
         fn new_raw() -> Self::FieldRaw;
-        fn cast_raw(data: *mut ()) -> Self::FieldRaw;
+        /// Connects the port at `index` relative to the field.
+        fn connect(raw: &mut Self::FieldRaw, index: usize, data: *mut ());
+        fn is_connected(raw: Self::FieldRaw) -> bool;
         fn convert(raw: Self::FieldRaw, sample_count: usize) -> Self::Field;
+        fn silence(_raw: Self::FieldRaw, _sample_count: usize) {}
+    }
+
+    /// Maps a port collection field type to its default port meta.
+    pub trait PortField<'h> {
+        type Meta: Port<'h, Field = Self>;
+    }
+
+    impl<'h> PortField<'h> for &'h f32 {
+        type Meta = InputControl;
+    }
+
+    impl<'h> PortField<'h> for &'h mut f32 {
+        type Meta = OutputControl;
+    }
+
+    impl<'h> PortField<'h> for &'h [f32] {
+        type Meta = InputAudio;
+    }
+
+    impl<'h> PortField<'h> for &'h mut [f32] {
+        type Meta = OutputAudio;
+    }
+
+    impl<'h> PortField<'h> for InPlace<'h> {
+        type Meta = InPlaceAudio;
+    }
+
+    impl<'h, F: PortField<'h>> PortField<'h> for Option<F> {
+        type Meta = Optional<F::Meta>;
+    }
+
+    /// Port attributes declared on a port collection field.
+    pub struct FieldInfo {
+        pub index: usize,
+        pub symbol: &'static str,
+        pub name: &'static str,
+        /// Symbols and names of each port of multi-port fields.
+        pub symbols: &'static [&'static str],
+        pub names: &'static [&'static str],
+        pub minimum: Option<f32>,
+        pub maximum: Option<f32>,
+        pub default: Option<f32>,
+        pub unit: Option<&'static str>,
+        pub properties: &'static [&'static str],
+        /// Overrides the designation of the port meta.
+        pub designation: Option<&'static str>,
+        pub statements: &'static [(&'static str, &'static str)],
+        pub scale_points: &'static [(&'static str, f32)],
+    }
+
+    impl FieldInfo {
+        pub fn new(index: usize, symbol: &'static str) -> FieldInfo {
+            FieldInfo {
+                index: index,
+                symbol: symbol,
+                name: symbol,
+                symbols: &[],
+                names: &[],
+                minimum: None,
+                maximum: None,
+                default: None,
+                unit: None,
+                properties: &[],
+                designation: None,
+                statements: &[],
+                scale_points: &[],
+            }
+        }
+
+        /// Metadata of the ports of a field of type `M`.
+        pub fn port_infos<'h, M: Port<'h>>(&self) -> Vec<PortInfo> {
+            (0 .. M::PORT_COUNT).map(|i| {
+                let (symbol, name) = if M::PORT_COUNT == 1 {
+                    (self.symbol.to_string(), self.name.to_string())
+                }
+                else {
+                    (format!("{}_{}", self.symbol, i), format!("{} {}", self.name, i))
+                };
+                let mut properties = M::properties().to_vec();
+                if M::OPTIONAL && !properties.contains(&inst::CONNECTIONOPTIONAL) {
+                    properties.push(inst::CONNECTIONOPTIONAL);
+                }
+                properties.extend_from_slice(self.properties);
+                let symbol = self.symbols.get(i).map(|s| s.to_string()).unwrap_or(symbol);
+                let name = self.names.get(i).or(self.symbols.get(i))
+                        .map(|s| s.to_string()).unwrap_or(name);
+                let mut statements = M::statements().to_vec();
+                statements.extend_from_slice(self.statements);
+                PortInfo {
+                    index: self.index + i,
+                    symbol: symbol,
+                    name: name,
+                    classes: M::classes(i),
+                    properties: properties,
+                    designation: self.designation.or(M::designation(i)),
+                    statements: statements,
+                    group: M::group().map(|g| (self.symbol.to_string(), g)),
+                    minimum: self.minimum,
+                    maximum: self.maximum,
+                    default: self.default,
+                    unit: self.unit,
+                    scale_points: self.scale_points.to_vec(),
+                }
+            }).collect()
+        }
+    }
+
+    /// Panics if port index ranges given as `(index, count)` overlap.
+    /// Evaluated in constant context to reject bad port collections at compile time.
+    ///
+    /// ```
+    /// extern crate lv2;
+    /// use lv2::PortCollection;
+    ///
+    /// struct Amp;
+    ///
+    /// #[derive(PortCollection)]
+    /// #[Plugin = "Amp"]
+    /// struct Ports<'h> {
+    ///     #[port(symbols("in", "out"))]
+    ///     io: lv2::InPlace<'h>,
+    ///     #[port(index = 2, min = -90, max = 24.0)]
+    ///     gain: &'h f32,
+    /// }
+    ///
+    /// fn main() {
+    ///     <Amp as lv2::Ported>::new_ports_raw();
+    /// }
+    /// ```
+    ///
+    /// `io` covers the indices 0 and 1:
+    ///
+    /// ```compile_fail,E0080
+    /// extern crate lv2;
+    /// use lv2::PortCollection;
+    ///
+    /// struct Amp;
+    ///
+    /// #[derive(PortCollection)]
+    /// #[Plugin = "Amp"]
+    /// struct Ports<'h> {
+    ///     #[port(symbols("in", "out"))]
+    ///     io: lv2::InPlace<'h>,
+    ///     #[port(index = 1)]
+    ///     gain: &'h f32,
+    /// }
+    ///
+    /// fn main() {
+    ///     <Amp as lv2::Ported>::new_ports_raw();
+    /// }
+    /// ```
+    ///
+    /// Explicit indices are checked by the derive itself:
+    ///
+    /// ```compile_fail
+    /// extern crate lv2;
+    /// use lv2::PortCollection;
+    ///
+    /// struct Amp;
+    ///
+    /// #[derive(PortCollection)]
+    /// #[Plugin = "Amp"]
+    /// struct Ports<'h> {
+    ///     #[port(index = 0)]
+    ///     gain: &'h f32,
+    ///     #[port(index = 0)]
+    ///     out: &'h mut [f32],
+    /// }
+    ///
+    /// fn main() {}
+    /// ```
+    pub const fn check_port_indices(ranges: &[(usize, usize)]) {
+        let mut i = 0;
+        while i < ranges.len() {
+            let mut j = i + 1;
+            while j < ranges.len() {
+                let (a, a_count) = ranges[i];
+                let (b, b_count) = ranges[j];
+                if a < b + b_count && b < a + a_count {
+                    panic!("overlapping port indices");
+                }
+                j += 1;
+            }
+            i += 1;
+        }
+    }
+
+    /// Panics if two ports get the same symbol. Each field is given as its
+    /// symbol, its explicit `symbols` and its port count. Ports without an
+    /// explicit symbol are named `{symbol}_{i}` if the field has several ports.
+    ///
+    /// ```compile_fail,E0080
+    /// extern crate lv2;
+    /// use lv2::PortCollection;
+    ///
+    /// struct Amp;
+    ///
+    /// #[derive(PortCollection)]
+    /// #[Plugin = "Amp"]
+    /// struct Ports<'h> {
+    ///     io: lv2::InPlace<'h>,
+    ///     #[port(symbol = "io_1")]
+    ///     gain: &'h f32,
+    /// }
+    ///
+    /// fn main() {
+    ///     <Amp as lv2::Ported>::new_ports_raw();
+    /// }
+    /// ```
+    pub const fn check_port_symbols(fields: &[(&str, &[&str], usize)]) {
+        let mut f = 0;
+        while f < fields.len() {
+            let mut i = 0;
+            while i < fields[f].2 {
+                let mut g = f;
+                let mut j = i + 1;
+                while g < fields.len() {
+                    while j < fields[g].2 {
+                        if port_symbol_eq(fields[f], i, fields[g], j) {
+                            panic!("duplicate port symbol");
+                        }
+                        j += 1;
+                    }
+                    g += 1;
+                    j = 0;
+                }
+                i += 1;
+            }
+            f += 1;
+        }
+    }
+
+    /// The symbol of port `i` of `field`, as a base and an optional index suffix.
+    const fn port_symbol<'a>(field: (&'a str, &'a [&'a str], usize), i: usize)
+        -> (&'a [u8], Option<usize>)
+    {
+        if i < field.1.len() {
+            (field.1[i].as_bytes(), None)
+        }
+        else if field.2 == 1 {
+            (field.0.as_bytes(), None)
+        }
+        else {
+            (field.0.as_bytes(), Some(i))
+        }
+    }
+
+    const fn port_symbol_eq(a: (&str, &[&str], usize), i: usize,
+                            b: (&str, &[&str], usize), j: usize) -> bool {
+        let a = port_symbol(a, i);
+        let b = port_symbol(b, j);
+        let len = symbol_len(a);
+        if len != symbol_len(b) {
+            return false;
+        }
+        let mut k = 0;
+        while k < len {
+            if symbol_byte(a, k) != symbol_byte(b, k) {
+                return false;
+            }
+            k += 1;
+        }
+        true
+    }
+
+    const fn digit_count(mut n: usize) -> usize {
+        let mut count = 1;
+        while n >= 10 {
+            n /= 10;
+            count += 1;
+        }
+        count
+    }
+
+    const fn symbol_len(sym: (&[u8], Option<usize>)) -> usize {
+        match sym.1 {
+            Some(n) => sym.0.len() + 1 + digit_count(n),
+            None => sym.0.len(),
+        }
+    }
+
+    const fn symbol_byte(sym: (&[u8], Option<usize>), k: usize) -> u8 {
+        let base = sym.0;
+        if k < base.len() {
+            return base[k];
+        }
+        let mut n = match sym.1 {
+            Some(n) => n,
+            None => return 0,
+        };
+        if k == base.len() {
+            return b'_';
+        }
+        // digits are written most significant first
+        let mut skip = digit_count(n) - (k - base.len());
+        while skip > 0 {
+            n /= 10;
+            skip -= 1;
+        }
+        b'0' + (n % 10) as u8
+    }
+
+    /// Panics if a port that is not connection optional is not connected,
+    /// before its field is converted. The panic is reported to the host by
+    /// `run`, which fails the instance and silences its outputs.
+    pub fn check_connected<'h, M: Port<'h>>(raw: M::FieldRaw, index: usize, name: &str) {
+        if !M::is_connected(raw) && !M::OPTIONAL {
+            panic!("port {} ({}) is not connected", index, name);
+        }
+    }
+
+    /// A `lv2:connectionOptional` port, which field is `None` when not connected.
+    pub struct Optional<M> {
+        marker: marker::PhantomData<M>,
+    }
+
+    unsafe impl<'h, M: Port<'h>> Port<'h> for Optional<M> {
+        type FieldRaw = M::FieldRaw;
+        type Field = Option<M::Field>;
+        const PORT_COUNT: usize = M::PORT_COUNT;
+        const OPTIONAL: bool = true;
+        fn classes(index: usize) -> &'static [&'static str] {
+            M::classes(index)
+        }
+        fn properties() -> &'static [&'static str] {
+            M::properties()
+        }
+        fn designation(index: usize) -> Option<&'static str> {
+            M::designation(index)
+        }
+        fn group() -> Option<GroupInfo> {
+            M::group()
+        }
+        fn statements() -> &'static [(&'static str, &'static str)] {
+            M::statements()
+        }
+        fn new_raw() -> Self::FieldRaw {
+            M::new_raw()
+        }
+        fn connect(raw: &mut Self::FieldRaw, index: usize, data: *mut ()) {
+            M::connect(raw, index, data)
+        }
+        fn is_connected(raw: Self::FieldRaw) -> bool {
+            M::is_connected(raw)
+        }
+        fn convert(raw: Self::FieldRaw, sample_count: usize) -> Self::Field {
+            if M::is_connected(raw) {
+                Some(M::convert(raw, sample_count))
+            }
+            else {
+                None
+            }
+        }
+        fn silence(raw: Self::FieldRaw, sample_count: usize) {
+            M::silence(raw, sample_count)
+        }
     }
 
     pub enum InputControl {}
     unsafe impl<'h> Port<'h> for InputControl {
         type FieldRaw = *const f32;
         type Field = &'h f32;
+        fn classes(_index: usize) -> &'static [&'static str] {
+            &[class::INPUTPORT, class::CONTROLPORT]
+        }
         fn new_raw() -> Self::FieldRaw {
             ptr::null()
         }
-        fn cast_raw(data: *mut ()) -> Self::FieldRaw {
-            data as Self::FieldRaw
+        fn connect(raw: &mut Self::FieldRaw, _index: usize, data: *mut ()) {
+            *raw = data as Self::FieldRaw;
+        }
+        fn is_connected(raw: Self::FieldRaw) -> bool {
+            !raw.is_null()
         }
         fn convert(raw: Self::FieldRaw, _: usize) -> Self::Field {
             unsafe { mem::transmute(raw) }
@@ -185,11 +1017,17 @@ pub mod meta {
     unsafe impl<'h> Port<'h> for OutputControl {
         type FieldRaw = *mut f32;
         type Field = &'h mut f32;
+        fn classes(_index: usize) -> &'static [&'static str] {
+            &[class::OUTPUTPORT, class::CONTROLPORT]
+        }
         fn new_raw() -> Self::FieldRaw {
             ptr::null_mut()
         }
-        fn cast_raw(data: *mut ()) -> Self::FieldRaw {
-            data as Self::FieldRaw
+        fn connect(raw: &mut Self::FieldRaw, _index: usize, data: *mut ()) {
+            *raw = data as Self::FieldRaw;
+        }
+        fn is_connected(raw: Self::FieldRaw) -> bool {
+            !raw.is_null()
         }
         fn convert(raw: Self::FieldRaw, _: usize) -> Self::Field {
             unsafe { mem::transmute(raw) }
@@ -200,11 +1038,17 @@ pub mod meta {
     unsafe impl<'h> Port<'h> for InputAudio {
         type FieldRaw = *const f32;
         type Field = &'h [f32];
+        fn classes(_index: usize) -> &'static [&'static str] {
+            &[class::INPUTPORT, class::AUDIOPORT]
+        }
         fn new_raw() -> Self::FieldRaw {
             ptr::null()
         }
-        fn cast_raw(data: *mut ()) -> Self::FieldRaw {
-            data as Self::FieldRaw
+        fn connect(raw: &mut Self::FieldRaw, _index: usize, data: *mut ()) {
+            *raw = data as Self::FieldRaw;
+        }
+        fn is_connected(raw: Self::FieldRaw) -> bool {
+            !raw.is_null()
         }
         fn convert(raw: Self::FieldRaw, sample_count: usize) -> Self::Field {
             unsafe { slice::from_raw_parts(raw, sample_count) }
@@ -215,15 +1059,112 @@ pub mod meta {
     unsafe impl<'h> Port<'h> for OutputAudio {
         type FieldRaw = *mut f32;
         type Field = &'h mut [f32];
+        fn classes(_index: usize) -> &'static [&'static str] {
+            &[class::OUTPUTPORT, class::AUDIOPORT]
+        }
+        fn new_raw() -> Self::FieldRaw {
+            ptr::null_mut()
+        }
+        fn connect(raw: &mut Self::FieldRaw, _index: usize, data: *mut ()) {
+            *raw = data as Self::FieldRaw;
+        }
+        fn is_connected(raw: Self::FieldRaw) -> bool {
+            !raw.is_null()
+        }
+        fn convert(raw: Self::FieldRaw, sample_count: usize) -> Self::Field {
+            unsafe { slice::from_raw_parts_mut(raw, sample_count) }
+        }
+        fn silence(raw: Self::FieldRaw, sample_count: usize) {
+            if !raw.is_null() {
+                unsafe { ptr::write_bytes(raw, 0, sample_count) }
+            }
+        }
+    }
+
+    /// An input and an output audio port at consecutive indices, that the
+    /// host may connect to the same buffer.
+    pub enum InPlaceAudio {}
+    unsafe impl<'h> Port<'h> for InPlaceAudio {
+        type FieldRaw = (*const f32, *mut f32);
+        type Field = InPlace<'h>;
+        const PORT_COUNT: usize = 2;
+        fn classes(index: usize) -> &'static [&'static str] {
+            if index == 0 {
+                &[class::INPUTPORT, class::AUDIOPORT]
+            }
+            else {
+                &[class::OUTPUTPORT, class::AUDIOPORT]
+            }
+        }
+        fn new_raw() -> Self::FieldRaw {
+            (ptr::null(), ptr::null_mut())
+        }
+        fn connect(raw: &mut Self::FieldRaw, index: usize, data: *mut ()) {
+            if index == 0 {
+                raw.0 = data as *const f32;
+            }
+            else {
+                raw.1 = data as *mut f32;
+            }
+        }
+        fn is_connected(raw: Self::FieldRaw) -> bool {
+            !raw.0.is_null() && !raw.1.is_null()
+        }
+        fn convert(raw: Self::FieldRaw, sample_count: usize) -> Self::Field {
+            unsafe { InPlace::new(raw.0, raw.1, sample_count) }
+        }
+        fn silence(raw: Self::FieldRaw, sample_count: usize) {
+            if !raw.1.is_null() {
+                unsafe { ptr::write_bytes(raw.1, 0, sample_count) }
+            }
+        }
+    }
+
+    pub enum InputCV {}
+    unsafe impl<'h> Port<'h> for InputCV {
+        type FieldRaw = *const f32;
+        type Field = &'h [f32];
+        fn classes(_index: usize) -> &'static [&'static str] {
+            &[class::INPUTPORT, class::CVPORT]
+        }
+        fn new_raw() -> Self::FieldRaw {
+            ptr::null()
+        }
+        fn connect(raw: &mut Self::FieldRaw, _index: usize, data: *mut ()) {
+            *raw = data as Self::FieldRaw;
+        }
+        fn is_connected(raw: Self::FieldRaw) -> bool {
+            !raw.is_null()
+        }
+        fn convert(raw: Self::FieldRaw, sample_count: usize) -> Self::Field {
+            unsafe { slice::from_raw_parts(raw, sample_count) }
+        }
+    }
+
+    pub enum OutputCV {}
+    unsafe impl<'h> Port<'h> for OutputCV {
+        type FieldRaw = *mut f32;
+        type Field = &'h mut [f32];
+        fn classes(_index: usize) -> &'static [&'static str] {
+            &[class::OUTPUTPORT, class::CVPORT]
+        }
         fn new_raw() -> Self::FieldRaw {
             ptr::null_mut()
         }
-        fn cast_raw(data: *mut ()) -> Self::FieldRaw {
-            data as Self::FieldRaw
+        fn connect(raw: &mut Self::FieldRaw, _index: usize, data: *mut ()) {
+            *raw = data as Self::FieldRaw;
+        }
+        fn is_connected(raw: Self::FieldRaw) -> bool {
+            !raw.is_null()
         }
         fn convert(raw: Self::FieldRaw, sample_count: usize) -> Self::Field {
             unsafe { slice::from_raw_parts_mut(raw, sample_count) }
         }
+        fn silence(raw: Self::FieldRaw, sample_count: usize) {
+            if !raw.is_null() {
+                unsafe { ptr::write_bytes(raw, 0, sample_count) }
+            }
+        }
     }
 
 }
@@ -321,3 +1262,158 @@ pub mod inst {
     pub const SAMPLERATE:         &'static str = "http://lv2plug.in/ns/lv2core#sampleRate";
     pub const TOGGLED:            &'static str = "http://lv2plug.in/ns/lv2core#toggled";
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::meta::{InputControl, OutputAudio, Port};
+    use PortCollection;
+
+    use std::ffi::CString;
+
+    struct Gain;
+
+    #[derive(Copy, Clone)]
+    struct GainPortsRaw {
+        gain: *const f32,
+        out: *mut f32,
+    }
+
+    struct GainPorts<'h> {
+        gain: &'h f32,
+        out: &'h mut [f32],
+    }
+
+    unsafe impl<'h> Ported<'h> for Gain {
+        type Ports = GainPorts<'h>;
+        type PortsRaw = GainPortsRaw;
+        fn new_ports_raw() -> GainPortsRaw {
+            GainPortsRaw { gain: InputControl::new_raw(), out: OutputAudio::new_raw() }
+        }
+        fn connect_port(port: usize, data: *mut (), ports_raw: &mut GainPortsRaw) {
+            match port {
+                0 => InputControl::connect(&mut ports_raw.gain, 0, data),
+                1 => OutputAudio::connect(&mut ports_raw.out, 0, data),
+                _ => {},
+            }
+        }
+        fn convert_ports(ports_raw: GainPortsRaw, sample_count: usize) -> GainPorts<'h> {
+            meta::check_connected::<InputControl>(ports_raw.gain, 0, "gain");
+            meta::check_connected::<OutputAudio>(ports_raw.out, 1, "out");
+            GainPorts {
+                gain: InputControl::convert(ports_raw.gain, sample_count),
+                out: OutputAudio::convert(ports_raw.out, sample_count),
+            }
+        }
+        fn silence_outputs(ports_raw: GainPortsRaw, sample_count: usize) {
+            OutputAudio::silence(ports_raw.out, sample_count);
+        }
+        fn port_infos() -> Vec<PortInfo> {
+            Vec::new()
+        }
+    }
+
+    impl<'h> Plugin<'h> for Gain {
+        type Features = ();
+        fn new(_sample_rate: f64, _bundle_path: &str, _features: ()) -> Option<Gain> {
+            Some(Gain)
+        }
+        fn run(&mut self, ports: &mut GainPorts<'h>, _sample_count: usize) {
+            for s in ports.out.iter_mut() {
+                *s = *ports.gain;
+            }
+        }
+    }
+
+    fn with_instance<F: FnOnce(LV2_Handle)>(f: F) {
+        let bundle = CString::new("/").unwrap();
+        let handle = instantiate::<Gain>(ptr::null(), 48000.0, bundle.as_ptr(), ptr::null());
+        assert!(!handle.is_null());
+        f(handle);
+        cleanup::<Gain>(handle);
+    }
+
+    #[test]
+    fn run_connected() {
+        with_instance(|handle| {
+            let gain = 0.5f32;
+            let mut out = [1.0f32; 4];
+            connect_port::<Gain>(handle, 0, &gain as *const f32 as *mut c_void);
+            connect_port::<Gain>(handle, 1, out.as_mut_ptr() as *mut c_void);
+            run::<Gain>(handle, 4);
+            assert_eq!(out, [0.5; 4]);
+            assert!(!unsafe { PluginInstance::<Gain>::from_handle(handle) }.failed());
+        });
+    }
+
+    #[test]
+    fn optional_properties() {
+        let mut field = meta::FieldInfo::new(0, "gain");
+        field.properties = &[inst::TOGGLED];
+        let infos = field.port_infos::<meta::Optional<InputControl>>();
+        assert_eq!(infos[0].properties, vec![inst::CONNECTIONOPTIONAL, inst::TOGGLED]);
+        let infos = field.port_infos::<meta::Optional<meta::Optional<InputControl>>>();
+        assert_eq!(infos[0].properties, vec![inst::CONNECTIONOPTIONAL, inst::TOGGLED]);
+        meta::check_connected::<meta::Optional<InputControl>>(ptr::null(), 0, "gain");
+    }
+
+    #[test]
+    fn run_unconnected() {
+        with_instance(|handle| {
+            let mut out = [1.0f32; 4];
+            connect_port::<Gain>(handle, 1, out.as_mut_ptr() as *mut c_void);
+            run::<Gain>(handle, 4);
+            assert_eq!(out, [0.0; 4]);
+            assert!(unsafe { PluginInstance::<Gain>::from_handle(handle) }.failed());
+        });
+    }
+
+    struct Amp;
+
+    #[derive(PortCollection)]
+    #[Plugin = "Amp"]
+    #[Lv2Crate = "crate"]
+    struct AmpPorts<'h> {
+        #[port(name = "Gain", min = -90.0, max = 24.0, default = 0, unit = "db",
+               scale_point(label = "-5", value = -5.0),
+               scale_point(label = "+5", value = 5))]
+        gain: &'h f32,
+        #[port(symbols("in", "out"), names("In", "Out"))]
+        io: InPlace<'h>,
+        #[port(index = 4, toggled, property = "urn:lv2-rs:test")]
+        bypass: Option<&'h f32>,
+    }
+
+    #[test]
+    fn derive_port_infos() {
+        let infos = <Amp as Ported>::port_infos();
+        let symbols: Vec<_> = infos.iter().map(|info| (info.index, info.symbol.as_str())).collect();
+        assert_eq!(symbols, vec![(0, "gain"), (1, "in"), (2, "out"), (4, "bypass")]);
+        assert_eq!(infos[0].name, "Gain");
+        assert_eq!((infos[0].minimum, infos[0].maximum, infos[0].default),
+                   (Some(-90.0), Some(24.0), Some(0.0)));
+        assert_eq!(infos[0].unit, Some("http://lv2plug.in/ns/extensions/units#db"));
+        assert_eq!(infos[0].scale_points, vec![("-5", -5.0), ("+5", 5.0)]);
+        assert_eq!(infos[2].name, "Out");
+        assert_eq!(infos[3].properties,
+                   vec![inst::CONNECTIONOPTIONAL, inst::TOGGLED, "urn:lv2-rs:test"]);
+    }
+
+    #[test]
+    fn derive_connect() {
+        let gain = 2.0f32;
+        let input = [1.0f32; 4];
+        let mut output = [0.0f32; 4];
+        let mut raw = <Amp as Ported>::new_ports_raw();
+        <Amp as Ported>::connect_port(0, &gain as *const f32 as *mut (), &mut raw);
+        <Amp as Ported>::connect_port(1, input.as_ptr() as *mut (), &mut raw);
+        <Amp as Ported>::connect_port(2, output.as_mut_ptr() as *mut (), &mut raw);
+        <Amp as Ported>::connect_port(3, ptr::null_mut(), &mut raw);
+        let mut ports = <Amp as Ported>::convert_ports(raw, 4);
+        assert!(ports.bypass.is_none());
+        let gain = *ports.gain;
+        ports.io.process(|s| s * gain);
+        assert_eq!(output, [2.0; 4]);
+    }
+}
@@ -1,6 +1,9 @@
 
 pub mod core;
 pub mod log;
+pub mod options;
+pub mod state;
 pub mod urid;
+pub mod worker;
 
 pub use self::core::*;
@@ -0,0 +1,37 @@
+#![allow(non_camel_case_types)]
+
+use ffi::core::LV2_Handle;
+use ::urid;
+
+use libc::c_void;
+
+pub type LV2_Options_Context = u32;
+pub const LV2_OPTIONS_INSTANCE: LV2_Options_Context = 0;
+pub const LV2_OPTIONS_RESOURCE: LV2_Options_Context = 1;
+pub const LV2_OPTIONS_BLANK: LV2_Options_Context = 2;
+pub const LV2_OPTIONS_PORT: LV2_Options_Context = 3;
+
+#[repr(C)]
+pub struct LV2_Options_Option {
+    pub context: LV2_Options_Context,
+    pub subject: u32,
+    pub key: urid::URID,
+    pub size: u32,
+    pub type_: urid::URID,
+    pub value: *const c_void,
+}
+
+pub type LV2_Options_Status = u32;
+pub const LV2_OPTIONS_SUCCESS: LV2_Options_Status = 0;
+pub const LV2_OPTIONS_ERR_UNKNOWN: LV2_Options_Status = 1;
+pub const LV2_OPTIONS_ERR_BAD_SUBJECT: LV2_Options_Status = 1 << 1;
+pub const LV2_OPTIONS_ERR_BAD_KEY: LV2_Options_Status = 1 << 2;
+pub const LV2_OPTIONS_ERR_BAD_VALUE: LV2_Options_Status = 1 << 3;
+
+#[repr(C)]
+pub struct LV2_Options_Interface {
+    pub get: Option<extern "C" fn (instance: LV2_Handle,
+                                   options: *mut LV2_Options_Option) -> LV2_Options_Status>,
+    pub set: Option<extern "C" fn (instance: LV2_Handle,
+                                   options: *const LV2_Options_Option) -> LV2_Options_Status>,
+}
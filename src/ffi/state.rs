@@ -0,0 +1,76 @@
+#![allow(non_camel_case_types)]
+
+use ffi::core::{LV2_Feature, LV2_Handle};
+use ::urid;
+
+use libc::{c_char, c_void};
+
+pub type LV2_State_Handle = *mut c_void;
+pub type LV2_State_Map_Path_Handle = *mut c_void;
+pub type LV2_State_Make_Path_Handle = *mut c_void;
+pub type LV2_State_Free_Path_Handle = *mut c_void;
+
+pub type LV2_State_Flags = u32;
+pub const LV2_STATE_IS_POD: LV2_State_Flags = 1;
+pub const LV2_STATE_IS_PORTABLE: LV2_State_Flags = 1 << 1;
+pub const LV2_STATE_IS_NATIVE: LV2_State_Flags = 1 << 2;
+
+pub type LV2_State_Status = u32;
+pub const LV2_STATE_SUCCESS: LV2_State_Status = 0;
+pub const LV2_STATE_ERR_UNKNOWN: LV2_State_Status = 1;
+pub const LV2_STATE_ERR_BAD_TYPE: LV2_State_Status = 2;
+pub const LV2_STATE_ERR_BAD_FLAGS: LV2_State_Status = 3;
+pub const LV2_STATE_ERR_NO_FEATURE: LV2_State_Status = 4;
+pub const LV2_STATE_ERR_NO_PROPERTY: LV2_State_Status = 5;
+pub const LV2_STATE_ERR_NO_SPACE: LV2_State_Status = 6;
+
+pub type LV2_State_Store_Function = Option<extern "C" fn (handle: LV2_State_Handle,
+                                                          key: urid::URID,
+                                                          value: *const c_void,
+                                                          size: usize,
+                                                          type_: urid::URID,
+                                                          flags: u32) -> LV2_State_Status>;
+
+pub type LV2_State_Retrieve_Function = Option<extern "C" fn (handle: LV2_State_Handle,
+                                                             key: urid::URID,
+                                                             size: *mut usize,
+                                                             type_: *mut urid::URID,
+                                                             flags: *mut u32) -> *const c_void>;
+
+#[repr(C)]
+pub struct LV2_State_Interface {
+    pub save:    Option<extern "C" fn (instance: LV2_Handle,
+                                       store: LV2_State_Store_Function,
+                                       handle: LV2_State_Handle,
+                                       flags: u32,
+                                       features: *const *const LV2_Feature) -> LV2_State_Status>,
+
+    pub restore: Option<extern "C" fn (instance: LV2_Handle,
+                                       retrieve: LV2_State_Retrieve_Function,
+                                       handle: LV2_State_Handle,
+                                       flags: u32,
+                                       features: *const *const LV2_Feature) -> LV2_State_Status>,
+}
+
+#[repr(C)]
+pub struct LV2_State_Map_Path {
+    pub handle: LV2_State_Map_Path_Handle,
+    pub abstract_path: Option<extern "C" fn (handle: LV2_State_Map_Path_Handle,
+                                             absolute_path: *const c_char) -> *mut c_char>,
+    pub absolute_path: Option<extern "C" fn (handle: LV2_State_Map_Path_Handle,
+                                             abstract_path: *const c_char) -> *mut c_char>,
+}
+
+#[repr(C)]
+pub struct LV2_State_Make_Path {
+    pub handle: LV2_State_Make_Path_Handle,
+    pub path: Option<extern "C" fn (handle: LV2_State_Make_Path_Handle,
+                                    path: *const c_char) -> *mut c_char>,
+}
+
+#[repr(C)]
+pub struct LV2_State_Free_Path {
+    pub handle: LV2_State_Free_Path_Handle,
+    pub free_path: Option<extern "C" fn (handle: LV2_State_Free_Path_Handle,
+                                         path: *mut c_char)>,
+}
@@ -0,0 +1,41 @@
+#![allow(non_camel_case_types)]
+
+use ffi::core::LV2_Handle;
+
+use libc::c_void;
+
+pub type LV2_Worker_Status = u32;
+pub const LV2_WORKER_SUCCESS: LV2_Worker_Status = 0;
+pub const LV2_WORKER_ERR_UNKNOWN: LV2_Worker_Status = 1;
+pub const LV2_WORKER_ERR_NO_SPACE: LV2_Worker_Status = 2;
+
+pub type LV2_Worker_Respond_Handle = *mut c_void;
+
+pub type LV2_Worker_Respond_Function = Option<extern "C" fn (handle: LV2_Worker_Respond_Handle,
+                                                             size: u32,
+                                                             data: *const c_void) -> LV2_Worker_Status>;
+
+#[repr(C)]
+pub struct LV2_Worker_Interface {
+    pub work:          Option<extern "C" fn (instance: LV2_Handle,
+                                             respond: LV2_Worker_Respond_Function,
+                                             handle: LV2_Worker_Respond_Handle,
+                                             size: u32,
+                                             data: *const c_void) -> LV2_Worker_Status>,
+
+    pub work_response: Option<extern "C" fn (instance: LV2_Handle,
+                                             size: u32,
+                                             body: *const c_void) -> LV2_Worker_Status>,
+
+    pub end_run:       Option<extern "C" fn (instance: LV2_Handle) -> LV2_Worker_Status>,
+}
+
+pub type LV2_Worker_Schedule_Handle = *mut c_void;
+
+#[repr(C)]
+pub struct LV2_Worker_Schedule {
+    pub handle: LV2_Worker_Schedule_Handle,
+    pub schedule_work: Option<extern "C" fn (handle: LV2_Worker_Schedule_Handle,
+                                             size: u32,
+                                             data: *const c_void) -> LV2_Worker_Status>,
+}
@@ -1,17 +1,35 @@
 
 extern crate libc;
-#[cfg(feature = "atom")]
-#[macro_use]
+#[cfg_attr(feature = "atom", macro_use)]
 extern crate lv2_derive;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
 
 #[cfg(feature = "atom")]
 pub mod atom;
+#[cfg(not(feature = "atom"))]
+#[path = "atom/uris.rs"]
+pub mod atom;
+pub mod buf_size;
 pub mod core;
 pub mod log;
+pub mod metadata;
 pub mod midi;
+pub mod options;
+pub mod parameters;
+pub mod port_groups;
+pub mod state;
+pub mod time;
+pub mod turtle;
+pub mod units;
 pub mod urid;
+pub mod worker;
 
 pub mod ffi;
 pub mod macros;
 
 pub use self::core::*;
+pub use self::metadata::Metadata;
+pub use lv2_derive::PortCollection;
@@ -1,8 +1,16 @@
 
+/// Exports the `lv2_descriptor` entry point for a list of plugins.
+/// Each plugin is given either as `"uri" => Type`, or as `Type` if it
+/// implements `Metadata`, in which case its Turtle data can also be
+/// generated from the shared library through the exported `lv2_rs_turtle`.
 #[macro_export]
 macro_rules! lv2_descriptor {
 
     (@desc $DESC:ident { $uri:expr => $Plug:ty }) => {
+        lv2_descriptor!{ @desc $DESC { $Plug } }
+    };
+
+    (@desc $DESC:ident { $Plug:ty }) => {
         static mut $DESC: $crate::ffi::LV2_Descriptor = $crate::ffi::LV2_Descriptor {
             URI: b"\0" as *const u8 as _,
             instantiate: Some($crate::instantiate::<$Plug>),
@@ -11,15 +19,44 @@ macro_rules! lv2_descriptor {
             run: Some($crate::run::<$Plug>),
             deactivate: Some($crate::deactivate::<$Plug>),
             cleanup: Some($crate::cleanup::<$Plug>),
-            extension_data: None,
+            extension_data: Some($crate::extension_data::<$Plug>),
         };
     };
 
+    (@uri $DESC:ident { $uri:expr => $Plug:ty }) => {
+        $DESC.URI = concat!($uri, "\0").as_ptr() as _;
+    };
+
+    (@uri $DESC:ident { $Plug:ty }) => {
+        // the descriptor is not returned if the URI has a nul byte or panics
+        static URI: ::std::sync::OnceLock<Option<::std::ffi::CString>> =
+            ::std::sync::OnceLock::new();
+        let uri = URI.get_or_init(|| {
+            let uri = ::std::panic::catch_unwind(|| {
+                ::std::ffi::CString::new(<$Plug as $crate::Metadata>::uri()).ok()
+            }).unwrap_or(None);
+            if let Some(ref uri) = uri {
+                $DESC.URI = uri.as_ptr();
+            }
+            uri
+        });
+        if uri.is_none() {
+            return ::std::ptr::null();
+        }
+    };
+
+    (@turtle { $uri:expr => $Plug:ty } $( $arg:expr ),*) => {
+        ::std::ptr::null_mut()
+    };
+
+    (@turtle { $Plug:ty } $( $arg:expr ),*) => {
+        $crate::metadata::export_turtle::<$Plug>( $( $arg ),* )
+    };
 
-    ( $( $idx:expr => $DESC:ident { $uri:expr => $Plug:ty } ),+ ) => {
+    ( $( $idx:expr => $DESC:ident { $( $body:tt )+ } ),+ ) => {
 
         $(
-            lv2_descriptor!{ @desc $DESC { $uri => $Plug } }
+            lv2_descriptor!{ @desc $DESC { $( $body )+ } }
         )+
 
         #[no_mangle]
@@ -28,7 +65,7 @@ macro_rules! lv2_descriptor {
             match index {
                 $(
                     $idx => {
-                        $DESC.URI = concat!($uri, "\0").as_ptr() as _;
+                        lv2_descriptor!{ @uri $DESC { $( $body )+ } }
                         &$DESC
                     },
                 )+
@@ -39,6 +76,27 @@ macro_rules! lv2_descriptor {
             }
         }
 
+        #[no_mangle]
+        pub unsafe extern "C" fn lv2_rs_turtle (index: u32, kind: u32,
+                                                binary: *const ::std::os::raw::c_char,
+                                                see_also: *const ::std::os::raw::c_char,
+                                                error: *mut *mut ::std::os::raw::c_char)
+                -> *mut ::std::os::raw::c_char
+        {
+            match index {
+                $(
+                    $idx => lv2_descriptor!(@turtle { $( $body )+ } kind, binary, see_also, error),
+                )+
+                _ => ::std::ptr::null_mut()
+            }
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn lv2_rs_free_turtle (ttl: *mut ::std::os::raw::c_char)
+        {
+            $crate::metadata::free_turtle(ttl)
+        }
+
     };
 
 }
@@ -72,41 +130,128 @@ macro_rules! lv2_ports {
             }
 
             fn connect_port(port: usize, data: *mut (), ports_raw: &mut Self::PortsRaw) {
-                match port {
-                    $(
-                        $idx => { ports_raw.$name = <$Meta as $crate::meta::Port<'h>>::cast_raw(data); }
-                    ),+
-                    _ => {},
-                }
+                $(
+                    let index = port.wrapping_sub($idx);
+                    if index < <$Meta as $crate::meta::Port<'h>>::PORT_COUNT {
+                        <$Meta as $crate::meta::Port<'h>>::connect(&mut ports_raw.$name, index, data);
+                        return;
+                    }
+                )+
             }
 
             fn convert_ports(ports_raw: Self::PortsRaw, sample_count: usize) -> Self::Ports {
+                $(
+                    $crate::meta::check_connected::<$Meta>(
+                        ports_raw.$name, $idx, stringify!($name)
+                    );
+                )+
                 Self::Ports {
                     $(
                         $name: <$Meta as $crate::meta::Port<'h>>::convert(ports_raw.$name, sample_count)
                     ),+
                 }
             }
+
+            fn silence_outputs(ports_raw: Self::PortsRaw, sample_count: usize) {
+                $(
+                    <$Meta as $crate::meta::Port<'h>>::silence(ports_raw.$name, sample_count);
+                )+
+            }
+
+            fn port_infos() -> Vec<$crate::PortInfo> {
+                let mut infos = Vec::new();
+                $(
+                    infos.extend($crate::meta::FieldInfo::new($idx, stringify!($name))
+                        .port_infos::<$Meta>());
+                )+
+                infos.sort_by_key(|info| info.index);
+                infos
+            }
         }
 
     }
 }
 
 #[macro_export]
-macro_rules! lv2_features_query {
-    ($list:expr, $( ($name:ident <= $Feat:ty) ),+ ) => {
-        let ($($name),+) = {
-            $(let mut $name = None;)+
-            for f in $list {
+macro_rules! lv2_features {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $Name:ident < $lt:lifetime > {
+            $( required $req:ident : $ReqFeat:ty, )*
+            $( optional $opt:ident : $OptFeat:ty, )*
+        }
+    ) => {
+
+        $(#[$attr])*
+        $vis struct $Name<$lt> {
+            $( pub $req: $ReqFeat, )*
+            $( pub $opt: Option<$OptFeat>, )*
+        }
+
+        impl<$lt> $crate::FeatureSet<$lt> for $Name<$lt> {
+            fn required_features() -> Vec<&'static str> {
+                vec![ $( <$ReqFeat as $crate::Feature<$lt>>::uri() ),* ]
+            }
+
+            fn optional_features() -> Vec<&'static str> {
+                vec![ $( <$OptFeat as $crate::Feature<$lt>>::uri() ),* ]
+            }
+
+            fn resolve(features: $crate::FeatureList<$lt>) -> Result<Self, Vec<&'static str>> {
+                $( let mut $req = None; )*
+                $( let mut $opt = None; )*
+                for f in features {
+                    $(
+                        if f.uri() == <$ReqFeat as $crate::Feature<$lt>>::uri() {
+                            $req = Some(unsafe {
+                                <$ReqFeat as $crate::Feature<$lt>>::from_raw(&f)
+                            });
+                        }
+                    )*
+                    $(
+                        if f.uri() == <$OptFeat as $crate::Feature<$lt>>::uri() {
+                            $opt = Some(unsafe {
+                                <$OptFeat as $crate::Feature<$lt>>::from_raw(&f)
+                            });
+                        }
+                    )*
+                }
+                #[allow(unused_mut)]
+                let mut missing = Vec::new();
                 $(
-                    if f.uri() == <$Feat as $crate::Feature>::uri() {
-                        $name = Some(unsafe {
-                            <$Feat as $crate::Feature>::from_raw(&f)
-                        });
+                    if $req.is_none() {
+                        missing.push(<$ReqFeat as $crate::Feature<$lt>>::uri());
                     }
-                )+
+                )*
+                if !missing.is_empty() {
+                    return Err(missing);
+                }
+                Ok($Name {
+                    $( $req: $req.unwrap(), )*
+                    $( $opt: $opt, )*
+                })
             }
-            ($($name),+)
-        };
+        }
+    };
+}
+
+/// Implements the extension methods of `Plugin` for a list of `Extension`
+/// interfaces, inside the `impl Plugin` block.
+#[macro_export]
+macro_rules! lv2_extensions {
+    ( $( $Ext:ty ),* ) => {
+        fn extension_data(uri: &str) -> Option<*const ::std::os::raw::c_void> {
+            <( $( $Ext, )* ) as $crate::Extensions<'_, Self>>::interface(uri)
+        }
+
+        fn extension_uris() -> Vec<&'static str> {
+            <( $( $Ext, )* ) as $crate::Extensions<'_, Self>>::uris()
+        }
+
+        fn extension_shared(&self)
+            -> Option<::std::sync::Arc<dyn (::std::any::Any) + Send + Sync>>
+        {
+            <( $( $Ext, )* ) as $crate::Extensions<'_, Self>>::shared(self)
+        }
     };
 }
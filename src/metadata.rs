@@ -0,0 +1,415 @@
+//! Generation of the Turtle data describing plugins: the bundle `manifest.ttl`
+//! and the plugin description, built from the Rust declarations.
+
+use core::{class, prop, FeatureSet, Plugin, PortInfo};
+use port_groups;
+use turtle::{self, doap, rdf, rdfs, Document, Graph, Node, Object, Term};
+use units;
+
+use libc::c_char;
+
+use std::error;
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+
+/// Static description of a plugin.
+pub trait Metadata {
+    /// The plugin URI.
+    fn uri() -> &'static str;
+    /// The `doap:name` of the plugin.
+    fn name() -> &'static str;
+    /// Classes of the plugin in addition to `lv2:Plugin`, e.g. `class::AMPLIFIERPLUGIN`.
+    fn classes() -> &'static [&'static str] {
+        &[]
+    }
+    fn license() -> Option<&'static str> {
+        None
+    }
+    fn project() -> Option<&'static str> {
+        None
+    }
+    /// Optional features not requested through `Plugin::Features`, e.g. `inst::HARDRTCAPABLE`.
+    fn optional_features() -> &'static [&'static str] {
+        &[]
+    }
+}
+
+/// Error returned when a generated description does not match the declarations.
+#[derive(Debug)]
+pub enum Error {
+    Parse(turtle::ParseError),
+    Invalid(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Parse(ref err) => write!(f, "invalid Turtle: {}", err),
+            Error::Invalid(ref msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<turtle::ParseError> for Error {
+    fn from(err: turtle::ParseError) -> Error {
+        Error::Parse(err)
+    }
+}
+
+
+/// A document declaring the prefixes of the common LV2 vocabularies.
+pub fn document() -> Document {
+    let mut doc = Document::new();
+    doc.prefix("atom", "http://lv2plug.in/ns/ext/atom#")
+        .prefix("doap", "http://usefulinc.com/ns/doap#")
+        .prefix("log", "http://lv2plug.in/ns/ext/log#")
+        .prefix("lv2", "http://lv2plug.in/ns/lv2core#")
+        .prefix("midi", "http://lv2plug.in/ns/ext/midi#")
+        .prefix("pg", "http://lv2plug.in/ns/ext/port-groups#")
+        .prefix("rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#")
+        .prefix("rdfs", "http://www.w3.org/2000/01/rdf-schema#")
+        .prefix("units", "http://lv2plug.in/ns/extensions/units#")
+        .prefix("urid", "http://lv2plug.in/ns/ext/urid#")
+        .prefix("xsd", "http://www.w3.org/2001/XMLSchema#");
+    doc
+}
+
+/// The manifest entry of plugin `P`, found in the shared library `binary`
+/// and described in `see_also`, both relative to the bundle.
+pub fn manifest_node<'h, P: Metadata + Plugin<'h>>(binary: &str, see_also: &str) -> Node {
+    Node::new(Term::iri(P::uri()))
+        .with(rdf::TYPE, Term::iri(class::PLUGIN))
+        .with(prop::BINARY, Term::iri(binary))
+        .with(rdfs::SEEALSO, Term::iri(see_also))
+}
+
+fn group_uri<'h, P: Metadata + Plugin<'h>>(symbol: &str) -> String {
+    format!("{}#{}", P::uri(), symbol)
+}
+
+fn port_node<'h, P: Metadata + Plugin<'h>>(info: &PortInfo) -> Node {
+    let mut node = Node::blank();
+    for class in info.classes {
+        node.add(rdf::TYPE, Term::iri(*class));
+    }
+    node.add(prop::INDEX, Term::integer(info.index as i64))
+        .add(prop::SYMBOL, Term::string(info.symbol.as_str()))
+        .add(prop::NAME, Term::string(info.name.as_str()));
+    if let Some(default) = info.default {
+        node.add(prop::DEFAULT, Term::number(default));
+    }
+    if let Some(minimum) = info.minimum {
+        node.add(prop::MINIMUM, Term::number(minimum));
+    }
+    if let Some(maximum) = info.maximum {
+        node.add(prop::MAXIMUM, Term::number(maximum));
+    }
+    if let Some(unit) = info.unit {
+        node.add(units::prop::UNIT, Term::iri(unit));
+    }
+    for property in &info.properties {
+        node.add(prop::PORTPROPERTY, Term::iri(*property));
+    }
+    if let Some(designation) = info.designation {
+        node.add(prop::DESIGNATION, Term::iri(designation));
+    }
+    if let Some((ref symbol, _)) = info.group {
+        node.add(port_groups::prop::GROUP, Term::iri(group_uri::<P>(symbol)));
+    }
+    for &(predicate, object) in &info.statements {
+        node.add(predicate, Term::iri(object));
+    }
+    for &(label, value) in &info.scale_points {
+        node.add(prop::SCALEPOINT, Node::blank()
+            .with(rdfs::LABEL, Term::string(label))
+            .with(rdf::VALUE, Term::number(value)));
+    }
+    node
+}
+
+/// The description of plugin `P` and of its port groups.
+pub fn plugin_nodes<'h, P: Metadata + Plugin<'h>>() -> Vec<Node> {
+    let infos = P::port_infos();
+    let mut plugin = Node::new(Term::iri(P::uri()));
+    plugin.add(rdf::TYPE, Term::iri(class::PLUGIN));
+    for class in P::classes() {
+        plugin.add(rdf::TYPE, Term::iri(*class));
+    }
+    plugin.add(doap::NAME, Term::string(P::name()));
+    if let Some(license) = P::license() {
+        plugin.add(doap::LICENSE, Term::iri(license));
+    }
+    if let Some(project) = P::project() {
+        plugin.add(prop::PROJECT, Term::iri(project));
+    }
+    for feature in P::Features::required_features() {
+        plugin.add(prop::REQUIREDFEATURE, Term::iri(feature));
+    }
+    for feature in P::Features::optional_features().iter().chain(P::optional_features()) {
+        plugin.add(prop::OPTIONALFEATURE, Term::iri(*feature));
+    }
+    for ext in P::extension_uris() {
+        plugin.add(prop::EXTENSIONDATA, Term::iri(ext));
+    }
+
+    let mut groups: Vec<Node> = Vec::new();
+    for info in &infos {
+        let (symbol, group) = match info.group {
+            Some((ref symbol, group)) => (symbol, group),
+            None => continue,
+        };
+        let uri = Term::iri(group_uri::<P>(symbol));
+        if groups.iter().any(|g| g.subject.as_ref() == Some(&uri)) {
+            continue;
+        }
+        if group.main {
+            let main = if group.input {
+                port_groups::prop::MAININPUT
+            } else {
+                port_groups::prop::MAINOUTPUT
+            };
+            plugin.add(main, uri.clone());
+        }
+        let direction = if group.input {
+            port_groups::class::INPUTGROUP
+        } else {
+            port_groups::class::OUTPUTGROUP
+        };
+        groups.push(Node::new(uri)
+            .with(rdf::TYPE, Term::iri(group.class))
+            .with(rdf::TYPE, Term::iri(direction))
+            .with(prop::SYMBOL, Term::string(symbol.as_str())));
+    }
+
+    for info in &infos {
+        plugin.add(prop::PORT, Object::Node(port_node::<P>(info)));
+    }
+
+    let mut nodes = vec![plugin];
+    nodes.extend(groups);
+    nodes
+}
+
+/// The `manifest.ttl` of a bundle containing only plugin `P`.
+pub fn manifest<'h, P: Metadata + Plugin<'h>>(binary: &str, see_also: &str) -> String {
+    let mut doc = document();
+    doc.push(manifest_node::<P>(binary, see_also));
+    doc.to_string()
+}
+
+/// The description of plugin `P`, validated against its declarations.
+pub fn description<'h, P: Metadata + Plugin<'h>>() -> Result<String, Error> {
+    let mut doc = document();
+    for node in plugin_nodes::<P>() {
+        doc.push(node);
+    }
+    let ttl = doc.to_string();
+    validate::<P>(&ttl)?;
+    Ok(ttl)
+}
+
+
+fn invalid<T>(msg: String) -> Result<T, Error> {
+    Err(Error::Invalid(msg))
+}
+
+fn iris<'a>(graph: &'a Graph, subject: &'a Term, predicate: &'a str) -> Vec<&'a str> {
+    let mut iris: Vec<_> = graph.objects(subject, predicate).filter_map(|o| o.as_iri()).collect();
+    iris.sort();
+    iris
+}
+
+fn sorted<'a, I: IntoIterator<Item = &'a str>>(iter: I) -> Vec<&'a str> {
+    let mut v: Vec<_> = iter.into_iter().collect();
+    v.sort();
+    v
+}
+
+fn is_symbol(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {},
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn check_number(graph: &Graph, port: &Term, predicate: &str, expected: Option<f32>, symbol: &str)
+        -> Result<(), Error> {
+    let found = graph.object(port, predicate).map(|t| t.as_f64().map(|v| v as f32));
+    match (expected, found) {
+        (None, None) => Ok(()),
+        (Some(e), Some(Some(f))) if e == f || e.is_nan() && f.is_nan() => Ok(()),
+        (_, Some(None)) => invalid(format!("port \"{}\": {} is not a number", symbol, predicate)),
+        (_, Some(Some(f))) => invalid(format!("port \"{}\": {} is {:?}, expected {:?}",
+                                             symbol, predicate, f, expected)),
+        (_, None) => invalid(format!("port \"{}\": missing {}", symbol, predicate)),
+    }
+}
+
+fn check_port(graph: &Graph, port: &Term, info: &PortInfo, group: Option<&str>)
+        -> Result<(), Error> {
+    let sym = info.symbol.as_str();
+    let symbol = graph.object(port, prop::SYMBOL).and_then(|t| t.as_str());
+    if symbol != Some(sym) {
+        return invalid(format!("port {}: symbol is {:?}, expected \"{}\"", info.index, symbol, sym));
+    }
+    let name = graph.object(port, prop::NAME).and_then(|t| t.as_str());
+    if name != Some(info.name.as_str()) {
+        return invalid(format!("port \"{}\": name is {:?}, expected \"{}\"", sym, name, info.name));
+    }
+    if iris(graph, port, rdf::TYPE) != sorted(info.classes.iter().cloned()) {
+        return invalid(format!("port \"{}\": classes do not match", sym));
+    }
+    if iris(graph, port, prop::PORTPROPERTY) != sorted(info.properties.iter().cloned()) {
+        return invalid(format!("port \"{}\": port properties do not match", sym));
+    }
+    if iris(graph, port, prop::DESIGNATION) != sorted(info.designation) {
+        return invalid(format!("port \"{}\": designation does not match", sym));
+    }
+    if iris(graph, port, units::prop::UNIT) != sorted(info.unit) {
+        return invalid(format!("port \"{}\": unit does not match", sym));
+    }
+    if iris(graph, port, port_groups::prop::GROUP) != sorted(group) {
+        return invalid(format!("port \"{}\": group does not match", sym));
+    }
+    for &(predicate, object) in &info.statements {
+        if !graph.objects(port, predicate).any(|o| o.as_iri() == Some(object)) {
+            return invalid(format!("port \"{}\": missing {} {}", sym, predicate, object));
+        }
+    }
+    check_number(graph, port, prop::DEFAULT, info.default, sym)?;
+    check_number(graph, port, prop::MINIMUM, info.minimum, sym)?;
+    check_number(graph, port, prop::MAXIMUM, info.maximum, sym)?;
+
+    let points: Vec<_> = graph.objects(port, prop::SCALEPOINT).collect();
+    if points.len() != info.scale_points.len() {
+        return invalid(format!("port \"{}\": scale points do not match", sym));
+    }
+    for &(label, value) in &info.scale_points {
+        let found = points.iter().any(|p| {
+            graph.object(p, rdfs::LABEL).and_then(|t| t.as_str()) == Some(label)
+                && graph.object(p, rdf::VALUE).and_then(|t| t.as_f64()) == Some(value as f64)
+        });
+        if !found {
+            return invalid(format!("port \"{}\": missing scale point \"{}\"", sym, label));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the Turtle description `ttl` matches the declarations of plugin `P`,
+/// and that the port indices and symbols are valid.
+pub fn validate<'h, P: Metadata + Plugin<'h>>(ttl: &str) -> Result<(), Error> {
+    let graph = Graph::parse(ttl)?;
+    let plugin = Term::iri(P::uri());
+
+    let types = iris(&graph, &plugin, rdf::TYPE);
+    if !types.contains(&class::PLUGIN) {
+        return invalid(format!("<{}> is not described as a lv2:Plugin", P::uri()));
+    }
+    if let Some(c) = P::classes().iter().find(|c| !types.contains(c)) {
+        return invalid(format!("missing plugin class <{}>", c));
+    }
+    let name = graph.object(&plugin, doap::NAME).and_then(|t| t.as_str());
+    if name != Some(P::name()) {
+        return invalid(format!("plugin name is {:?}, expected \"{}\"", name, P::name()));
+    }
+    let required = P::Features::required_features();
+    if iris(&graph, &plugin, prop::REQUIREDFEATURE) != sorted(required.iter().cloned()) {
+        return invalid("required features do not match".to_string());
+    }
+
+    let infos = P::port_infos();
+    let ports: Vec<_> = graph.objects(&plugin, prop::PORT).collect();
+    if ports.len() != infos.len() {
+        return invalid(format!("{} ports described, {} declared", ports.len(), infos.len()));
+    }
+    let mut symbols = Vec::new();
+    for (i, info) in infos.iter().enumerate() {
+        if info.index != i {
+            return invalid(format!("port indices are not contiguous: expected {}, found {}",
+                                   i, info.index));
+        }
+        if !is_symbol(&info.symbol) {
+            return invalid(format!("\"{}\" is not a valid port symbol", info.symbol));
+        }
+        if symbols.contains(&info.symbol.as_str()) {
+            return invalid(format!("duplicate port symbol \"{}\"", info.symbol));
+        }
+        symbols.push(info.symbol.as_str());
+
+        let mut matching = ports.iter()
+            .filter(|p| graph.object(p, prop::INDEX).and_then(|t| t.as_i64()) == Some(i as i64));
+        let port = match (matching.next(), matching.next()) {
+            (Some(port), None) => port,
+            _ => return invalid(format!("expected exactly one port with index {}", i)),
+        };
+        let group = info.group.as_ref().map(|&(ref symbol, _)| group_uri::<P>(symbol));
+        check_port(&graph, port, info, group.as_deref())?;
+        if let Some(group) = group {
+            let group = Term::iri(group);
+            let class = info.group.as_ref().unwrap().1.class;
+            if !iris(&graph, &group, rdf::TYPE).contains(&class) {
+                return invalid(format!("port \"{}\": group is not described", info.symbol));
+            }
+        }
+    }
+    Ok(())
+}
+
+
+/// Document kinds produced by `export_turtle`.
+pub const MANIFEST: u32 = 0;
+pub const DESCRIPTION: u32 = 1;
+
+/// Returns a newly allocated document of `kind` for plugin `P`, to be released
+/// with `free_turtle`. On failure, returns null and stores in `error`, if not
+/// null, a message released the same way.
+/// Backs the `lv2_rs_turtle` symbol exported by `lv2_descriptor!`, which lets
+/// tools generate the data of a bundle from its shared library.
+pub unsafe fn export_turtle<'h, P: Metadata + Plugin<'h>>(kind: u32, binary: *const c_char,
+                                                          see_also: *const c_char,
+                                                          error: *mut *mut c_char)
+        -> *mut c_char {
+    let res = panic::catch_unwind(AssertUnwindSafe(|| {
+        match kind {
+            MANIFEST => {
+                let binary = CStr::from_ptr(binary).to_string_lossy();
+                let see_also = CStr::from_ptr(see_also).to_string_lossy();
+                Ok(manifest::<P>(&binary, &see_also))
+            },
+            DESCRIPTION => description::<P>().map_err(|err| err.to_string()),
+            _ => Err(format!("unknown document kind {}", kind)),
+        }
+    }));
+    let res = match res {
+        Ok(res) => res,
+        Err(_) => Err("panicked while generating the document".to_string()),
+    };
+    match res {
+        Ok(ttl) => export_string(ttl),
+        Err(err) => {
+            if !error.is_null() {
+                *error = export_string(format!("<{}>: {}", P::uri(), err));
+            }
+            ptr::null_mut()
+        },
+    }
+}
+
+fn export_string(s: String) -> *mut c_char {
+    CString::new(s).map(|s| s.into_raw()).unwrap_or(ptr::null_mut())
+}
+
+/// Releases a document returned by `export_turtle`.
+pub unsafe fn free_turtle(ttl: *mut c_char) {
+    if !ttl.is_null() {
+        drop(CString::from_raw(ttl));
+    }
+}
@@ -0,0 +1,566 @@
+use ::atom;
+
+use ::core::{Extension, Feature, Plugin, PluginInstance, RawFeature};
+use ::ffi::core::LV2_Handle;
+use ::ffi::options::*;
+use ::urid::{self, URID};
+
+use libc::c_void;
+use std::error;
+use std::fmt;
+use std::marker;
+use std::mem;
+use std::ptr;
+use std::slice;
+use std::str;
+
+pub const URI: &'static str = "http://lv2plug.in/ns/ext/options";
+
+pub mod class {
+    pub const OPTION          : &'static str = "http://lv2plug.in/ns/ext/options#Option";
+}
+pub mod prop {
+    pub const REQUIREDOPTION  : &'static str = "http://lv2plug.in/ns/ext/options#requiredOption";
+    pub const SUPPORTEDOPTION : &'static str = "http://lv2plug.in/ns/ext/options#supportedOption";
+}
+pub mod inst {
+    pub const INTERFACE       : &'static str = "http://lv2plug.in/ns/ext/options#interface";
+    pub const OPTIONS         : &'static str = "http://lv2plug.in/ns/ext/options#options";
+}
+
+
+/// Error of an options operation, mapped to the LV2 status codes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    Unknown,
+    BadSubject,
+    BadKey,
+    BadValue,
+}
+
+impl Error {
+    fn status(res: Result<(), Error>) -> LV2_Options_Status {
+        match res {
+            Ok(()) => LV2_OPTIONS_SUCCESS,
+            Err(Error::Unknown) => LV2_OPTIONS_ERR_UNKNOWN,
+            Err(Error::BadSubject) => LV2_OPTIONS_ERR_BAD_SUBJECT,
+            Err(Error::BadKey) => LV2_OPTIONS_ERR_BAD_KEY,
+            Err(Error::BadValue) => LV2_OPTIONS_ERR_BAD_VALUE,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Error::Unknown => "unknown options error",
+            Error::BadSubject => "unsupported option subject",
+            Error::BadKey => "unsupported option key",
+            Error::BadValue => "invalid option value",
+        })
+    }
+}
+
+impl error::Error for Error {}
+
+
+/// What an option applies to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Subject {
+    /// The plugin instance.
+    Instance,
+    /// The resource identified by the URID.
+    Resource(URID),
+    /// The blank node identified by the id.
+    Blank(u32),
+    /// The port of the given index.
+    Port(u32),
+}
+
+impl Subject {
+    fn from_raw(context: LV2_Options_Context, subject: u32) -> Option<Subject> {
+        match context {
+            LV2_OPTIONS_INSTANCE => Some(Subject::Instance),
+            LV2_OPTIONS_RESOURCE => Some(Subject::Resource(subject)),
+            LV2_OPTIONS_BLANK => Some(Subject::Blank(subject)),
+            LV2_OPTIONS_PORT => Some(Subject::Port(subject)),
+            _ => None,
+        }
+    }
+}
+
+
+/// URIDs of the atom types options are decoded to.
+#[derive(Copy, Clone, Debug)]
+pub struct Types {
+    pub int: URID,
+    pub long: URID,
+    pub float: URID,
+    pub double: URID,
+    pub bool: URID,
+    pub urid: URID,
+    pub string: URID,
+}
+
+impl Types {
+    pub fn new(map: &urid::Map) -> Types {
+        Types {
+            int: map.map(atom::class::INT),
+            long: map.map(atom::class::LONG),
+            float: map.map(atom::class::FLOAT),
+            double: map.map(atom::class::DOUBLE),
+            bool: map.map(atom::class::BOOL),
+            urid: map.map(atom::class::URID),
+            string: map.map(atom::class::STRING),
+        }
+    }
+}
+
+
+/// A typed option value.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Value<'a> {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Bool(bool),
+    URID(URID),
+    String(&'a str),
+    /// A value of another type, or of unexpected size.
+    Other(RawValue<'a>),
+}
+
+impl<'a> Value<'a> {
+    /// The value as an integer, if it is an integer or boolean.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Value::Int(v) => Some(v as i64),
+            Value::Long(v) => Some(v),
+            Value::Bool(v) => Some(v as i64),
+            _ => None,
+        }
+    }
+
+    /// The value as a float, if it is numeric.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Float(v) => Some(v as f64),
+            Value::Double(v) => Some(v),
+            _ => self.as_i64().map(|v| v as f64),
+        }
+    }
+}
+
+
+/// An option value as laid out in memory: an atom type and body.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RawValue<'a> {
+    type_urid: URID,
+    body: &'a [u8],
+}
+
+impl<'a> RawValue<'a> {
+    pub fn new(type_urid: URID, body: &'a [u8]) -> RawValue<'a> {
+        RawValue { type_urid: type_urid, body: body }
+    }
+    pub fn int(value: &'a i32, types: &Types) -> RawValue<'a> {
+        RawValue::new(types.int, bytes_of(value))
+    }
+    pub fn long(value: &'a i64, types: &Types) -> RawValue<'a> {
+        RawValue::new(types.long, bytes_of(value))
+    }
+    pub fn float(value: &'a f32, types: &Types) -> RawValue<'a> {
+        RawValue::new(types.float, bytes_of(value))
+    }
+    pub fn double(value: &'a f64, types: &Types) -> RawValue<'a> {
+        RawValue::new(types.double, bytes_of(value))
+    }
+    pub fn urid(value: &'a URID, types: &Types) -> RawValue<'a> {
+        RawValue::new(types.urid, bytes_of(value))
+    }
+
+    pub fn type_urid(&self) -> URID {
+        self.type_urid
+    }
+    pub fn body(&self) -> &'a [u8] {
+        self.body
+    }
+
+    /// Decodes the value according to its type and size.
+    pub fn value(&self, types: &Types) -> Value<'a> {
+        let t = self.type_urid;
+        let size = self.body.len();
+        if t == types.int && size == 4 {
+            Value::Int(unsafe { read(self.body) })
+        }
+        else if t == types.long && size == 8 {
+            Value::Long(unsafe { read(self.body) })
+        }
+        else if t == types.float && size == 4 {
+            Value::Float(unsafe { read(self.body) })
+        }
+        else if t == types.double && size == 8 {
+            Value::Double(unsafe { read(self.body) })
+        }
+        else if t == types.bool && size == 4 {
+            Value::Bool(unsafe { read::<i32>(self.body) } != 0)
+        }
+        else if t == types.urid && size == 4 {
+            Value::URID(unsafe { read(self.body) })
+        }
+        else if t == types.string {
+            let len = self.body.iter().position(|&b| b == 0).unwrap_or(size);
+            match str::from_utf8(&self.body[.. len]) {
+                Ok(s) => Value::String(s),
+                Err(_) => Value::Other(*self),
+            }
+        }
+        else {
+            Value::Other(*self)
+        }
+    }
+
+    unsafe fn from_raw(raw: &LV2_Options_Option) -> RawValue<'a> {
+        let body = if raw.value.is_null() {
+            &[][..]
+        }
+        else {
+            slice::from_raw_parts(raw.value as *const u8, raw.size as usize)
+        };
+        RawValue::new(raw.type_, body)
+    }
+}
+
+fn bytes_of<T: Copy>(value: &T) -> &[u8] {
+    unsafe { slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) }
+}
+
+unsafe fn read<T: Copy>(body: &[u8]) -> T {
+    debug_assert!(body.len() == mem::size_of::<T>());
+    ptr::read_unaligned(body.as_ptr() as *const T)
+}
+
+
+/// An option given by the host.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Item<'a> {
+    pub subject: Subject,
+    pub key: URID,
+    pub value: RawValue<'a>,
+}
+
+
+/// The `options:options` feature: options given by the host at instantiation.
+pub struct Options<'h> {
+    raw: *const LV2_Options_Option,
+    marker: marker::PhantomData<&'h ()>,
+}
+
+impl<'h> Feature<'h> for Options<'h> {
+    fn uri() -> &'static str {
+        inst::OPTIONS
+    }
+    unsafe fn from_raw(raw: &RawFeature<'h>) -> Self {
+        debug_assert!(raw.uri() == Self::uri());
+        Options {
+            raw: mem::transmute((*raw.raw).data),
+            marker: marker::PhantomData,
+        }
+    }
+}
+
+impl<'h> Options<'h> {
+    pub fn iter(&self) -> Iter<'h> {
+        Iter {
+            raw: self.raw,
+            marker: marker::PhantomData,
+        }
+    }
+
+    /// The value of the instance option `key`, e.g. `buf_size::prop::MAXBLOCKLENGTH` mapped.
+    pub fn get(&self, key: URID, types: &Types) -> Option<Value<'h>> {
+        self.iter()
+            .find(|item| item.subject == Subject::Instance && item.key == key)
+            .map(|item| item.value.value(types))
+    }
+}
+
+/// Iterator over an option array, ending at the zero key terminator.
+pub struct Iter<'a> {
+    raw: *const LV2_Options_Option,
+    marker: marker::PhantomData<&'a ()>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = Item<'a>;
+    fn next(&mut self) -> Option<Item<'a>> {
+        loop {
+            if self.raw.is_null() {
+                return None;
+            }
+            let raw = unsafe { &*self.raw };
+            if raw.key == 0 && raw.value.is_null() {
+                self.raw = ptr::null();
+                return None;
+            }
+            self.raw = unsafe { self.raw.offset(1) };
+            if let Some(subject) = Subject::from_raw(raw.context, raw.subject) {
+                return Some(Item {
+                    subject: subject,
+                    key: raw.key,
+                    value: unsafe { RawValue::from_raw(raw) },
+                });
+            }
+        }
+    }
+}
+
+
+/// Plugins whose options can be read and changed by the host at run time.
+/// Register with `lv2_extensions!(lv2::options::Interface)`, which also lists
+/// the extension in the plugin description.
+///
+/// Both methods are in the instantiation threading class: the host never
+/// calls them concurrently with another method of the instance.
+pub trait OptionsInterface<'h>: Plugin<'h> {
+    /// The value of option `key`.
+    /// The host reads the value in place after this returns, until it calls
+    /// the plugin again, so it must be borrowed from the plugin itself, not
+    /// from a temporary. The plugin must not change it in the meantime.
+    fn get_option(&self, subject: Subject, key: URID) -> Result<RawValue<'_>, Error>;
+    /// Changes option `key`. `value` is only valid during the call.
+    fn set_option(&mut self, subject: Subject, key: URID, value: RawValue) -> Result<(), Error>;
+}
+
+
+/// The `options:interface` extension for plugins implementing `OptionsInterface`.
+pub enum Interface {}
+
+unsafe impl<'h, T: OptionsInterface<'h>> Extension<'h, T> for Interface {
+    fn uri() -> &'static str {
+        inst::INTERFACE
+    }
+    fn interface() -> *const c_void {
+        let iface: &'static LV2_Options_Interface = &LV2_Options_Interface {
+            get: Some(get::<T>),
+            set: Some(set::<T>),
+        };
+        iface as *const LV2_Options_Interface as *const c_void
+    }
+}
+
+extern "C" fn get<'h, T: OptionsInterface<'h>>(instance: LV2_Handle,
+                                               options: *mut LV2_Options_Option)
+                                               -> LV2_Options_Status
+{
+    let instance = unsafe { PluginInstance::<T>::from_handle(instance) };
+    let get_options = |plugin: &mut T| {
+        let mut status = LV2_OPTIONS_SUCCESS;
+        let mut raw = options;
+        while !raw.is_null() {
+            let opt = unsafe { &mut *raw };
+            if opt.key == 0 && opt.value.is_null() {
+                break;
+            }
+            let res = match Subject::from_raw(opt.context, opt.subject) {
+                Some(subject) => plugin.get_option(subject, opt.key),
+                None => Err(Error::BadSubject),
+            };
+            match res {
+                Ok(value) => {
+                    opt.type_ = value.type_urid;
+                    opt.size = value.body.len() as u32;
+                    opt.value = value.body.as_ptr() as *const c_void;
+                },
+                Err(err) => status |= Error::status(Err(err)),
+            }
+            raw = unsafe { raw.offset(1) };
+        }
+        status
+    };
+    let res = unsafe { instance.call("get", get_options) };
+    res.unwrap_or(LV2_OPTIONS_ERR_UNKNOWN)
+}
+
+extern "C" fn set<'h, T: OptionsInterface<'h>>(instance: LV2_Handle,
+                                               options: *const LV2_Options_Option)
+                                               -> LV2_Options_Status
+{
+    let instance = unsafe { PluginInstance::<T>::from_handle(instance) };
+    let set_options = |plugin: &mut T| {
+        let mut status = LV2_OPTIONS_SUCCESS;
+        let mut raw = options;
+        while !raw.is_null() {
+            let opt = unsafe { &*raw };
+            if opt.key == 0 && opt.value.is_null() {
+                break;
+            }
+            let res = match Subject::from_raw(opt.context, opt.subject) {
+                Some(subject) => {
+                    let value = unsafe { RawValue::from_raw(opt) };
+                    plugin.set_option(subject, opt.key, value)
+                },
+                None => Err(Error::BadSubject),
+            };
+            status |= Error::status(res);
+            raw = unsafe { raw.offset(1) };
+        }
+        status
+    };
+    let res = unsafe { instance.call("set", set_options) };
+    res.unwrap_or(LV2_OPTIONS_ERR_UNKNOWN)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::core::{self, Extensions, PortInfo, Ported};
+    use ::ffi::core::LV2_Descriptor;
+
+    use std::ffi::CString;
+
+    const BLOCK: URID = 1;
+    const GAIN: URID = 2;
+
+    fn types() -> Types {
+        Types { int: 10, long: 11, float: 12, double: 13, bool: 14, urid: 15, string: 16 }
+    }
+
+    struct Synth {
+        types: Types,
+        block: i32,
+        gain: f32,
+    }
+
+    unsafe impl<'h> Ported<'h> for Synth {
+        type Ports = ();
+        type PortsRaw = ();
+        fn new_ports_raw() {}
+        fn connect_port(_port: usize, _data: *mut (), _ports_raw: &mut ()) {}
+        fn convert_ports(_ports_raw: (), _sample_count: usize) {}
+        fn silence_outputs(_ports_raw: (), _sample_count: usize) {}
+        fn port_infos() -> Vec<PortInfo> {
+            Vec::new()
+        }
+    }
+
+    impl<'h> Plugin<'h> for Synth {
+        type Features = ();
+        fn new(_sample_rate: f64, _bundle_path: &str, _features: ()) -> Option<Synth> {
+            Some(Synth { types: types(), block: 64, gain: 1.0 })
+        }
+        fn run(&mut self, _ports: &mut (), _sample_count: usize) {}
+        fn extension_data(uri: &str) -> Option<*const c_void> {
+            <(Interface,) as Extensions<Self>>::interface(uri)
+        }
+    }
+
+    impl<'h> OptionsInterface<'h> for Synth {
+        fn get_option(&self, subject: Subject, key: URID) -> Result<RawValue<'_>, Error> {
+            match (subject, key) {
+                (Subject::Instance, BLOCK) => Ok(RawValue::int(&self.block, &self.types)),
+                (Subject::Instance, GAIN) => Ok(RawValue::float(&self.gain, &self.types)),
+                (Subject::Instance, _) => Err(Error::BadKey),
+                _ => Err(Error::BadSubject),
+            }
+        }
+        fn set_option(&mut self, subject: Subject, key: URID, value: RawValue) -> Result<(), Error> {
+            if subject != Subject::Instance {
+                return Err(Error::BadSubject);
+            }
+            match (key, value.value(&self.types)) {
+                (BLOCK, Value::Int(block)) => self.block = block,
+                (GAIN, Value::Float(gain)) => self.gain = gain,
+                (BLOCK, _) | (GAIN, _) => return Err(Error::BadValue),
+                _ => return Err(Error::BadKey),
+            }
+            Ok(())
+        }
+    }
+
+    fn option(context: LV2_Options_Context, key: URID) -> LV2_Options_Option {
+        LV2_Options_Option {
+            context: context,
+            subject: 0,
+            key: key,
+            size: 0,
+            type_: 0,
+            value: ptr::null(),
+        }
+    }
+
+    fn with_interface<F: FnOnce(LV2_Handle, &LV2_Options_Interface)>(f: F) {
+        let bundle = CString::new("/").unwrap();
+        let handle = core::instantiate::<Synth>(ptr::null::<LV2_Descriptor>(), 48000.0,
+                                                bundle.as_ptr(), ptr::null());
+        assert!(!handle.is_null());
+        let uri = CString::new(inst::INTERFACE).unwrap();
+        let iface = core::extension_data::<Synth>(uri.as_ptr()) as *const LV2_Options_Interface;
+        assert!(!iface.is_null());
+        f(handle, unsafe { &*iface });
+        core::cleanup::<Synth>(handle);
+    }
+
+    unsafe fn value<T: Copy>(opt: &LV2_Options_Option) -> T {
+        assert_eq!(opt.size as usize, mem::size_of::<T>());
+        ptr::read_unaligned(opt.value as *const T)
+    }
+
+    #[test]
+    fn extension_uris() {
+        assert_eq!(<(Interface,) as Extensions<Synth>>::uris(), vec![inst::INTERFACE]);
+        assert_eq!(<() as Extensions<Synth>>::uris(), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn get() {
+        with_interface(|handle, iface| {
+            let mut opts = [
+                option(LV2_OPTIONS_INSTANCE, BLOCK),
+                option(LV2_OPTIONS_INSTANCE, GAIN),
+                option(LV2_OPTIONS_INSTANCE, 99),
+                option(LV2_OPTIONS_PORT, BLOCK),
+                option(0, 0),
+            ];
+            let status = (iface.get.unwrap())(handle, opts.as_mut_ptr());
+            assert_eq!(status, LV2_OPTIONS_ERR_BAD_KEY | LV2_OPTIONS_ERR_BAD_SUBJECT);
+            assert_eq!(opts[0].type_, types().int);
+            assert_eq!(unsafe { value::<i32>(&opts[0]) }, 64);
+            assert_eq!(opts[1].type_, types().float);
+            assert_eq!(unsafe { value::<f32>(&opts[1]) }, 1.0);
+            assert!(opts[2].value.is_null() && opts[3].value.is_null());
+        });
+    }
+
+    #[test]
+    fn set() {
+        with_interface(|handle, iface| {
+            let block = 256i32;
+            let gain = 0.5f64;
+            let mut opts = [
+                option(LV2_OPTIONS_INSTANCE, BLOCK),
+                option(LV2_OPTIONS_INSTANCE, GAIN),
+                option(7, BLOCK),
+                option(0, 0),
+            ];
+            opts[0].type_ = types().int;
+            opts[0].size = 4;
+            opts[0].value = &block as *const i32 as *const c_void;
+            // a double where a float is expected
+            opts[1].type_ = types().double;
+            opts[1].size = 8;
+            opts[1].value = &gain as *const f64 as *const c_void;
+            opts[2].value = &block as *const i32 as *const c_void;
+            let status = (iface.set.unwrap())(handle, opts.as_ptr());
+            assert_eq!(status, LV2_OPTIONS_ERR_BAD_VALUE | LV2_OPTIONS_ERR_BAD_SUBJECT);
+
+            let mut opts = [option(LV2_OPTIONS_INSTANCE, BLOCK), option(LV2_OPTIONS_INSTANCE, GAIN),
+                            option(0, 0)];
+            assert_eq!((iface.get.unwrap())(handle, opts.as_mut_ptr()), LV2_OPTIONS_SUCCESS);
+            assert_eq!(unsafe { value::<i32>(&opts[0]) }, 256);
+            assert_eq!(unsafe { value::<f32>(&opts[1]) }, 1.0);
+        });
+    }
+}
@@ -0,0 +1,32 @@
+pub const URI: &'static str = "http://lv2plug.in/ns/ext/parameters";
+
+pub mod class {
+    pub const COMPRESSORCONTROLS: &'static str = "http://lv2plug.in/ns/ext/parameters#CompressorControls";
+    pub const CONTROLGROUP:       &'static str = "http://lv2plug.in/ns/ext/parameters#ControlGroup";
+    pub const ENVELOPECONTROLS:   &'static str = "http://lv2plug.in/ns/ext/parameters#EnvelopeControls";
+    pub const FILTERCONTROLS:     &'static str = "http://lv2plug.in/ns/ext/parameters#FilterControls";
+    pub const OSCILLATORCONTROLS: &'static str = "http://lv2plug.in/ns/ext/parameters#OscillatorControls";
+}
+
+pub mod prop {
+    pub const AMPLITUDE:          &'static str = "http://lv2plug.in/ns/ext/parameters#amplitude";
+    pub const ATTACK:             &'static str = "http://lv2plug.in/ns/ext/parameters#attack";
+    pub const BYPASS:             &'static str = "http://lv2plug.in/ns/ext/parameters#bypass";
+    pub const CUTOFFFREQUENCY:    &'static str = "http://lv2plug.in/ns/ext/parameters#cutoffFrequency";
+    pub const DECAY:              &'static str = "http://lv2plug.in/ns/ext/parameters#decay";
+    pub const DELAY:              &'static str = "http://lv2plug.in/ns/ext/parameters#delay";
+    pub const DRYLEVEL:           &'static str = "http://lv2plug.in/ns/ext/parameters#dryLevel";
+    pub const FREQUENCY:          &'static str = "http://lv2plug.in/ns/ext/parameters#frequency";
+    pub const GAIN:               &'static str = "http://lv2plug.in/ns/ext/parameters#gain";
+    pub const HOLD:               &'static str = "http://lv2plug.in/ns/ext/parameters#hold";
+    pub const PULSEWIDTH:         &'static str = "http://lv2plug.in/ns/ext/parameters#pulseWidth";
+    pub const RATIO:              &'static str = "http://lv2plug.in/ns/ext/parameters#ratio";
+    pub const RELEASE:            &'static str = "http://lv2plug.in/ns/ext/parameters#release";
+    pub const RESONANCE:          &'static str = "http://lv2plug.in/ns/ext/parameters#resonance";
+    pub const SAMPLERATE:         &'static str = "http://lv2plug.in/ns/ext/parameters#sampleRate";
+    pub const SUSTAIN:            &'static str = "http://lv2plug.in/ns/ext/parameters#sustain";
+    pub const THRESHOLD:          &'static str = "http://lv2plug.in/ns/ext/parameters#threshold";
+    pub const WAVEFORM:           &'static str = "http://lv2plug.in/ns/ext/parameters#waveform";
+    pub const WETDRYRATIO:        &'static str = "http://lv2plug.in/ns/ext/parameters#wetDryRatio";
+    pub const WETLEVEL:           &'static str = "http://lv2plug.in/ns/ext/parameters#wetLevel";
+}
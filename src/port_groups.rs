@@ -0,0 +1,297 @@
+
+pub const URI: &'static str = "http://lv2plug.in/ns/ext/port-groups";
+
+pub mod class {
+    pub const AMBISONICBH1P0GROUP:    &'static str = "http://lv2plug.in/ns/ext/port-groups#AmbisonicBH1P0Group";
+    pub const AMBISONICBH1P1GROUP:    &'static str = "http://lv2plug.in/ns/ext/port-groups#AmbisonicBH1P1Group";
+    pub const AMBISONICBH2P0GROUP:    &'static str = "http://lv2plug.in/ns/ext/port-groups#AmbisonicBH2P0Group";
+    pub const AMBISONICBH2P1GROUP:    &'static str = "http://lv2plug.in/ns/ext/port-groups#AmbisonicBH2P1Group";
+    pub const AMBISONICBH2P2GROUP:    &'static str = "http://lv2plug.in/ns/ext/port-groups#AmbisonicBH2P2Group";
+    pub const AMBISONICBH3P0GROUP:    &'static str = "http://lv2plug.in/ns/ext/port-groups#AmbisonicBH3P0Group";
+    pub const AMBISONICBH3P1GROUP:    &'static str = "http://lv2plug.in/ns/ext/port-groups#AmbisonicBH3P1Group";
+    pub const AMBISONICBH3P2GROUP:    &'static str = "http://lv2plug.in/ns/ext/port-groups#AmbisonicBH3P2Group";
+    pub const AMBISONICBH3P3GROUP:    &'static str = "http://lv2plug.in/ns/ext/port-groups#AmbisonicBH3P3Group";
+    pub const DISCRETEGROUP:          &'static str = "http://lv2plug.in/ns/ext/port-groups#DiscreteGroup";
+    pub const ELEMENT:                &'static str = "http://lv2plug.in/ns/ext/port-groups#Element";
+    pub const FIVEPOINTONEGROUP:      &'static str = "http://lv2plug.in/ns/ext/port-groups#FivePointOneGroup";
+    pub const FIVEPOINTZEROGROUP:     &'static str = "http://lv2plug.in/ns/ext/port-groups#FivePointZeroGroup";
+    pub const FOURPOINTZEROGROUP:     &'static str = "http://lv2plug.in/ns/ext/port-groups#FourPointZeroGroup";
+    pub const GROUP:                  &'static str = "http://lv2plug.in/ns/ext/port-groups#Group";
+    pub const INPUTGROUP:             &'static str = "http://lv2plug.in/ns/ext/port-groups#InputGroup";
+    pub const MIDSIDEGROUP:           &'static str = "http://lv2plug.in/ns/ext/port-groups#MidSideGroup";
+    pub const MONOGROUP:              &'static str = "http://lv2plug.in/ns/ext/port-groups#MonoGroup";
+    pub const OUTPUTGROUP:            &'static str = "http://lv2plug.in/ns/ext/port-groups#OutputGroup";
+    pub const SEVENPOINTONEGROUP:     &'static str = "http://lv2plug.in/ns/ext/port-groups#SevenPointOneGroup";
+    pub const SEVENPOINTONEWIDEGROUP: &'static str = "http://lv2plug.in/ns/ext/port-groups#SevenPointOneWideGroup";
+    pub const SIXPOINTONEGROUP:       &'static str = "http://lv2plug.in/ns/ext/port-groups#SixPointOneGroup";
+    pub const STEREOGROUP:            &'static str = "http://lv2plug.in/ns/ext/port-groups#StereoGroup";
+    pub const THREEPOINTZEROGROUP:    &'static str = "http://lv2plug.in/ns/ext/port-groups#ThreePointZeroGroup";
+}
+
+pub mod prop {
+    pub const ELEMENT:                &'static str = "http://lv2plug.in/ns/ext/port-groups#element";
+    pub const GROUP:                  &'static str = "http://lv2plug.in/ns/ext/port-groups#group";
+    pub const MAININPUT:              &'static str = "http://lv2plug.in/ns/ext/port-groups#mainInput";
+    pub const MAINOUTPUT:             &'static str = "http://lv2plug.in/ns/ext/port-groups#mainOutput";
+    pub const SIDECHAINOF:            &'static str = "http://lv2plug.in/ns/ext/port-groups#sideChainOf";
+    pub const SOURCE:                 &'static str = "http://lv2plug.in/ns/ext/port-groups#source";
+    pub const SUBGROUPOF:             &'static str = "http://lv2plug.in/ns/ext/port-groups#subGroupOf";
+}
+
+pub mod inst {
+    pub const CENTER:                 &'static str = "http://lv2plug.in/ns/ext/port-groups#center";
+    pub const CENTERLEFT:             &'static str = "http://lv2plug.in/ns/ext/port-groups#centerLeft";
+    pub const CENTERRIGHT:            &'static str = "http://lv2plug.in/ns/ext/port-groups#centerRight";
+    pub const LEFT:                   &'static str = "http://lv2plug.in/ns/ext/port-groups#left";
+    pub const LOWFREQUENCYEFFECTS:    &'static str = "http://lv2plug.in/ns/ext/port-groups#lowFrequencyEffects";
+    pub const REARCENTER:             &'static str = "http://lv2plug.in/ns/ext/port-groups#rearCenter";
+    pub const REARLEFT:               &'static str = "http://lv2plug.in/ns/ext/port-groups#rearLeft";
+    pub const REARRIGHT:              &'static str = "http://lv2plug.in/ns/ext/port-groups#rearRight";
+    pub const RIGHT:                  &'static str = "http://lv2plug.in/ns/ext/port-groups#right";
+    pub const SIDE:                   &'static str = "http://lv2plug.in/ns/ext/port-groups#side";
+    pub const SIDELEFT:               &'static str = "http://lv2plug.in/ns/ext/port-groups#sideLeft";
+    pub const SIDERIGHT:              &'static str = "http://lv2plug.in/ns/ext/port-groups#sideRight";
+}
+
+
+/// A kind of port group, such as stereo or 5.1.
+pub trait Group {
+    /// The group class, e.g. `pg:StereoGroup`.
+    fn uri() -> &'static str;
+    /// The designation of each channel, in port order.
+    fn elements() -> &'static [&'static str];
+}
+
+/// Description of the group a port belongs to, for metadata generation.
+#[derive(Copy, Clone, Debug)]
+pub struct GroupInfo {
+    pub class: &'static str,
+    pub input: bool,
+    /// Whether the group is the `pg:mainInput` or `pg:mainOutput` of the plugin.
+    pub main: bool,
+}
+
+
+/// Iterator over the frames of a group of input channels.
+pub struct Frames<'a, const N: usize> {
+    channels: [&'a [f32]; N],
+    frame: usize,
+    len: usize,
+}
+
+impl<'a, const N: usize> Iterator for Frames<'a, N> {
+    type Item = [f32; N];
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.frame < self.len {
+            let frame = self.frame;
+            self.frame += 1;
+            Some(self.channels.map(|ch| ch[frame]))
+        }
+        else {
+            None
+        }
+    }
+}
+
+pub trait InputChannels<'a, const N: usize> {
+    /// Iterates over the samples of all channels, frame by frame.
+    fn frames(&self) -> Frames<'a, N>;
+}
+
+impl<'a, const N: usize> InputChannels<'a, N> for [&'a [f32]; N] {
+    fn frames(&self) -> Frames<'a, N> {
+        let len = self.iter().map(|ch| ch.len()).min().unwrap_or(0);
+        Frames { channels: *self, frame: 0, len: len }
+    }
+}
+
+pub trait OutputChannels<const N: usize> {
+    fn frame_count(&self) -> usize;
+    fn set_frame(&mut self, frame: usize, values: [f32; N]);
+    /// Writes the frames yielded by `frames`, starting at the first frame.
+    fn write_frames<I: IntoIterator<Item = [f32; N]>>(&mut self, frames: I) {
+        let count = self.frame_count();
+        for (frame, values) in frames.into_iter().take(count).enumerate() {
+            self.set_frame(frame, values);
+        }
+    }
+}
+
+impl<'a, const N: usize> OutputChannels<N> for [&'a mut [f32]; N] {
+    fn frame_count(&self) -> usize {
+        self.iter().map(|ch| ch.len()).min().unwrap_or(0)
+    }
+    fn set_frame(&mut self, frame: usize, values: [f32; N]) {
+        for (ch, value) in self.iter_mut().zip(values.iter()) {
+            ch[frame] = *value;
+        }
+    }
+}
+
+
+macro_rules! impl_group {
+    ( $Group:ident, $n:expr, $uri:expr, [ $( $elem:expr ),+ ] ) => {
+
+        pub enum $Group {}
+        impl Group for $Group {
+            fn uri() -> &'static str {
+                $uri
+            }
+            fn elements() -> &'static [&'static str] {
+                &[ $( $elem ),+ ]
+            }
+        }
+
+        unsafe impl<'h> Port<'h> for meta::InputGroup<$Group> {
+            type FieldRaw = [*const f32; $n];
+            type Field = [&'h [f32]; $n];
+            const PORT_COUNT: usize = $n;
+            fn classes(_index: usize) -> &'static [&'static str] {
+                &[core::class::INPUTPORT, core::class::AUDIOPORT]
+            }
+            fn designation(index: usize) -> Option<&'static str> {
+                $Group::elements().get(index).cloned()
+            }
+            fn group() -> Option<GroupInfo> {
+                Some(GroupInfo { class: $Group::uri(), input: true, main: false })
+            }
+            fn new_raw() -> Self::FieldRaw {
+                [ptr::null(); $n]
+            }
+            fn connect(raw: &mut Self::FieldRaw, index: usize, data: *mut ()) {
+                raw[index] = data as *const f32;
+            }
+            fn is_connected(raw: Self::FieldRaw) -> bool {
+                raw.iter().all(|ch| !ch.is_null())
+            }
+            fn convert(raw: Self::FieldRaw, sample_count: usize) -> Self::Field {
+                raw.map(|ch| unsafe { slice::from_raw_parts(ch, sample_count) })
+            }
+        }
+
+        unsafe impl<'h> Port<'h> for meta::OutputGroup<$Group> {
+            type FieldRaw = [*mut f32; $n];
+            type Field = [&'h mut [f32]; $n];
+            const PORT_COUNT: usize = $n;
+            fn classes(_index: usize) -> &'static [&'static str] {
+                &[core::class::OUTPUTPORT, core::class::AUDIOPORT]
+            }
+            fn designation(index: usize) -> Option<&'static str> {
+                $Group::elements().get(index).cloned()
+            }
+            fn group() -> Option<GroupInfo> {
+                Some(GroupInfo { class: $Group::uri(), input: false, main: false })
+            }
+            fn new_raw() -> Self::FieldRaw {
+                [ptr::null_mut(); $n]
+            }
+            fn connect(raw: &mut Self::FieldRaw, index: usize, data: *mut ()) {
+                raw[index] = data as *mut f32;
+            }
+            fn is_connected(raw: Self::FieldRaw) -> bool {
+                raw.iter().all(|ch| !ch.is_null())
+            }
+            fn convert(raw: Self::FieldRaw, sample_count: usize) -> Self::Field {
+                raw.map(|ch| unsafe { slice::from_raw_parts_mut(ch, sample_count) })
+            }
+            fn silence(raw: Self::FieldRaw, sample_count: usize) {
+                for ch in raw.iter() {
+                    if !ch.is_null() {
+                        unsafe { ptr::write_bytes(*ch, 0, sample_count) }
+                    }
+                }
+            }
+        }
+    };
+}
+
+mod groups {
+    use super::{class, inst, meta, Group, GroupInfo};
+    use ::core;
+    use ::core::meta::Port;
+    use std::ptr;
+    use std::slice;
+
+    impl_group!(Mono, 1, class::MONOGROUP, [inst::CENTER]);
+    impl_group!(Stereo, 2, class::STEREOGROUP, [inst::LEFT, inst::RIGHT]);
+    impl_group!(MidSide, 2, class::MIDSIDEGROUP, [inst::CENTER, inst::SIDE]);
+    impl_group!(ThreePointZero, 3, class::THREEPOINTZEROGROUP,
+                [inst::LEFT, inst::RIGHT, inst::REARCENTER]);
+    impl_group!(FourPointZero, 4, class::FOURPOINTZEROGROUP,
+                [inst::LEFT, inst::CENTER, inst::RIGHT, inst::REARCENTER]);
+    impl_group!(FivePointZero, 5, class::FIVEPOINTZEROGROUP,
+                [inst::LEFT, inst::CENTER, inst::RIGHT, inst::REARLEFT, inst::REARRIGHT]);
+    impl_group!(FivePointOne, 6, class::FIVEPOINTONEGROUP,
+                [inst::LEFT, inst::CENTER, inst::RIGHT, inst::REARLEFT, inst::REARRIGHT,
+                 inst::LOWFREQUENCYEFFECTS]);
+    impl_group!(SixPointOne, 7, class::SIXPOINTONEGROUP,
+                [inst::LEFT, inst::CENTER, inst::RIGHT, inst::SIDELEFT, inst::SIDERIGHT,
+                 inst::REARCENTER, inst::LOWFREQUENCYEFFECTS]);
+    impl_group!(SevenPointOne, 8, class::SEVENPOINTONEGROUP,
+                [inst::LEFT, inst::CENTER, inst::RIGHT, inst::SIDELEFT, inst::SIDERIGHT,
+                 inst::REARLEFT, inst::REARRIGHT, inst::LOWFREQUENCYEFFECTS]);
+    impl_group!(SevenPointOneWide, 8, class::SEVENPOINTONEWIDEGROUP,
+                [inst::LEFT, inst::CENTERLEFT, inst::CENTER, inst::CENTERRIGHT, inst::RIGHT,
+                 inst::REARLEFT, inst::REARRIGHT, inst::LOWFREQUENCYEFFECTS]);
+}
+
+pub use self::groups::*;
+
+
+pub mod meta {
+    use super::GroupInfo;
+    use ::core::meta::Port;
+    use std::marker;
+
+    /// Input audio ports forming a group `G`, yielding one slice per channel.
+    pub struct InputGroup<G> {
+        marker: marker::PhantomData<G>,
+    }
+
+    /// Output audio ports forming a group `G`, yielding one slice per channel.
+    pub struct OutputGroup<G> {
+        marker: marker::PhantomData<G>,
+    }
+
+    /// Designates the group `M` as the `pg:mainInput` or `pg:mainOutput` of the plugin.
+    pub struct Main<M> {
+        marker: marker::PhantomData<M>,
+    }
+
+    unsafe impl<'h, M: Port<'h>> Port<'h> for Main<M> {
+        type FieldRaw = M::FieldRaw;
+        type Field = M::Field;
+        const PORT_COUNT: usize = M::PORT_COUNT;
+        const OPTIONAL: bool = M::OPTIONAL;
+        fn classes(index: usize) -> &'static [&'static str] {
+            M::classes(index)
+        }
+        fn properties() -> &'static [&'static str] {
+            M::properties()
+        }
+        fn designation(index: usize) -> Option<&'static str> {
+            M::designation(index)
+        }
+        fn group() -> Option<GroupInfo> {
+            M::group().map(|g| GroupInfo { main: true, ..g })
+        }
+        fn statements() -> &'static [(&'static str, &'static str)] {
+            M::statements()
+        }
+        fn new_raw() -> Self::FieldRaw {
+            M::new_raw()
+        }
+        fn connect(raw: &mut Self::FieldRaw, index: usize, data: *mut ()) {
+            M::connect(raw, index, data)
+        }
+        fn is_connected(raw: Self::FieldRaw) -> bool {
+            M::is_connected(raw)
+        }
+        fn convert(raw: Self::FieldRaw, sample_count: usize) -> Self::Field {
+            M::convert(raw, sample_count)
+        }
+        fn silence(raw: Self::FieldRaw, sample_count: usize) {
+            M::silence(raw, sample_count)
+        }
+    }
+}
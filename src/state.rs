@@ -0,0 +1,903 @@
+
+use ::core::{Extension, Feature, FeatureList, Plugin, PluginInstance, RawFeature};
+use ::ffi::core::{LV2_Feature, LV2_Handle};
+use ::ffi::state::*;
+use ::urid;
+
+#[cfg(feature = "atom")]
+use ::atom::{self, Header, Scalar, Types};
+#[cfg(feature = "atom")]
+use std::ptr;
+
+use libc::{self, c_char, c_void};
+use std::any::Any;
+use std::error;
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::marker;
+use std::mem;
+use std::ops;
+use std::path::{Path, PathBuf};
+use std::slice;
+use std::sync::Arc;
+
+pub const URI: &'static str = "http://lv2plug.in/ns/ext/state";
+
+pub mod class {
+    pub const STATECHANGED      : &'static str = "http://lv2plug.in/ns/ext/state#StateChanged";
+}
+pub mod inst {
+    pub const INTERFACE         : &'static str = "http://lv2plug.in/ns/ext/state#interface";
+    pub const LOADDEFAULTSTATE  : &'static str = "http://lv2plug.in/ns/ext/state#loadDefaultState";
+    pub const MAKEPATH          : &'static str = "http://lv2plug.in/ns/ext/state#makePath";
+    pub const MAPPATH           : &'static str = "http://lv2plug.in/ns/ext/state#mapPath";
+    pub const FREEPATH          : &'static str = "http://lv2plug.in/ns/ext/state#freePath";
+    pub const THREADSAFERESTORE : &'static str = "http://lv2plug.in/ns/ext/state#threadSafeRestore";
+}
+pub mod prop {
+    pub const STATE             : &'static str = "http://lv2plug.in/ns/ext/state#state";
+}
+
+
+/// A flag describing a stored value or a save/restore request.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Flag {
+    /// Plain old data: the value can be copied and compared byte-wise.
+    Pod = LV2_STATE_IS_POD as isize,
+    /// The value can be used on other architectures.
+    Portable = LV2_STATE_IS_PORTABLE as isize,
+    /// The value is only meaningful on this machine.
+    Native = LV2_STATE_IS_NATIVE as isize,
+}
+
+/// A set of `Flag`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Flags(u32);
+
+impl Flags {
+    pub fn empty() -> Flags {
+        Flags(0)
+    }
+    pub fn from_bits(bits: u32) -> Flags {
+        Flags(bits)
+    }
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+    pub fn contains(&self, flag: Flag) -> bool {
+        self.0 & flag as u32 != 0
+    }
+    pub fn with(self, flag: Flag) -> Flags {
+        Flags(self.0 | flag as u32)
+    }
+}
+
+impl From<Flag> for Flags {
+    fn from(flag: Flag) -> Flags {
+        Flags(flag as u32)
+    }
+}
+
+impl ops::BitOr for Flag {
+    type Output = Flags;
+    fn bitor(self, other: Flag) -> Flags {
+        Flags::from(self).with(other)
+    }
+}
+
+impl ops::BitOr<Flag> for Flags {
+    type Output = Flags;
+    fn bitor(self, other: Flag) -> Flags {
+        self.with(other)
+    }
+}
+
+
+/// Error of a state operation, mapped to the LV2 status codes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    Unknown,
+    BadType,
+    BadFlags,
+    NoFeature,
+    NoProperty,
+    NoSpace,
+}
+
+impl Error {
+    fn from_status(status: LV2_State_Status) -> Result<(), Error> {
+        match status {
+            LV2_STATE_SUCCESS => Ok(()),
+            LV2_STATE_ERR_BAD_TYPE => Err(Error::BadType),
+            LV2_STATE_ERR_BAD_FLAGS => Err(Error::BadFlags),
+            LV2_STATE_ERR_NO_FEATURE => Err(Error::NoFeature),
+            LV2_STATE_ERR_NO_PROPERTY => Err(Error::NoProperty),
+            LV2_STATE_ERR_NO_SPACE => Err(Error::NoSpace),
+            _ => Err(Error::Unknown),
+        }
+    }
+
+    fn status(res: Result<(), Error>) -> LV2_State_Status {
+        match res {
+            Ok(()) => LV2_STATE_SUCCESS,
+            Err(Error::Unknown) => LV2_STATE_ERR_UNKNOWN,
+            Err(Error::BadType) => LV2_STATE_ERR_BAD_TYPE,
+            Err(Error::BadFlags) => LV2_STATE_ERR_BAD_FLAGS,
+            Err(Error::NoFeature) => LV2_STATE_ERR_NO_FEATURE,
+            Err(Error::NoProperty) => LV2_STATE_ERR_NO_PROPERTY,
+            Err(Error::NoSpace) => LV2_STATE_ERR_NO_SPACE,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Error::Unknown => "unknown state error",
+            Error::BadType => "unsupported value type",
+            Error::BadFlags => "unsupported value flags",
+            Error::NoFeature => "missing feature",
+            Error::NoProperty => "missing property",
+            Error::NoSpace => "not enough space",
+        })
+    }
+}
+
+impl error::Error for Error {}
+
+
+/// Plugins whose state can be saved and restored by the host.
+/// Register with `lv2_extensions!(lv2::state::Interface)`, which also lists
+/// the extension in the plugin description.
+pub trait State<'h>: Plugin<'h> {
+    /// The state saved by `save`, shared between the plugin and the host
+    /// threads calling `save`, e.g. atomics or a lock.
+    type Shared: Send + Sync + 'static;
+
+    /// The shared state, taken once after `Plugin::new`.
+    fn shared(&self) -> Arc<Self::Shared>;
+
+    /// Saves the plugin state.
+    /// The host may call this from any thread, concurrently with `run`, so it
+    /// only has access to the shared state.
+    fn save(shared: &Self::Shared, store: &mut StateStore) -> Result<(), Error>;
+
+    /// Restores the plugin state.
+    /// This is in the instantiation threading class: it is never called
+    /// concurrently with `run` or any other plugin function.
+    fn restore(&mut self, retrieve: &StateRetrieve) -> Result<(), Error>;
+}
+
+
+/// Host callback used to save properties.
+pub struct StateStore<'a> {
+    func: extern "C" fn (LV2_State_Handle, urid::URID, *const c_void, usize, urid::URID, u32)
+                         -> LV2_State_Status,
+    handle: LV2_State_Handle,
+    flags: Flags,
+    features: *const *const LV2_Feature,
+    marker: marker::PhantomData<&'a ()>,
+}
+
+impl<'a> StateStore<'a> {
+    /// Flags requested by the host, e.g. `Flag::Portable` when saving to share.
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+
+    /// Features passed by the host for this save.
+    pub fn features(&self) -> FeatureList<'a> {
+        unsafe { FeatureList::from_raw(self.features) }
+    }
+
+    /// Stores `body`, with atom type `type_urid`, under `key`.
+    pub fn store(&mut self, key: urid::URID, type_urid: urid::URID, body: &[u8], flags: Flags)
+        -> Result<(), Error>
+    {
+        let status = (self.func)(self.handle, key,
+                                 body.as_ptr() as *const c_void, body.len(),
+                                 type_urid, flags.bits());
+        Error::from_status(status)
+    }
+
+    /// Stores the body of `atom` under `key`.
+    #[cfg(feature = "atom")]
+    pub fn store_atom(&mut self, key: urid::URID, atom: &Header, flags: Flags)
+        -> Result<(), Error>
+    {
+        let body = unsafe { atom.contents() };
+        self.store(key, atom.type_urid(), body, flags)
+    }
+
+    /// Stores a scalar as a POD value.
+    #[cfg(feature = "atom")]
+    pub fn store_scalar<T: Scalar>(&mut self, key: urid::URID, types: &Types, value: T)
+        -> Result<(), Error>
+    {
+        let body = unsafe {
+            slice::from_raw_parts(&value as *const T as *const u8, mem::size_of::<T>())
+        };
+        self.store(key, T::child_type(types), body, Flag::Pod | Flag::Portable)
+    }
+
+    /// Stores a string atom as a POD value.
+    #[cfg(feature = "atom")]
+    pub fn store_str(&mut self, key: urid::URID, types: &Types, value: &str)
+        -> Result<(), Error>
+    {
+        let mut body = Vec::with_capacity(value.len() + 1);
+        body.extend_from_slice(value.as_bytes());
+        body.push(0);
+        self.store(key, types.string, &body, Flag::Pod | Flag::Portable)
+    }
+
+    /// Stores a path atom, made abstract with the host `state:mapPath` feature.
+    /// The path is stored unchanged if the host does not provide it.
+    #[cfg(feature = "atom")]
+    pub fn store_path(&mut self, key: urid::URID, types: &Types, path: &Path)
+        -> Result<(), Error>
+    {
+        let map = self.features().feature::<MapPath>();
+        let free = self.features().feature::<FreePath>();
+        let path = match map {
+            Some(map) => map.abstract_path(path, free.as_ref()).ok_or(Error::Unknown)?,
+            None => path.to_owned(),
+        };
+        let path = path_to_cstring(&path).ok_or(Error::BadType)?;
+        self.store(key, types.path, path.as_bytes_with_nul(), Flag::Pod | Flag::Portable)
+    }
+}
+
+
+/// A value retrieved from the host, valid until `State::restore` returns.
+#[derive(Copy, Clone, Debug)]
+pub struct Value<'a> {
+    type_urid: urid::URID,
+    flags: Flags,
+    body: &'a [u8],
+}
+
+impl<'a> Value<'a> {
+    pub fn type_urid(&self) -> urid::URID {
+        self.type_urid
+    }
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+    pub fn body(&self) -> &'a [u8] {
+        self.body
+    }
+
+    /// The value as a scalar, if it has the type and size of `T`.
+    #[cfg(feature = "atom")]
+    pub fn scalar<T: Scalar>(&self, types: &Types) -> Result<T, Error> {
+        if self.type_urid != T::child_type(types) || self.body.len() != mem::size_of::<T>() {
+            return Err(Error::BadType);
+        }
+        Ok(unsafe { ptr::read_unaligned(self.body.as_ptr() as *const T) })
+    }
+
+    /// The value as a string, if it is a well-formed string atom body.
+    #[cfg(feature = "atom")]
+    pub fn as_str(&self, types: &Types) -> Result<&'a str, Error> {
+        if self.type_urid != types.string {
+            return Err(Error::BadType);
+        }
+        atom::read::string(self.body).map_err(|_| Error::BadType)
+    }
+
+    /// The value as a path atom, as stored in the body.
+    #[cfg(feature = "atom")]
+    pub fn as_path(&self, types: &Types) -> Result<PathBuf, Error> {
+        if self.type_urid != types.path {
+            return Err(Error::BadType);
+        }
+        match CStr::from_bytes_until_nul(self.body) {
+            Ok(cstr) => Ok(cstr_to_path(cstr)),
+            Err(_) => Err(Error::BadType),
+        }
+    }
+}
+
+
+/// Host callback used to restore properties.
+pub struct StateRetrieve<'a> {
+    func: extern "C" fn (LV2_State_Handle, urid::URID, *mut usize, *mut urid::URID, *mut u32)
+                         -> *const c_void,
+    handle: LV2_State_Handle,
+    flags: Flags,
+    features: *const *const LV2_Feature,
+    marker: marker::PhantomData<&'a ()>,
+}
+
+impl<'a> StateRetrieve<'a> {
+    /// Flags given by the host for this restore.
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+
+    /// Features passed by the host for this restore.
+    pub fn features(&self) -> FeatureList<'a> {
+        unsafe { FeatureList::from_raw(self.features) }
+    }
+
+    /// The value stored under `key`, or `Error::NoProperty`.
+    pub fn retrieve(&self, key: urid::URID) -> Result<Value<'a>, Error> {
+        let mut size = 0;
+        let mut type_urid = 0;
+        let mut flags = 0;
+        let ptr = (self.func)(self.handle, key, &mut size, &mut type_urid, &mut flags);
+        if ptr.is_null() {
+            return Err(Error::NoProperty);
+        }
+        Ok(Value {
+            type_urid: type_urid,
+            flags: Flags(flags),
+            body: unsafe { slice::from_raw_parts(ptr as *const u8, size) },
+        })
+    }
+
+    /// The path atom stored under `key`, made absolute with the host
+    /// `state:mapPath` feature if provided.
+    #[cfg(feature = "atom")]
+    pub fn retrieve_path(&self, key: urid::URID, types: &Types) -> Result<PathBuf, Error> {
+        let path = self.retrieve(key)?.as_path(types)?;
+        let map = self.features().feature::<MapPath>();
+        let free = self.features().feature::<FreePath>();
+        match map {
+            Some(map) => map.absolute_path(&path, free.as_ref()).ok_or(Error::Unknown),
+            None => Ok(path),
+        }
+    }
+}
+
+
+/// The `state:mapPath` feature, mapping between absolute and abstract paths.
+pub struct MapPath<'h> {
+    raw: *const LV2_State_Map_Path,
+    marker: marker::PhantomData<&'h ()>,
+}
+
+impl<'h> Feature<'h> for MapPath<'h> {
+    fn uri() -> &'static str {
+        inst::MAPPATH
+    }
+    unsafe fn from_raw(raw: &RawFeature<'h>) -> Self {
+        debug_assert!(raw.uri() == Self::uri());
+        MapPath {
+            raw: mem::transmute((*raw.raw).data),
+            marker: marker::PhantomData,
+        }
+    }
+}
+
+impl<'h> MapPath<'h> {
+    /// The abstract path to store for `absolute_path`.
+    /// The host string is released with `free`, or `libc::free` if `None`.
+    pub fn abstract_path(&self, absolute_path: &Path, free: Option<&FreePath>) -> Option<PathBuf> {
+        let func = unsafe { (*self.raw).abstract_path }?;
+        let path = path_to_cstring(absolute_path)?;
+        unsafe {
+            host_path(func((*self.raw).handle, path.as_ptr()), free)
+        }
+    }
+
+    /// The absolute path of the stored `abstract_path`.
+    /// The host string is released with `free`, or `libc::free` if `None`.
+    pub fn absolute_path(&self, abstract_path: &Path, free: Option<&FreePath>) -> Option<PathBuf> {
+        let func = unsafe { (*self.raw).absolute_path }?;
+        let path = path_to_cstring(abstract_path)?;
+        unsafe {
+            host_path(func((*self.raw).handle, path.as_ptr()), free)
+        }
+    }
+}
+
+
+/// The `state:makePath` feature, creating files in the plugin state directory.
+pub struct MakePath<'h> {
+    raw: *const LV2_State_Make_Path,
+    marker: marker::PhantomData<&'h ()>,
+}
+
+impl<'h> Feature<'h> for MakePath<'h> {
+    fn uri() -> &'static str {
+        inst::MAKEPATH
+    }
+    unsafe fn from_raw(raw: &RawFeature<'h>) -> Self {
+        debug_assert!(raw.uri() == Self::uri());
+        MakePath {
+            raw: mem::transmute((*raw.raw).data),
+            marker: marker::PhantomData,
+        }
+    }
+}
+
+impl<'h> MakePath<'h> {
+    /// The absolute path of `path`, relative to the state directory.
+    /// Parent directories are created by the host.
+    /// The host string is released with `free`, or `libc::free` if `None`.
+    pub fn make_path(&self, path: &Path, free: Option<&FreePath>) -> Option<PathBuf> {
+        let func = unsafe { (*self.raw).path }?;
+        let path = path_to_cstring(path)?;
+        unsafe {
+            host_path(func((*self.raw).handle, path.as_ptr()), free)
+        }
+    }
+}
+
+
+/// The `state:freePath` feature, releasing paths returned by the host.
+pub struct FreePath<'h> {
+    raw: *const LV2_State_Free_Path,
+    marker: marker::PhantomData<&'h ()>,
+}
+
+impl<'h> Feature<'h> for FreePath<'h> {
+    fn uri() -> &'static str {
+        inst::FREEPATH
+    }
+    unsafe fn from_raw(raw: &RawFeature<'h>) -> Self {
+        debug_assert!(raw.uri() == Self::uri());
+        FreePath {
+            raw: mem::transmute((*raw.raw).data),
+            marker: marker::PhantomData,
+        }
+    }
+}
+
+impl<'h> FreePath<'h> {
+    /// Frees `path`, which must have been returned by a host path function.
+    pub unsafe fn free_path(&self, path: *mut c_char) {
+        if let Some(func) = (*self.raw).free_path {
+            func((*self.raw).handle, path);
+        }
+    }
+}
+
+/// Converts and frees a path returned by the host.
+unsafe fn host_path(ptr: *mut c_char, free: Option<&FreePath>) -> Option<PathBuf> {
+    if ptr.is_null() {
+        return None;
+    }
+    let path = cstr_to_path(CStr::from_ptr(ptr));
+    match free {
+        Some(free) => free.free_path(ptr),
+        None => libc::free(ptr as *mut c_void),
+    }
+    Some(path)
+}
+
+#[cfg(unix)]
+fn path_to_cstring(path: &Path) -> Option<CString> {
+    use std::os::unix::ffi::OsStrExt;
+    CString::new(path.as_os_str().as_bytes()).ok()
+}
+
+#[cfg(not(unix))]
+fn path_to_cstring(path: &Path) -> Option<CString> {
+    CString::new(path.to_string_lossy().into_owned()).ok()
+}
+
+#[cfg(unix)]
+fn cstr_to_path(cstr: &CStr) -> PathBuf {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(OsStr::from_bytes(cstr.to_bytes()))
+}
+
+#[cfg(not(unix))]
+fn cstr_to_path(cstr: &CStr) -> PathBuf {
+    PathBuf::from(cstr.to_string_lossy().into_owned())
+}
+
+
+/// The `state:interface` extension for plugins implementing `State`.
+pub enum Interface {}
+
+unsafe impl<'h, T: State<'h>> Extension<'h, T> for Interface {
+    fn uri() -> &'static str {
+        inst::INTERFACE
+    }
+    fn interface() -> *const c_void {
+        let iface: &'static LV2_State_Interface = &LV2_State_Interface {
+            save: Some(save::<T>),
+            restore: Some(restore::<T>),
+        };
+        iface as *const LV2_State_Interface as *const c_void
+    }
+    fn shared(plugin: &T) -> Option<Arc<dyn Any + Send + Sync>> {
+        Some(State::shared(plugin))
+    }
+}
+
+extern "C" fn save<'h, T: State<'h>>(instance: LV2_Handle,
+                                     store: LV2_State_Store_Function,
+                                     handle: LV2_State_Handle,
+                                     flags: u32,
+                                     features: *const *const LV2_Feature) -> LV2_State_Status
+{
+    // save may run concurrently with run, hence only the shared state is used
+    let instance = unsafe { PluginInstance::<T>::from_handle(instance) };
+    let func = match store {
+        Some(func) => func,
+        None => return LV2_STATE_ERR_UNKNOWN,
+    };
+    let mut store = StateStore {
+        func: func,
+        handle: handle,
+        flags: Flags(flags),
+        features: features,
+        marker: marker::PhantomData,
+    };
+    let res = instance.call_shared("save", |shared| T::save(shared, &mut store));
+    Error::status(res.unwrap_or(Err(Error::Unknown)))
+}
+
+extern "C" fn restore<'h, T: State<'h>>(instance: LV2_Handle,
+                                        retrieve: LV2_State_Retrieve_Function,
+                                        handle: LV2_State_Handle,
+                                        flags: u32,
+                                        features: *const *const LV2_Feature) -> LV2_State_Status
+{
+    let instance = unsafe { PluginInstance::<T>::from_handle(instance) };
+    let func = match retrieve {
+        Some(func) => func,
+        None => return LV2_STATE_ERR_UNKNOWN,
+    };
+    let retrieve = StateRetrieve {
+        func: func,
+        handle: handle,
+        flags: Flags(flags),
+        features: features,
+        marker: marker::PhantomData,
+    };
+    let res = unsafe { instance.call("restore", |plugin| plugin.restore(&retrieve)) };
+    Error::status(res.unwrap_or(Err(Error::Unknown)))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::core::{self, Extensions, PortInfo, Ported};
+    use ::ffi::core::LV2_Descriptor;
+    use ::urid::URID;
+
+    use std::cell::Cell;
+    use std::ptr;
+    use std::sync::atomic::{AtomicI32, Ordering};
+
+    #[test]
+    fn flags() {
+        let flags = Flag::Pod | Flag::Portable;
+        assert!(flags.contains(Flag::Pod) && flags.contains(Flag::Portable));
+        assert!(!flags.contains(Flag::Native));
+        assert_eq!(flags.bits(), LV2_STATE_IS_POD | LV2_STATE_IS_PORTABLE);
+        assert_eq!(Flags::from_bits(flags.bits()), flags);
+        assert_eq!((flags | Flag::Native).bits(), 7);
+        assert_eq!(Flags::empty().with(Flag::Native), Flags::from(Flag::Native));
+        assert_eq!(Flags::default(), Flags::empty());
+    }
+
+    #[test]
+    fn status() {
+        let errors = [Error::Unknown, Error::BadType, Error::BadFlags,
+                      Error::NoFeature, Error::NoProperty, Error::NoSpace];
+        for &err in &errors {
+            assert!(Error::status(Err(err)) != LV2_STATE_SUCCESS);
+            assert_eq!(Error::from_status(Error::status(Err(err))), Err(err));
+        }
+        assert_eq!(Error::status(Ok(())), LV2_STATE_SUCCESS);
+        assert_eq!(Error::from_status(LV2_STATE_SUCCESS), Ok(()));
+        assert_eq!(Error::from_status(99), Err(Error::Unknown));
+    }
+
+    /// A host keeping the stored values.
+    #[derive(Default)]
+    struct Host {
+        full: bool,
+        values: Vec<(URID, URID, u32, Vec<u8>)>,
+    }
+
+    extern "C" fn store_value(handle: LV2_State_Handle, key: URID, value: *const c_void,
+                              size: usize, type_urid: URID, flags: u32) -> LV2_State_Status {
+        let host = unsafe { &mut *(handle as *mut Host) };
+        if host.full {
+            return LV2_STATE_ERR_NO_SPACE;
+        }
+        let body = unsafe { slice::from_raw_parts(value as *const u8, size) };
+        host.values.retain(|v| v.0 != key);
+        host.values.push((key, type_urid, flags, body.to_vec()));
+        LV2_STATE_SUCCESS
+    }
+
+    extern "C" fn retrieve_value(handle: LV2_State_Handle, key: URID, size: *mut usize,
+                                 type_urid: *mut URID, flags: *mut u32) -> *const c_void {
+        let host = unsafe { &*(handle as *const Host) };
+        match host.values.iter().find(|v| v.0 == key) {
+            Some(&(_, t, f, ref body)) => {
+                unsafe {
+                    *size = body.len();
+                    *type_urid = t;
+                    *flags = f;
+                }
+                body.as_ptr() as *const c_void
+            },
+            None => ptr::null(),
+        }
+    }
+
+    fn store<'a>(host: &'a mut Host, features: *const *const LV2_Feature) -> StateStore<'a> {
+        StateStore {
+            func: store_value,
+            handle: host as *mut Host as LV2_State_Handle,
+            flags: Flag::Portable.into(),
+            features: features,
+            marker: marker::PhantomData,
+        }
+    }
+
+    fn retrieve<'a>(host: &'a Host, features: *const *const LV2_Feature) -> StateRetrieve<'a> {
+        StateRetrieve {
+            func: retrieve_value,
+            handle: host as *const Host as LV2_State_Handle,
+            flags: Flags::empty(),
+            features: features,
+            marker: marker::PhantomData,
+        }
+    }
+
+    /// Duplicates `bytes` with `malloc`, as hosts do for returned paths.
+    fn host_string(bytes: &[u8]) -> *mut c_char {
+        let string = CString::new(bytes).unwrap();
+        unsafe { libc::strdup(string.as_ptr()) }
+    }
+
+    /// Abstract paths are relative to `/state/`.
+    extern "C" fn abstract_path(_handle: *mut c_void, path: *const c_char) -> *mut c_char {
+        let path = unsafe { CStr::from_ptr(path) }.to_bytes();
+        host_string(path.strip_prefix(b"/state/").unwrap_or(path))
+    }
+
+    extern "C" fn absolute_path(_handle: *mut c_void, path: *const c_char) -> *mut c_char {
+        let path = unsafe { CStr::from_ptr(path) }.to_bytes();
+        host_string(&[&b"/state/"[..], path].concat())
+    }
+
+    extern "C" fn free_path(handle: *mut c_void, path: *mut c_char) {
+        let frees = unsafe { &*(handle as *const Cell<usize>) };
+        frees.set(frees.get() + 1);
+        unsafe { libc::free(path as *mut c_void) };
+    }
+
+    /// Calls `f` with a feature list holding `state:mapPath`, and
+    /// `state:freePath` counting its calls if `free` is set.
+    fn with_path_features<F>(free: bool, f: F)
+        where F: FnOnce(*const *const LV2_Feature, &Cell<usize>)
+    {
+        let frees = Cell::new(0);
+        let map = LV2_State_Map_Path {
+            handle: ptr::null_mut(),
+            abstract_path: Some(abstract_path),
+            absolute_path: Some(absolute_path),
+        };
+        let free_raw = LV2_State_Free_Path {
+            handle: &frees as *const Cell<usize> as *mut c_void,
+            free_path: Some(free_path),
+        };
+        let map_uri = CString::new(inst::MAPPATH).unwrap();
+        let free_uri = CString::new(inst::FREEPATH).unwrap();
+        let features = [
+            LV2_Feature { URI: map_uri.as_ptr(), data: &map as *const _ as *mut c_void },
+            LV2_Feature { URI: free_uri.as_ptr(), data: &free_raw as *const _ as *mut c_void },
+        ];
+        let list = if free {
+            vec![&features[0] as *const LV2_Feature, &features[1], ptr::null()]
+        }
+        else {
+            vec![&features[0] as *const LV2_Feature, ptr::null()]
+        };
+        f(list.as_ptr(), &frees);
+    }
+
+    #[test]
+    fn store_retrieve() {
+        let mut host = Host::default();
+        {
+            let mut store = store(&mut host, ptr::null());
+            assert_eq!(store.flags(), Flags::from(Flag::Portable));
+            store.store(1, 99, &[1, 2], Flag::Native.into()).unwrap();
+        }
+        assert_eq!(host.values, vec![(1, 99, Flag::Native as u32, vec![1, 2])]);
+        {
+            let retrieve = retrieve(&host, ptr::null());
+            let value = retrieve.retrieve(1).unwrap();
+            assert_eq!((value.type_urid(), value.flags(), value.body()),
+                       (99, Flags::from(Flag::Native), &[1u8, 2][..]));
+            assert_eq!(retrieve.retrieve(2).err(), Some(Error::NoProperty));
+        }
+        host.full = true;
+        assert_eq!(store(&mut host, ptr::null()).store(1, 99, &[3], Flags::empty()),
+                   Err(Error::NoSpace));
+    }
+
+    #[test]
+    fn host_path_free() {
+        with_path_features(true, |features, frees| {
+            let free = unsafe { FeatureList::from_raw(features) }.feature::<FreePath>();
+            assert!(free.is_some());
+            assert_eq!(unsafe { host_path(ptr::null_mut(), free.as_ref()) }, None);
+            assert_eq!(frees.get(), 0);
+            let path = unsafe { host_path(host_string(b"/a"), free.as_ref()) };
+            assert_eq!(path, Some(PathBuf::from("/a")));
+            assert_eq!(frees.get(), 1);
+        });
+        // released with libc::free without the feature
+        let path = unsafe { host_path(host_string(b"/b"), None) };
+        assert_eq!(path, Some(PathBuf::from("/b")));
+    }
+
+    #[cfg(feature = "atom")]
+    #[test]
+    fn store_values() {
+        let types = Types::test();
+        let mut host = Host::default();
+        {
+            let mut store = store(&mut host, ptr::null());
+            store.store_scalar(1, &types, 5i32).unwrap();
+            store.store_str(2, &types, "abc").unwrap();
+            let atom = [4u32, types.float, 1.5f32.to_bits()];
+            let atom = unsafe { &*(atom.as_ptr() as *const Header) };
+            store.store_atom(3, atom, Flag::Pod.into()).unwrap();
+            store.store_path(4, &types, Path::new("/state/a.wav")).unwrap();
+        }
+        let pod = (Flag::Pod | Flag::Portable).bits();
+        assert_eq!(host.values, vec![
+            (1, types.int, pod, 5i32.to_ne_bytes().to_vec()),
+            (2, types.string, pod, b"abc\0".to_vec()),
+            (3, types.float, Flag::Pod as u32, 1.5f32.to_ne_bytes().to_vec()),
+            (4, types.path, pod, b"/state/a.wav\0".to_vec()),
+        ]);
+    }
+
+    #[cfg(feature = "atom")]
+    #[test]
+    fn store_mapped_path() {
+        let types = Types::test();
+        for &free in &[true, false] {
+            with_path_features(free, |features, frees| {
+                let mut host = Host::default();
+                store(&mut host, features).store_path(1, &types, Path::new("/state/a.wav")).unwrap();
+                assert_eq!(host.values[0].3, b"a.wav\0".to_vec());
+                assert_eq!(frees.get(), free as usize);
+                let path = retrieve(&host, features).retrieve_path(1, &types);
+                assert_eq!(path, Ok(PathBuf::from("/state/a.wav")));
+                assert_eq!(frees.get(), 2 * free as usize);
+            });
+        }
+    }
+
+    #[cfg(feature = "atom")]
+    #[test]
+    fn retrieve_values() {
+        let types = Types::test();
+        let mut host = Host::default();
+        {
+            let mut store = store(&mut host, ptr::null());
+            store.store_scalar(1, &types, 5i32).unwrap();
+            store.store_str(2, &types, "abc").unwrap();
+            store.store_path(3, &types, Path::new("/a.wav")).unwrap();
+            store.store(4, types.string, b"ab", Flags::empty()).unwrap();
+        }
+        let retrieve = retrieve(&host, ptr::null());
+        assert_eq!(retrieve.retrieve(9).err(), Some(Error::NoProperty));
+
+        let int = retrieve.retrieve(1).unwrap();
+        assert_eq!(int.type_urid(), types.int);
+        assert_eq!(int.flags(), Flag::Pod | Flag::Portable);
+        assert_eq!(int.scalar::<i32>(&types), Ok(5));
+        assert_eq!(int.scalar::<f32>(&types), Err(Error::BadType));
+        assert_eq!(int.as_str(&types), Err(Error::BadType));
+        assert_eq!(int.as_path(&types), Err(Error::BadType));
+
+        let string = retrieve.retrieve(2).unwrap();
+        assert_eq!(string.as_str(&types), Ok("abc"));
+        assert_eq!(string.scalar::<i32>(&types), Err(Error::BadType));
+        assert_eq!(retrieve.retrieve(4).unwrap().as_str(&types), Err(Error::BadType));
+
+        assert_eq!(retrieve.retrieve(3).unwrap().as_path(&types), Ok(PathBuf::from("/a.wav")));
+        assert_eq!(retrieve.retrieve_path(3, &types), Ok(PathBuf::from("/a.wav")));
+        assert_eq!(retrieve.retrieve_path(2, &types), Err(Error::BadType));
+    }
+
+    const INT: URID = 6;
+
+    struct Counter {
+        count: Arc<AtomicI32>,
+    }
+
+    unsafe impl<'h> Ported<'h> for Counter {
+        type Ports = ();
+        type PortsRaw = ();
+        fn new_ports_raw() {}
+        fn connect_port(_port: usize, _data: *mut (), _ports_raw: &mut ()) {}
+        fn convert_ports(_ports_raw: (), _sample_count: usize) {}
+        fn silence_outputs(_ports_raw: (), _sample_count: usize) {}
+        fn port_infos() -> Vec<PortInfo> {
+            Vec::new()
+        }
+    }
+
+    impl<'h> Plugin<'h> for Counter {
+        type Features = ();
+        fn new(_sample_rate: f64, _bundle_path: &str, _features: ()) -> Option<Counter> {
+            Some(Counter { count: Arc::new(AtomicI32::new(3)) })
+        }
+        fn run(&mut self, _ports: &mut (), _sample_count: usize) {
+            self.count.fetch_add(1, Ordering::Relaxed);
+        }
+        fn extension_data(uri: &str) -> Option<*const c_void> {
+            <(Interface,) as Extensions<Self>>::interface(uri)
+        }
+        fn extension_shared(&self) -> Option<Arc<dyn Any + Send + Sync>> {
+            <(Interface,) as Extensions<Self>>::shared(self)
+        }
+    }
+
+    impl<'h> State<'h> for Counter {
+        type Shared = AtomicI32;
+        fn shared(&self) -> Arc<AtomicI32> {
+            self.count.clone()
+        }
+        fn save(count: &AtomicI32, store: &mut StateStore) -> Result<(), Error> {
+            let count = count.load(Ordering::Relaxed).to_ne_bytes();
+            store.store(1, INT, &count, Flag::Pod | Flag::Portable)
+        }
+        fn restore(&mut self, retrieve: &StateRetrieve) -> Result<(), Error> {
+            let value = retrieve.retrieve(1)?;
+            if value.type_urid() != INT || value.body().len() != 4 {
+                return Err(Error::BadType);
+            }
+            let mut count = [0; 4];
+            count.copy_from_slice(value.body());
+            self.count.store(i32::from_ne_bytes(count), Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn save_restore() {
+        let bundle = CString::new("/").unwrap();
+        let handle = core::instantiate::<Counter>(ptr::null::<LV2_Descriptor>(), 48000.0,
+                                                  bundle.as_ptr(), ptr::null());
+        assert!(!handle.is_null());
+        let uri = CString::new(inst::INTERFACE).unwrap();
+        let iface = core::extension_data::<Counter>(uri.as_ptr()) as *const LV2_State_Interface;
+        let iface = unsafe { &*iface };
+
+        let mut host = Host::default();
+        core::run::<Counter>(handle, 0);
+        let status = (iface.save.unwrap())(handle, Some(store_value),
+                                           &mut host as *mut Host as LV2_State_Handle,
+                                           0, ptr::null());
+        assert_eq!(status, LV2_STATE_SUCCESS);
+        assert_eq!(host.values[0].3, 4i32.to_ne_bytes().to_vec());
+
+        host.values[0].3 = 9i32.to_ne_bytes().to_vec();
+        let status = (iface.restore.unwrap())(handle, Some(retrieve_value),
+                                              &mut host as *mut Host as LV2_State_Handle,
+                                              0, ptr::null());
+        assert_eq!(status, LV2_STATE_SUCCESS);
+        host.values.clear();
+        (iface.save.unwrap())(handle, Some(store_value),
+                              &mut host as *mut Host as LV2_State_Handle, 0, ptr::null());
+        assert_eq!(host.values[0].3, 9i32.to_ne_bytes().to_vec());
+
+        let status = (iface.restore.unwrap())(handle, Some(retrieve_value),
+                                              &mut Host::default() as *mut Host as LV2_State_Handle,
+                                              0, ptr::null());
+        assert_eq!(status, LV2_STATE_ERR_NO_PROPERTY);
+        core::cleanup::<Counter>(handle);
+    }
+}
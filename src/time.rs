@@ -0,0 +1,386 @@
+
+#[cfg(feature = "atom")]
+use ::atom::{self, Header, Value};
+use ::urid::{self, URID};
+
+#[cfg(feature = "atom")]
+use std::convert::TryFrom;
+
+pub const URI: &'static str = "http://lv2plug.in/ns/ext/time";
+
+pub mod class {
+    pub const POSITION        : &'static str = "http://lv2plug.in/ns/ext/time#Position";
+    pub const RATE            : &'static str = "http://lv2plug.in/ns/ext/time#Rate";
+    pub const TIME            : &'static str = "http://lv2plug.in/ns/ext/time#Time";
+}
+pub mod prop {
+    pub const BAR             : &'static str = "http://lv2plug.in/ns/ext/time#bar";
+    pub const BARBEAT         : &'static str = "http://lv2plug.in/ns/ext/time#barBeat";
+    pub const BEAT            : &'static str = "http://lv2plug.in/ns/ext/time#beat";
+    pub const BEATUNIT        : &'static str = "http://lv2plug.in/ns/ext/time#beatUnit";
+    pub const BEATSPERBAR     : &'static str = "http://lv2plug.in/ns/ext/time#beatsPerBar";
+    pub const BEATSPERMINUTE  : &'static str = "http://lv2plug.in/ns/ext/time#beatsPerMinute";
+    pub const FRAME           : &'static str = "http://lv2plug.in/ns/ext/time#frame";
+    pub const FRAMESPERSECOND : &'static str = "http://lv2plug.in/ns/ext/time#framesPerSecond";
+    pub const POSITION        : &'static str = "http://lv2plug.in/ns/ext/time#position";
+    pub const SPEED           : &'static str = "http://lv2plug.in/ns/ext/time#speed";
+}
+
+
+/// URIDs of the time class and properties, mapped once with `Urids::new`.
+#[derive(Copy, Clone, Debug)]
+pub struct Urids {
+    pub position: URID,
+    pub bar: URID,
+    pub bar_beat: URID,
+    pub beat: URID,
+    pub beat_unit: URID,
+    pub beats_per_bar: URID,
+    pub beats_per_minute: URID,
+    pub frame: URID,
+    pub frames_per_second: URID,
+    pub speed: URID,
+}
+
+impl Urids {
+    pub fn new(map: &urid::Map) -> Urids {
+        Urids {
+            position: map.map(class::POSITION),
+            bar: map.map(prop::BAR),
+            bar_beat: map.map(prop::BARBEAT),
+            beat: map.map(prop::BEAT),
+            beat_unit: map.map(prop::BEATUNIT),
+            beats_per_bar: map.map(prop::BEATSPERBAR),
+            beats_per_minute: map.map(prop::BEATSPERMINUTE),
+            frame: map.map(prop::FRAME),
+            frames_per_second: map.map(prop::FRAMESPERSECOND),
+            speed: map.map(prop::SPEED),
+        }
+    }
+}
+
+
+/// A transport position, as sent by hosts in `time:Position` objects.
+/// Fields absent from the object are `None`.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Position {
+    /// Transport frame.
+    pub frame: Option<i64>,
+    /// Transport speed: 0 when stopped, 1 when rolling forward at nominal speed.
+    pub speed: Option<f64>,
+    pub bar: Option<i64>,
+    /// Beat within the bar, from 0.
+    pub bar_beat: Option<f64>,
+    /// Beat since the start of the timeline.
+    pub beat: Option<f64>,
+    /// Note value of a beat, e.g. 4 for quarter notes.
+    pub beat_unit: Option<i32>,
+    pub beats_per_bar: Option<f64>,
+    pub beats_per_minute: Option<f64>,
+    pub frames_per_second: Option<f64>,
+}
+
+impl Position {
+    /// Decodes a `time:Position` object, or returns `None` if `object` is of another type.
+    /// Numeric properties are accepted as any of the atom number types.
+    #[cfg(feature = "atom")]
+    pub fn from_object(object: &atom::Object, urids: &Urids, types: &atom::Types)
+        -> Option<Position>
+    {
+        if object.otype() != urids.position {
+            return None;
+        }
+        let mut pos = Position::default();
+        for (key, _, value) in object.iter() {
+            let num = match number(value, types) {
+                Some(num) => num,
+                None => continue,
+            };
+            if key == urids.frame {
+                pos.frame = integer(value, types);
+            }
+            else if key == urids.speed {
+                pos.speed = Some(num);
+            }
+            else if key == urids.bar {
+                pos.bar = integer(value, types);
+            }
+            else if key == urids.bar_beat {
+                pos.bar_beat = Some(num);
+            }
+            else if key == urids.beat {
+                pos.beat = Some(num);
+            }
+            else if key == urids.beat_unit {
+                pos.beat_unit = integer(value, types).and_then(|unit| i32::try_from(unit).ok());
+            }
+            else if key == urids.beats_per_bar {
+                pos.beats_per_bar = Some(num);
+            }
+            else if key == urids.beats_per_minute {
+                pos.beats_per_minute = Some(num);
+            }
+            else if key == urids.frames_per_second {
+                pos.frames_per_second = Some(num);
+            }
+        }
+        Some(pos)
+    }
+
+    /// Overwrites the fields of `self` that are set in `other`.
+    pub fn merge(&mut self, other: &Position) {
+        fn set<T: Copy>(field: &mut Option<T>, other: Option<T>) {
+            if other.is_some() {
+                *field = other;
+            }
+        }
+        set(&mut self.frame, other.frame);
+        set(&mut self.speed, other.speed);
+        set(&mut self.bar, other.bar);
+        set(&mut self.bar_beat, other.bar_beat);
+        set(&mut self.beat, other.beat);
+        set(&mut self.beat_unit, other.beat_unit);
+        set(&mut self.beats_per_bar, other.beats_per_bar);
+        set(&mut self.beats_per_minute, other.beats_per_minute);
+        set(&mut self.frames_per_second, other.frames_per_second);
+    }
+}
+
+/// Integer atoms as is, so that large frame counts are exact, and others truncated.
+#[cfg(feature = "atom")]
+fn integer(atom: &Header, types: &atom::Types) -> Option<i64> {
+    if let Some(v) = atom.downcast::<atom::Long>(types) {
+        Some(v.value())
+    }
+    else if let Some(v) = atom.downcast::<atom::Int>(types) {
+        Some(v.value() as i64)
+    }
+    else {
+        number(atom, types).map(|num| num as i64)
+    }
+}
+
+#[cfg(feature = "atom")]
+fn number(atom: &Header, types: &atom::Types) -> Option<f64> {
+    if let Some(v) = atom.downcast::<atom::Int>(types) {
+        Some(v.value() as f64)
+    }
+    else if let Some(v) = atom.downcast::<atom::Long>(types) {
+        Some(v.value() as f64)
+    }
+    else if let Some(v) = atom.downcast::<atom::Float>(types) {
+        Some(v.value() as f64)
+    }
+    else if let Some(v) = atom.downcast::<atom::Double>(types) {
+        Some(v.value())
+    }
+    else {
+        None
+    }
+}
+
+
+/// Tracks the host transport across `run` calls.
+/// Feed it the positions received during a block with `update`, query it
+/// with `position_at`, and call `advance` at the end of each block.
+/// A missing speed counts as 1.
+#[derive(Copy, Clone, Debug)]
+pub struct Transport {
+    sample_rate: f64,
+    // position of the last update, from which positions are extrapolated
+    anchor: Position,
+    // frames from the anchor to the start of the current block
+    elapsed: i64,
+}
+
+impl Transport {
+    pub fn new(sample_rate: f64) -> Transport {
+        Transport {
+            sample_rate: sample_rate,
+            anchor: Position::default(),
+            elapsed: 0,
+        }
+    }
+
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    /// Whether the transport is rolling.
+    pub fn rolling(&self) -> bool {
+        self.anchor.speed.unwrap_or(1.0) != 0.0
+    }
+
+    /// Applies a position received at frame `offset` of the current block.
+    pub fn update(&mut self, offset: i64, pos: &Position) {
+        self.anchor = self.position_at(offset);
+        self.anchor.merge(pos);
+        self.elapsed = -offset;
+    }
+
+    /// Applies the `time:Position` objects of a control sequence.
+    /// Events in beats are placed with the current tempo.
+    #[cfg(feature = "atom")]
+    pub fn update_from_sequence(&mut self, seq: &atom::Sequence, urids: &Urids, types: &atom::Types) {
+        for (time, ev) in seq.timed(types) {
+            let object = match ev.downcast::<atom::Object>(types) {
+                Some(object) => object,
+                None => continue,
+            };
+            if let Some(pos) = Position::from_object(object, urids, types) {
+                let offset = match time {
+                    atom::Time::Frames(frames) => frames,
+                    atom::Time::Beats(_) => self.anchor.beats_per_minute
+                        .map_or((-self.elapsed).max(0), |bpm| time.to_frames(bpm, self.sample_rate)),
+                };
+                self.update(offset, &pos);
+            }
+        }
+    }
+
+    /// The position at frame `offset` of the current block, extrapolated
+    /// from the last update with the current speed and tempo.
+    pub fn position_at(&self, offset: i64) -> Position {
+        let mut pos = self.anchor;
+        let frames = (self.elapsed + offset) as f64 * pos.speed.unwrap_or(1.0);
+        if let Some(frame) = pos.frame {
+            pos.frame = Some(frame + frames.round() as i64);
+        }
+        let bpm = match pos.beats_per_minute {
+            Some(bpm) => bpm,
+            None => return pos,
+        };
+        let beats = frames * bpm / (60.0 * self.sample_rate);
+        if let Some(beat) = pos.beat {
+            pos.beat = Some(beat + beats);
+        }
+        if let Some(bar_beat) = pos.bar_beat {
+            let bar_beat = bar_beat + beats;
+            match (pos.beats_per_bar, pos.bar) {
+                (Some(bpb), Some(bar)) if bpb > 0.0 => {
+                    pos.bar = Some(bar + (bar_beat / bpb).floor() as i64);
+                    pos.bar_beat = Some(bar_beat.rem_euclid(bpb));
+                },
+                _ => pos.bar_beat = Some(bar_beat),
+            }
+        }
+        pos
+    }
+
+    /// Moves to the next block, `sample_count` frames after the current one.
+    pub fn advance(&mut self, sample_count: usize) {
+        self.elapsed += sample_count as i64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "atom")]
+    #[test]
+    fn decode_position() {
+        use ::atom::testing::forged;
+        use ::urid::tests::with_host;
+
+        with_host(|map, _| {
+            let urids = Urids::new(map);
+            let types = atom::Types::new(map);
+            let forged = forged(&types, |forge| {
+                let frame = forge.object(0, urids.position).unwrap();
+                forge.key(urids.frame).unwrap();
+                forge.long((1 << 53) + 1).unwrap();
+                forge.key(urids.speed).unwrap();
+                forge.float(1.0).unwrap();
+                forge.key(urids.bar).unwrap();
+                forge.double(3.0).unwrap();
+                forge.key(urids.beat_unit).unwrap();
+                forge.int(4).unwrap();
+                forge.pop(frame);
+            });
+            let object = forged.atom(0).downcast::<atom::Object>(&types).unwrap();
+            let pos = Position::from_object(object, &urids, &types).unwrap();
+            assert_eq!(pos, Position {
+                frame: Some((1 << 53) + 1),
+                speed: Some(1.0),
+                bar: Some(3),
+                beat_unit: Some(4),
+                ..Position::default()
+            });
+        });
+    }
+
+    #[cfg(feature = "atom")]
+    #[test]
+    fn decode_beat_unit() {
+        use ::atom::testing::forged;
+        use ::urid::tests::with_host;
+
+        with_host(|map, _| {
+            let urids = Urids::new(map);
+            let types = atom::Types::new(map);
+            let decode = |f: &dyn Fn(&mut atom::Forge)| {
+                let forged = forged(&types, |forge| {
+                    let frame = forge.object(0, urids.position).unwrap();
+                    forge.key(urids.beat_unit).unwrap();
+                    f(forge);
+                    forge.pop(frame);
+                });
+                let object = forged.atom(0).downcast::<atom::Object>(&types).unwrap();
+                Position::from_object(object, &urids, &types).unwrap().beat_unit
+            };
+            assert_eq!(decode(&|forge| { forge.double(8.0).unwrap(); }), Some(8));
+            assert_eq!(decode(&|forge| { forge.long(-4).unwrap(); }), Some(-4));
+            assert_eq!(decode(&|forge| { forge.long(1 << 32).unwrap(); }), None);
+        });
+    }
+
+    #[test]
+    fn fractional_speed() {
+        let mut transport = Transport::new(48000.0);
+        transport.update(0, &Position { frame: Some(0), speed: Some(0.5), ..Position::default() });
+        for _ in 0 .. 100 {
+            transport.advance(1);
+        }
+        assert_eq!(transport.position_at(0).frame, Some(50));
+        assert_eq!(transport.position_at(3).frame, Some(52));
+    }
+
+    #[test]
+    fn beats_and_bars() {
+        let mut transport = Transport::new(48000.0);
+        transport.update(16, &Position {
+            frame: Some(1000),
+            bar: Some(1),
+            bar_beat: Some(3.5),
+            beat: Some(7.5),
+            beats_per_bar: Some(4.0),
+            beats_per_minute: Some(120.0),
+            ..Position::default()
+        });
+        assert!(transport.rolling());
+        // half a second is a beat at 120 bpm
+        transport.advance(24016);
+        let pos = transport.position_at(0);
+        assert_eq!(pos.frame, Some(25000));
+        assert_eq!(pos.beat, Some(8.5));
+        assert_eq!(pos.bar, Some(2));
+        assert_eq!(pos.bar_beat, Some(0.5));
+    }
+
+    #[test]
+    fn update_mid_block() {
+        let mut transport = Transport::new(44100.0);
+        transport.update(0, &Position { frame: Some(0), ..Position::default() });
+        transport.advance(64);
+        // the host relocates at frame 10 of the block and stops
+        transport.update(10, &Position { frame: Some(5000), speed: Some(0.0), ..Position::default() });
+        assert!(!transport.rolling());
+        assert_eq!(transport.position_at(5).frame, Some(5000));
+        assert_eq!(transport.position_at(63).frame, Some(5000));
+        transport.update(20, &Position { speed: Some(1.0), ..Position::default() });
+        assert_eq!(transport.position_at(30).frame, Some(5010));
+        transport.advance(64);
+        assert_eq!(transport.position_at(0).frame, Some(5044));
+    }
+}
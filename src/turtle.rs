@@ -0,0 +1,1192 @@
+//! Minimal Turtle support: a writer producing readable documents and a parser
+//! producing triples, enough to generate and check LV2 data files.
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt::{self, Write};
+
+pub mod rdf {
+    pub const FIRST:   &'static str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#first";
+    pub const NIL:     &'static str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#nil";
+    pub const REST:    &'static str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#rest";
+    pub const TYPE:    &'static str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+    pub const VALUE:   &'static str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#value";
+}
+
+pub mod rdfs {
+    pub const LABEL:   &'static str = "http://www.w3.org/2000/01/rdf-schema#label";
+    pub const SEEALSO: &'static str = "http://www.w3.org/2000/01/rdf-schema#seeAlso";
+}
+
+pub mod xsd {
+    pub const ANYURI:  &'static str = "http://www.w3.org/2001/XMLSchema#anyURI";
+    pub const BASE64BINARY: &'static str = "http://www.w3.org/2001/XMLSchema#base64Binary";
+    pub const BOOLEAN: &'static str = "http://www.w3.org/2001/XMLSchema#boolean";
+    pub const DECIMAL: &'static str = "http://www.w3.org/2001/XMLSchema#decimal";
+    pub const DOUBLE:  &'static str = "http://www.w3.org/2001/XMLSchema#double";
+    pub const FLOAT:   &'static str = "http://www.w3.org/2001/XMLSchema#float";
+    pub const INT:     &'static str = "http://www.w3.org/2001/XMLSchema#int";
+    pub const INTEGER: &'static str = "http://www.w3.org/2001/XMLSchema#integer";
+    pub const LONG:    &'static str = "http://www.w3.org/2001/XMLSchema#long";
+    pub const STRING:  &'static str = "http://www.w3.org/2001/XMLSchema#string";
+}
+
+pub mod doap {
+    pub const LICENSE: &'static str = "http://usefulinc.com/ns/doap#license";
+    pub const NAME:    &'static str = "http://usefulinc.com/ns/doap#name";
+}
+
+
+/// An RDF term.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Term {
+    Iri(String),
+    /// A blank node, identified by its label.
+    Blank(String),
+    Literal {
+        value: String,
+        datatype: Option<String>,
+        lang: Option<String>,
+    },
+}
+
+impl Term {
+    pub fn iri<S: Into<String>>(iri: S) -> Term {
+        Term::Iri(iri.into())
+    }
+
+    /// A plain string literal.
+    pub fn string<S: Into<String>>(value: S) -> Term {
+        Term::Literal { value: value.into(), datatype: None, lang: None }
+    }
+
+    pub fn typed<S: Into<String>>(value: S, datatype: &str) -> Term {
+        Term::Literal { value: value.into(), datatype: Some(datatype.to_string()), lang: None }
+    }
+
+    pub fn integer(value: i64) -> Term {
+        Term::typed(value.to_string(), xsd::INTEGER)
+    }
+
+    /// A number written with the shortest representation of `value`.
+    /// Infinities and NaN are written as the special `xsd:float` values.
+    pub fn number(value: f32) -> Term {
+        if value.is_nan() {
+            return Term::typed("NaN", xsd::FLOAT);
+        }
+        if value.is_infinite() {
+            return Term::typed(if value > 0.0 { "INF" } else { "-INF" }, xsd::FLOAT);
+        }
+        let repr = format!("{:?}", value);
+        if repr.contains('e') {
+            Term::typed(repr, xsd::DOUBLE)
+        }
+        else {
+            Term::typed(repr, xsd::DECIMAL)
+        }
+    }
+
+    pub fn boolean(value: bool) -> Term {
+        Term::typed(value.to_string(), xsd::BOOLEAN)
+    }
+
+    pub fn as_iri(&self) -> Option<&str> {
+        match *self {
+            Term::Iri(ref iri) => Some(iri),
+            _ => None,
+        }
+    }
+
+    /// The lexical value of a literal.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Term::Literal { ref value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_str().and_then(|v| v.parse().ok())
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_str().and_then(|v| v.parse().ok())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Triple {
+    pub subject: Term,
+    pub predicate: String,
+    pub object: Term,
+}
+
+
+/// The object of a statement in a document being written.
+#[derive(Clone, Debug)]
+pub enum Object {
+    Term(Term),
+    /// A nested node, written inline when anonymous.
+    Node(Node),
+    /// An RDF collection.
+    List(Vec<Object>),
+}
+
+impl From<Term> for Object {
+    fn from(term: Term) -> Object {
+        Object::Term(term)
+    }
+}
+
+impl From<Node> for Object {
+    fn from(node: Node) -> Object {
+        Object::Node(node)
+    }
+}
+
+/// A subject and its properties.
+#[derive(Clone, Debug)]
+pub struct Node {
+    /// `None` for an anonymous blank node, written as `[ ... ]`.
+    pub subject: Option<Term>,
+    pub properties: Vec<(String, Object)>,
+}
+
+impl Node {
+    pub fn new(subject: Term) -> Node {
+        Node { subject: Some(subject), properties: Vec::new() }
+    }
+
+    pub fn blank() -> Node {
+        Node { subject: None, properties: Vec::new() }
+    }
+
+    pub fn add<O: Into<Object>>(&mut self, predicate: &str, object: O) -> &mut Node {
+        self.properties.push((predicate.to_string(), object.into()));
+        self
+    }
+
+    /// Builder variant of `add`.
+    pub fn with<O: Into<Object>>(mut self, predicate: &str, object: O) -> Node {
+        self.add(predicate, object);
+        self
+    }
+}
+
+/// A Turtle document being written.
+#[derive(Clone, Debug, Default)]
+pub struct Document {
+    prefixes: Vec<(String, String)>,
+    pub nodes: Vec<Node>,
+}
+
+impl Document {
+    pub fn new() -> Document {
+        Document::default()
+    }
+
+    /// Declares a prefix. Only the prefixes actually used are written.
+    pub fn prefix(&mut self, name: &str, namespace: &str) -> &mut Document {
+        self.prefixes.push((name.to_string(), namespace.to_string()));
+        self
+    }
+
+    pub fn push(&mut self, node: Node) -> &mut Document {
+        self.nodes.push(node);
+        self
+    }
+
+    fn compact(&self, iri: &str, used: &mut Vec<usize>) -> String {
+        for (i, &(ref name, ref ns)) in self.prefixes.iter().enumerate() {
+            if iri.starts_with(ns.as_str()) && is_local_name(&iri[ns.len() ..]) {
+                if !used.contains(&i) {
+                    used.push(i);
+                }
+                return format!("{}:{}", name, &iri[ns.len() ..]);
+            }
+        }
+        format!("<{}>", iri)
+    }
+
+    fn write_term(&self, out: &mut String, term: &Term, used: &mut Vec<usize>) {
+        match *term {
+            Term::Iri(ref iri) => out.push_str(&self.compact(iri, used)),
+            Term::Blank(ref label) => {
+                out.push_str("_:");
+                out.push_str(label);
+            },
+            Term::Literal { ref value, ref datatype, ref lang } => {
+                let bare = match datatype.as_ref().map(|dt| dt.as_str()) {
+                    Some(xsd::INTEGER) => is_integer(value),
+                    Some(xsd::DECIMAL) => is_decimal(value),
+                    Some(xsd::DOUBLE) => is_double(value),
+                    Some(xsd::BOOLEAN) => value == "true" || value == "false",
+                    _ => false,
+                };
+                if bare {
+                    out.push_str(value);
+                    return;
+                }
+                write_string(out, value);
+                if let Some(ref lang) = *lang {
+                    out.push('@');
+                    out.push_str(lang);
+                }
+                else if let Some(ref dt) = *datatype {
+                    if dt != xsd::STRING {
+                        out.push_str("^^");
+                        out.push_str(&self.compact(dt, used));
+                    }
+                }
+            },
+        }
+    }
+
+    fn write_object(&self, out: &mut String, obj: &Object, depth: usize, used: &mut Vec<usize>) {
+        match *obj {
+            Object::Term(ref term) => self.write_term(out, term, used),
+            Object::Node(ref node) => match node.subject {
+                Some(ref subject) => self.write_term(out, subject, used),
+                None if node.properties.is_empty() => out.push_str("[]"),
+                None => {
+                    out.push_str("[\n");
+                    self.write_properties(out, node, depth + 1, used);
+                    out.push('\n');
+                    indent(out, depth);
+                    out.push(']');
+                },
+            },
+            Object::List(ref items) => {
+                out.push('(');
+                for item in items {
+                    out.push(' ');
+                    self.write_object(out, item, depth, used);
+                }
+                out.push_str(" )");
+            },
+        }
+    }
+
+    fn write_properties(&self, out: &mut String, node: &Node, depth: usize, used: &mut Vec<usize>) {
+        let mut written = vec![false; node.properties.len()];
+        let mut first = true;
+        for i in 0 .. node.properties.len() {
+            if written[i] {
+                continue;
+            }
+            let pred = &node.properties[i].0;
+            if !first {
+                out.push_str(" ;\n");
+            }
+            first = false;
+            indent(out, depth);
+            if pred == rdf::TYPE {
+                out.push('a');
+            }
+            else {
+                out.push_str(&self.compact(pred, used));
+            }
+            out.push(' ');
+            let mut first_obj = true;
+            for (j, &(ref p, ref obj)) in node.properties.iter().enumerate().skip(i) {
+                if written[j] || p != pred {
+                    continue;
+                }
+                written[j] = true;
+                if !first_obj {
+                    out.push_str(" , ");
+                }
+                first_obj = false;
+                self.write_object(out, obj, depth, used);
+            }
+        }
+    }
+
+    /// Nested named nodes are written as separate statements after their parent.
+    fn collect_named<'a>(obj: &'a Object, named: &mut Vec<&'a Node>) {
+        match *obj {
+            Object::Node(ref node) => {
+                if node.subject.is_some() && !node.properties.is_empty() {
+                    named.push(node);
+                }
+                for &(_, ref o) in &node.properties {
+                    Document::collect_named(o, named);
+                }
+            },
+            Object::List(ref items) => {
+                for item in items {
+                    Document::collect_named(item, named);
+                }
+            },
+            Object::Term(_) => {},
+        }
+    }
+
+    fn write_node(&self, out: &mut String, node: &Node, used: &mut Vec<usize>) {
+        out.push('\n');
+        match node.subject {
+            Some(ref subject) => {
+                self.write_term(out, subject, used);
+                out.push('\n');
+                self.write_properties(out, node, 1, used);
+            },
+            None => {
+                out.push_str("[\n");
+                self.write_properties(out, node, 1, used);
+                out.push_str("\n]");
+            },
+        }
+        out.push_str(" .\n");
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut used = Vec::new();
+        let mut body = String::new();
+        for node in &self.nodes {
+            self.write_node(&mut body, node, &mut used);
+            let mut named = Vec::new();
+            for &(_, ref o) in &node.properties {
+                Document::collect_named(o, &mut named);
+            }
+            for n in named {
+                self.write_node(&mut body, n, &mut used);
+            }
+        }
+        used.sort();
+        let width = used.iter().map(|&i| self.prefixes[i].0.len()).max().unwrap_or(0);
+        for i in used {
+            let (ref name, ref ns) = self.prefixes[i];
+            let name = format!("{}:", name);
+            writeln!(f, "@prefix {:w$} <{}> .", name, ns, w = width + 1)?;
+        }
+        f.write_str(&body)
+    }
+}
+
+fn indent(out: &mut String, depth: usize) {
+    for _ in 0 .. depth {
+        out.push('\t');
+    }
+}
+
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04X}", c as u32);
+            },
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn is_local_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_alphanumeric() || c == '_' => {},
+        _ => return false,
+    }
+    chars.all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+fn is_digits(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn strip_sign(s: &str) -> &str {
+    if s.starts_with('+') || s.starts_with('-') { &s[1 ..] } else { s }
+}
+
+fn is_integer(s: &str) -> bool {
+    is_digits(strip_sign(s))
+}
+
+fn is_decimal(s: &str) -> bool {
+    let s = strip_sign(s);
+    match s.find('.') {
+        Some(dot) => (dot == 0 || is_digits(&s[.. dot])) && is_digits(&s[dot + 1 ..]),
+        None => false,
+    }
+}
+
+fn is_double(s: &str) -> bool {
+    let s = strip_sign(s);
+    match s.find(&['e', 'E'][..]) {
+        Some(e) => {
+            let mantissa = &s[.. e];
+            (is_digits(mantissa) || is_decimal(mantissa) || mantissa.ends_with('.')
+                    && is_digits(&mantissa[.. mantissa.len() - 1]))
+                && is_integer(&s[e + 1 ..])
+        },
+        None => false,
+    }
+}
+
+
+/// A Turtle syntax error.
+#[derive(Clone, Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.msg)
+    }
+}
+
+impl error::Error for ParseError {}
+
+/// Parses a Turtle document. Relative IRIs are resolved against `@base`
+/// if one is declared, and kept as written otherwise.
+pub fn parse(text: &str) -> Result<Vec<Triple>, ParseError> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        pos: 0,
+        line: 1,
+        prefixes: HashMap::new(),
+        base: None,
+        blank_count: 0,
+        triples: Vec::new(),
+    };
+    parser.document()?;
+    Ok(parser.triples)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    prefixes: HashMap<String, String>,
+    base: Option<String>,
+    blank_count: usize,
+    triples: Vec<Triple>,
+}
+
+impl Parser {
+    fn error<T>(&self, msg: &str) -> Result<T, ParseError> {
+        Err(ParseError { line: self.line, msg: msg.to_string() })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).cloned()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        self.pos += 1;
+        c
+    }
+
+    fn skip_ws(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '#' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.bump();
+                }
+            }
+            else if c.is_whitespace() {
+                self.bump();
+            }
+            else {
+                break;
+            }
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseError> {
+        self.skip_ws();
+        if self.peek() == Some(c) {
+            self.bump();
+            Ok(())
+        }
+        else {
+            self.error(&format!("expected '{}'", c))
+        }
+    }
+
+    fn looking_at_keyword(&self, kw: &str) -> bool {
+        let n = kw.len();
+        let word: String = self.chars[self.pos .. (self.pos + n).min(self.chars.len())]
+                .iter().collect();
+        word.eq_ignore_ascii_case(kw)
+            && self.peek_at(n).map_or(true, |c| c.is_whitespace() || c == '<')
+    }
+
+    fn new_blank(&mut self) -> Term {
+        self.blank_count += 1;
+        Term::Blank(format!("genid{}", self.blank_count))
+    }
+
+    fn emit(&mut self, subject: &Term, predicate: &str, object: Term) {
+        self.triples.push(Triple {
+            subject: subject.clone(),
+            predicate: predicate.to_string(),
+            object: object,
+        });
+    }
+
+    fn document(&mut self) -> Result<(), ParseError> {
+        loop {
+            self.skip_ws();
+            match self.peek() {
+                None => return Ok(()),
+                Some('@') => {
+                    self.bump();
+                    if self.looking_at_keyword("prefix") {
+                        self.pos += 6;
+                        self.prefix_decl()?;
+                    }
+                    else if self.looking_at_keyword("base") {
+                        self.pos += 4;
+                        self.base_decl()?;
+                    }
+                    else {
+                        return self.error("unknown directive");
+                    }
+                    self.expect('.')?;
+                },
+                Some(_) if self.looking_at_keyword("prefix") => {
+                    self.pos += 6;
+                    self.prefix_decl()?;
+                },
+                Some(_) if self.looking_at_keyword("base") => {
+                    self.pos += 4;
+                    self.base_decl()?;
+                },
+                Some(_) => {
+                    self.triples_statement()?;
+                    self.expect('.')?;
+                },
+            }
+        }
+    }
+
+    fn prefix_decl(&mut self) -> Result<(), ParseError> {
+        self.skip_ws();
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c == ':' {
+                break;
+            }
+            if !(c.is_alphanumeric() || c == '_' || c == '-' || c == '.') {
+                return self.error("invalid prefix name");
+            }
+            name.push(c);
+            self.bump();
+        }
+        self.expect(':')?;
+        self.skip_ws();
+        let iri = self.iri_ref()?;
+        self.prefixes.insert(name, iri);
+        Ok(())
+    }
+
+    fn base_decl(&mut self) -> Result<(), ParseError> {
+        self.skip_ws();
+        let iri = self.iri_ref()?;
+        self.base = Some(iri);
+        Ok(())
+    }
+
+    fn triples_statement(&mut self) -> Result<(), ParseError> {
+        self.skip_ws();
+        if self.peek() == Some('[') {
+            let subject = self.blank_node_property_list()?;
+            self.skip_ws();
+            if self.peek() != Some('.') {
+                self.predicate_object_list(&subject)?;
+            }
+            return Ok(());
+        }
+        let subject = match self.peek() {
+            Some('(') => self.collection()?,
+            _ => self.resource()?,
+        };
+        if let Term::Literal { .. } = subject {
+            return self.error("literal as subject");
+        }
+        self.predicate_object_list(&subject)
+    }
+
+    fn predicate_object_list(&mut self, subject: &Term) -> Result<(), ParseError> {
+        loop {
+            self.skip_ws();
+            let predicate = if self.peek() == Some('a')
+                    && self.peek_at(1).is_some_and(|c| c.is_whitespace() || c == '<' || c == '[') {
+                self.bump();
+                rdf::TYPE.to_string()
+            }
+            else {
+                match self.resource()? {
+                    Term::Iri(iri) => iri,
+                    _ => return self.error("expected a predicate IRI"),
+                }
+            };
+            loop {
+                let object = self.object()?;
+                self.emit(subject, &predicate, object);
+                self.skip_ws();
+                if self.peek() == Some(',') {
+                    self.bump();
+                }
+                else {
+                    break;
+                }
+            }
+            self.skip_ws();
+            if self.peek() != Some(';') {
+                return Ok(());
+            }
+            while self.peek() == Some(';') {
+                self.bump();
+                self.skip_ws();
+            }
+            match self.peek() {
+                Some('.') | Some(']') | None => return Ok(()),
+                _ => {},
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Term, ParseError> {
+        self.skip_ws();
+        match self.peek() {
+            Some('[') => self.blank_node_property_list(),
+            Some('(') => self.collection(),
+            _ => self.resource(),
+        }
+    }
+
+    fn blank_node_property_list(&mut self) -> Result<Term, ParseError> {
+        self.expect('[')?;
+        let node = self.new_blank();
+        self.skip_ws();
+        if self.peek() != Some(']') {
+            self.predicate_object_list(&node)?;
+        }
+        self.expect(']')?;
+        Ok(node)
+    }
+
+    fn collection(&mut self) -> Result<Term, ParseError> {
+        self.expect('(')?;
+        let mut items = Vec::new();
+        loop {
+            self.skip_ws();
+            if self.peek() == Some(')') {
+                self.bump();
+                break;
+            }
+            if self.peek().is_none() {
+                return self.error("unterminated collection");
+            }
+            items.push(self.object()?);
+        }
+        let mut list = Term::iri(rdf::NIL);
+        for item in items.into_iter().rev() {
+            let cell = self.new_blank();
+            self.emit(&cell, rdf::FIRST, item);
+            self.emit(&cell, rdf::REST, list);
+            list = cell;
+        }
+        Ok(list)
+    }
+
+    /// An IRI, a blank node label or a literal.
+    fn resource(&mut self) -> Result<Term, ParseError> {
+        self.skip_ws();
+        match self.peek() {
+            Some('<') => Ok(Term::Iri(self.iri_ref()?)),
+            Some('"') | Some('\'') => self.string_literal(),
+            Some('_') if self.peek_at(1) == Some(':') => {
+                self.pos += 2;
+                let label = self.name_chars();
+                if label.is_empty() {
+                    return self.error("empty blank node label");
+                }
+                Ok(Term::Blank(label))
+            },
+            Some(c) if c.is_ascii_digit() || c == '+' || c == '-' => self.numeric(),
+            Some('.') if self.peek_at(1).is_some_and(|c| c.is_ascii_digit()) => self.numeric(),
+            Some('.') | Some(',') | Some(';') | Some(']') | Some(')') => self.error("expected a term"),
+            Some(_) if self.looking_at_bool("true") => {
+                self.pos += 4;
+                Ok(Term::boolean(true))
+            },
+            Some(_) if self.looking_at_bool("false") => {
+                self.pos += 5;
+                Ok(Term::boolean(false))
+            },
+            Some(_) => Ok(Term::Iri(self.prefixed_name()?)),
+            None => self.error("unexpected end of document"),
+        }
+    }
+
+    fn looking_at_bool(&self, kw: &str) -> bool {
+        let n = kw.len();
+        let word: String = self.chars[self.pos .. (self.pos + n).min(self.chars.len())]
+                .iter().collect();
+        word == kw && self.peek_at(n).map_or(true, |c| !(c.is_alphanumeric() || c == ':' || c == '_'))
+    }
+
+    fn name_chars(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || c == '_' || c == '-' || c == ':' || c == '%' {
+                name.push(c);
+                self.bump();
+            }
+            else if c == '\\' && self.peek_at(1).is_some() {
+                self.bump();
+                name.push(self.bump().unwrap());
+            }
+            else if c == '.' && self.peek_at(1).is_some_and(|n| n.is_alphanumeric() || n == '_') {
+                name.push(c);
+                self.bump();
+            }
+            else {
+                break;
+            }
+        }
+        name
+    }
+
+    fn prefixed_name(&mut self) -> Result<String, ParseError> {
+        let mut prefix = String::new();
+        while let Some(c) = self.peek() {
+            if c == ':' {
+                break;
+            }
+            if !(c.is_alphanumeric() || c == '_' || c == '-' || c == '.') {
+                return self.error(&format!("unexpected character '{}'", c));
+            }
+            prefix.push(c);
+            self.bump();
+        }
+        if self.bump() != Some(':') {
+            return self.error("expected a prefixed name");
+        }
+        let local = self.name_chars();
+        match self.prefixes.get(&prefix) {
+            Some(ns) => Ok(format!("{}{}", ns, local)),
+            None => self.error(&format!("undeclared prefix \"{}\"", prefix)),
+        }
+    }
+
+    fn iri_ref(&mut self) -> Result<String, ParseError> {
+        if self.bump() != Some('<') {
+            return self.error("expected '<'");
+        }
+        let mut iri = String::new();
+        loop {
+            match self.bump() {
+                Some('>') => break,
+                Some('\\') => iri.push(self.unicode_escape()?),
+                Some(c) if c.is_whitespace() => return self.error("whitespace in IRI"),
+                Some(c) => iri.push(c),
+                None => return self.error("unterminated IRI"),
+            }
+        }
+        Ok(self.resolve(iri))
+    }
+
+    fn resolve(&self, iri: String) -> String {
+        match self.base {
+            Some(ref base) if !iri.contains(':') => {
+                if iri.starts_with('#') || iri.is_empty() {
+                    let base = base.split('#').next().unwrap();
+                    format!("{}{}", base, iri)
+                }
+                else {
+                    let dir = &base[.. base.rfind('/').map_or(0, |i| i + 1)];
+                    format!("{}{}", dir, iri)
+                }
+            },
+            _ => iri,
+        }
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, ParseError> {
+        let len = match self.bump() {
+            Some('u') => 4,
+            Some('U') => 8,
+            _ => return self.error("invalid escape sequence"),
+        };
+        let mut code = String::new();
+        for _ in 0 .. len {
+            match self.bump() {
+                Some(c) => code.push(c),
+                None => return self.error("invalid escape sequence"),
+            }
+        }
+        match u32::from_str_radix(&code, 16).ok().and_then(::std::char::from_u32) {
+            Some(c) => Ok(c),
+            None => self.error("invalid escape sequence"),
+        }
+    }
+
+    fn string_literal(&mut self) -> Result<Term, ParseError> {
+        let quote = self.bump().unwrap();
+        let long = self.peek() == Some(quote) && self.peek_at(1) == Some(quote);
+        if long {
+            self.pos += 2;
+        }
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None => return self.error("unterminated string"),
+                Some(c) if c == quote => {
+                    if !long {
+                        self.bump();
+                        break;
+                    }
+                    if self.peek_at(1) == Some(quote) && self.peek_at(2) == Some(quote)
+                            && self.peek_at(3) != Some(quote) {
+                        self.pos += 3;
+                        break;
+                    }
+                    value.push(c);
+                    self.bump();
+                },
+                Some('\\') => {
+                    self.bump();
+                    let c = match self.peek() {
+                        Some('t') => '\t',
+                        Some('b') => '\u{8}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('f') => '\u{c}',
+                        Some('"') => '"',
+                        Some('\'') => '\'',
+                        Some('\\') => '\\',
+                        Some('u') | Some('U') => {
+                            value.push(self.unicode_escape()?);
+                            continue;
+                        },
+                        _ => return self.error("invalid escape sequence"),
+                    };
+                    self.bump();
+                    value.push(c);
+                },
+                Some('\n') if !long => return self.error("newline in string"),
+                Some(c) => {
+                    value.push(c);
+                    self.bump();
+                },
+            }
+        }
+        if self.peek() == Some('@') {
+            self.bump();
+            let mut lang = String::new();
+            while let Some(c) = self.peek() {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    lang.push(c);
+                    self.bump();
+                }
+                else {
+                    break;
+                }
+            }
+            if lang.is_empty() {
+                return self.error("empty language tag");
+            }
+            return Ok(Term::Literal { value: value, datatype: None, lang: Some(lang) });
+        }
+        if self.peek() == Some('^') && self.peek_at(1) == Some('^') {
+            self.pos += 2;
+            let datatype = match self.peek() {
+                Some('<') => self.iri_ref()?,
+                _ => self.prefixed_name()?,
+            };
+            return Ok(Term::Literal { value: value, datatype: Some(datatype), lang: None });
+        }
+        Ok(Term::string(value))
+    }
+
+    fn numeric(&mut self) -> Result<Term, ParseError> {
+        let mut repr = String::new();
+        if let Some(c) = self.peek() {
+            if c == '+' || c == '-' {
+                repr.push(c);
+                self.bump();
+            }
+        }
+        while let Some(c) = self.peek() {
+            let exp_sign = (c == '+' || c == '-')
+                    && repr.ends_with(&['e', 'E'][..]);
+            let dot = c == '.' && self.peek_at(1).is_some_and(|n| n.is_ascii_digit());
+            if c.is_ascii_digit() || c == 'e' || c == 'E' || exp_sign || dot {
+                repr.push(c);
+                self.bump();
+            }
+            else {
+                break;
+            }
+        }
+        if is_integer(&repr) {
+            Ok(Term::typed(repr, xsd::INTEGER))
+        }
+        else if is_decimal(&repr) {
+            Ok(Term::typed(repr, xsd::DECIMAL))
+        }
+        else if is_double(&repr) {
+            Ok(Term::typed(repr, xsd::DOUBLE))
+        }
+        else {
+            self.error(&format!("invalid number \"{}\"", repr))
+        }
+    }
+}
+
+
+/// A set of triples with simple queries.
+#[derive(Clone, Debug, Default)]
+pub struct Graph {
+    pub triples: Vec<Triple>,
+}
+
+impl Graph {
+    pub fn parse(text: &str) -> Result<Graph, ParseError> {
+        Ok(Graph { triples: parse(text)? })
+    }
+
+    pub fn objects<'a>(&'a self, subject: &'a Term, predicate: &'a str)
+            -> impl Iterator<Item = &'a Term> + 'a {
+        self.triples.iter()
+            .filter(move |t| t.subject == *subject && t.predicate == predicate)
+            .map(|t| &t.object)
+    }
+
+    /// The first object of `predicate` for `subject`.
+    pub fn object(&self, subject: &Term, predicate: &str) -> Option<&Term> {
+        self.triples.iter()
+            .find(|t| t.subject == *subject && t.predicate == predicate)
+            .map(|t| &t.object)
+    }
+
+    pub fn subjects<'a>(&'a self, predicate: &'a str, object: &'a Term)
+            -> impl Iterator<Item = &'a Term> + 'a {
+        self.triples.iter()
+            .filter(move |t| t.object == *object && t.predicate == predicate)
+            .map(|t| &t.subject)
+    }
+
+    /// The items of the RDF collection starting at `head`,
+    /// or `None` if it is not a well formed list.
+    pub fn list<'a>(&'a self, head: &'a Term) -> Option<Vec<&'a Term>> {
+        let mut items = Vec::new();
+        let mut cell = head;
+        while cell.as_iri() != Some(rdf::NIL) {
+            items.push(self.object(cell, rdf::FIRST)?);
+            cell = self.object(cell, rdf::REST)?;
+        }
+        Some(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EX: &'static str = "http://example.org/ns#";
+
+    fn ex(name: &str) -> String {
+        format!("{}{}", EX, name)
+    }
+
+    fn round_trip(doc: &Document) -> Graph {
+        let text = doc.to_string();
+        match Graph::parse(&text) {
+            Ok(graph) => graph,
+            Err(err) => panic!("{} in\n{}", err, text),
+        }
+    }
+
+    fn parse_error(text: &str) -> ParseError {
+        match parse(text) {
+            Ok(triples) => panic!("parsed {:?}", triples),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn written_text() {
+        let mut doc = Document::new();
+        doc.prefix("ex", EX).prefix("unused", "http://example.org/unused#");
+        doc.push(Node::new(Term::iri(ex("a")))
+            .with(rdf::TYPE, Term::iri(ex("Thing")))
+            .with(&ex("size"), Term::integer(2))
+            .with(&ex("size"), Term::integer(3))
+            .with(&ex("name"), Term::string("a")));
+        assert_eq!(doc.to_string(), "@prefix ex: <http://example.org/ns#> .\n\
+                                     \n\
+                                     ex:a\n\
+                                     \ta ex:Thing ;\n\
+                                     \tex:size 2 , 3 ;\n\
+                                     \tex:name \"a\" .\n");
+    }
+
+    #[test]
+    fn escapes() {
+        let value = "quote \" backslash \\ newline \n return \r tab \t bell \u{7} é";
+        let mut doc = Document::new();
+        doc.push(Node::new(Term::iri(ex("a"))).with(&ex("p"), Term::string(value)));
+        let text = doc.to_string();
+        assert!(text.contains(r#""quote \" backslash \\ newline \n return \r tab \t bell \u0007 é""#));
+        let graph = round_trip(&doc);
+        assert_eq!(graph.object(&Term::iri(ex("a")), &ex("p")), Some(&Term::string(value)));
+    }
+
+    #[test]
+    fn prefixed_names() {
+        let mut doc = Document::new();
+        doc.prefix("ex", EX);
+        // not a valid local name, written in full
+        doc.push(Node::new(Term::iri(ex("a/b"))).with(&ex("p"), Term::iri(ex("c-d_1"))));
+        let text = doc.to_string();
+        assert!(text.contains("<http://example.org/ns#a/b>"));
+        assert!(text.contains("ex:p ex:c-d_1"));
+        let graph = round_trip(&doc);
+        assert_eq!(graph.triples, vec![Triple {
+            subject: Term::iri(ex("a/b")),
+            predicate: ex("p"),
+            object: Term::iri(ex("c-d_1")),
+        }]);
+    }
+
+    #[test]
+    fn literals() {
+        let values = vec![
+            Term::integer(-3),
+            Term::number(0.5),
+            Term::number(1e20),
+            Term::number(f32::NEG_INFINITY),
+            Term::number(f32::NAN),
+            Term::boolean(false),
+            Term::typed("1.", xsd::DECIMAL),
+            Term::typed("7", xsd::INT),
+            Term::Literal { value: "chat".to_string(), datatype: None, lang: Some("fr".to_string()) },
+        ];
+        let mut node = Node::new(Term::iri(ex("a")));
+        for v in &values {
+            node.add(&ex("p"), v.clone());
+        }
+        let mut doc = Document::new();
+        doc.prefix("xsd", "http://www.w3.org/2001/XMLSchema#");
+        doc.push(node);
+        let text = doc.to_string();
+        assert!(text.contains("ns#p> -3 , 0.5 , 1e20 , \"-INF\"^^xsd:float , \"NaN\"^^xsd:float , false , \"1.\"^^xsd:decimal , \"7\"^^xsd:int , \"chat\"@fr ."),
+                "{}", text);
+        let graph = round_trip(&doc);
+        let read: Vec<Term> = graph.objects(&Term::iri(ex("a")), &ex("p")).cloned().collect();
+        assert_eq!(read, values);
+        assert_eq!(Term::number(f32::NEG_INFINITY).as_f64(), Some(f64::NEG_INFINITY));
+        assert!(Term::number(f32::NAN).as_f64().unwrap().is_nan());
+        assert_eq!(read[2].as_f64(), Some(1e20));
+        assert_eq!(read[0].as_i64(), Some(-3));
+    }
+
+    #[test]
+    fn blank_nodes() {
+        let mut doc = Document::new();
+        doc.push(Node::new(Term::iri(ex("a")))
+            .with(&ex("port"), Node::blank().with(&ex("index"), Term::integer(0)))
+            .with(&ex("empty"), Node::blank())
+            .with(&ex("label"), Term::Blank("b1".to_string()))
+            .with(&ex("list"), Object::List(vec![Term::integer(1).into(), Term::integer(2).into()])));
+        let graph = round_trip(&doc);
+        let a = Term::iri(ex("a"));
+
+        let port = graph.object(&a, &ex("port")).unwrap();
+        match *port {
+            Term::Blank(_) => {},
+            ref t => panic!("not a blank node: {:?}", t),
+        }
+        assert_eq!(graph.object(port, &ex("index")), Some(&Term::integer(0)));
+        let empty = graph.object(&a, &ex("empty")).unwrap();
+        assert!(empty != port && graph.objects(empty, &ex("index")).next().is_none());
+        assert_eq!(graph.object(&a, &ex("label")), Some(&Term::Blank("b1".to_string())));
+
+        let list = graph.object(&a, &ex("list")).unwrap();
+        assert_eq!(graph.list(list), Some(vec![&Term::integer(1), &Term::integer(2)]));
+    }
+
+    #[test]
+    fn named_nested_nodes() {
+        let mut doc = Document::new();
+        doc.push(Node::new(Term::iri(ex("a")))
+            .with(&ex("p"), Node::new(Term::iri(ex("b"))).with(&ex("q"), Term::integer(1))));
+        let graph = round_trip(&doc);
+        assert_eq!(graph.object(&Term::iri(ex("a")), &ex("p")), Some(&Term::iri(ex("b"))));
+        assert_eq!(graph.object(&Term::iri(ex("b")), &ex("q")), Some(&Term::integer(1)));
+    }
+
+    #[test]
+    fn parse_syntax() {
+        let text = "# comment\n\
+                    @base <http://example.org/dir/file> .\n\
+                    PREFIX ex: <http://example.org/ns#>\n\
+                    <#a> a ex:T , <other> ; ex:s '''long \"\" string''' ; ; ex:n .5 , +1 , 2E-1 ; .\n\
+                    [ ex:p ex:q ] .\n";
+        let graph = Graph::parse(text).unwrap();
+        let a = Term::iri("http://example.org/dir/file#a");
+        let types: Vec<&Term> = graph.objects(&a, rdf::TYPE).collect();
+        assert_eq!(types, vec![&Term::iri(ex("T")), &Term::iri("http://example.org/dir/other")]);
+        assert_eq!(graph.object(&a, &ex("s")), Some(&Term::string("long \"\" string")));
+        let n = ex("n");
+        let numbers: Vec<&Term> = graph.objects(&a, &n).collect();
+        assert_eq!(numbers, vec![&Term::typed(".5", xsd::DECIMAL), &Term::typed("+1", xsd::INTEGER),
+                                 &Term::typed("2E-1", xsd::DOUBLE)]);
+        assert_eq!(graph.subjects(&ex("p"), &Term::iri(ex("q"))).count(), 1);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let cases = [
+            ("<a> <b> \"open .\n", "newline in string"),
+            ("<a> <b> \"open", "unterminated string"),
+            ("<a> <b> \"\\q\" .", "invalid escape sequence"),
+            ("<a> <b> \"x\"@ .", "empty language tag"),
+            ("<a> <b> <c d> .", "whitespace in IRI"),
+            ("<a> <b> <c", "unterminated IRI"),
+            ("<a> <b> ex:c .", "undeclared prefix \"ex\""),
+            ("<a> <b> <c>", "expected '.'"),
+            ("<a> <b> ( <c>", "unterminated collection"),
+            ("\"a\" <b> <c> .", "literal as subject"),
+            ("<a> \"b\" <c> .", "expected a predicate IRI"),
+            ("<a> <b> 1e .", "invalid number \"1e\""),
+            ("<a> <b> _: .", "empty blank node label"),
+            ("<a> <b> , .", "expected a term"),
+            ("@version 1 .", "unknown directive"),
+            ("<a> <b>", "unexpected end of document"),
+        ];
+        for &(text, msg) in &cases {
+            assert_eq!(parse_error(text).msg, msg, "parsing {:?}", text);
+        }
+    }
+
+    #[test]
+    fn parse_error_line() {
+        let err = parse_error("<a> <b> <c> .\n\n<a> <b> \"x\n");
+        assert_eq!(err.line, 3);
+        assert_eq!(err.to_string(), "line 3: newline in string");
+    }
+}
@@ -0,0 +1,44 @@
+
+pub const URI: &'static str = "http://lv2plug.in/ns/extensions/units";
+
+pub mod class {
+    pub const CONVERSION:       &'static str = "http://lv2plug.in/ns/extensions/units#Conversion";
+    pub const UNIT:             &'static str = "http://lv2plug.in/ns/extensions/units#Unit";
+}
+
+pub mod prop {
+    pub const CONVERSION:       &'static str = "http://lv2plug.in/ns/extensions/units#conversion";
+    pub const FACTOR:           &'static str = "http://lv2plug.in/ns/extensions/units#factor";
+    pub const PREFIXCONVERSION: &'static str = "http://lv2plug.in/ns/extensions/units#prefixConversion";
+    pub const RENDER:           &'static str = "http://lv2plug.in/ns/extensions/units#render";
+    pub const SYMBOL:           &'static str = "http://lv2plug.in/ns/extensions/units#symbol";
+    pub const TO:               &'static str = "http://lv2plug.in/ns/extensions/units#to";
+    pub const UNIT:             &'static str = "http://lv2plug.in/ns/extensions/units#unit";
+}
+
+pub mod inst {
+    pub const BAR:              &'static str = "http://lv2plug.in/ns/extensions/units#bar";
+    pub const BEAT:             &'static str = "http://lv2plug.in/ns/extensions/units#beat";
+    pub const BPM:              &'static str = "http://lv2plug.in/ns/extensions/units#bpm";
+    pub const CENT:             &'static str = "http://lv2plug.in/ns/extensions/units#cent";
+    pub const CM:               &'static str = "http://lv2plug.in/ns/extensions/units#cm";
+    pub const COEF:             &'static str = "http://lv2plug.in/ns/extensions/units#coef";
+    pub const DB:               &'static str = "http://lv2plug.in/ns/extensions/units#db";
+    pub const DEGREE:           &'static str = "http://lv2plug.in/ns/extensions/units#degree";
+    pub const FRAME:            &'static str = "http://lv2plug.in/ns/extensions/units#frame";
+    pub const HZ:               &'static str = "http://lv2plug.in/ns/extensions/units#hz";
+    pub const INCH:             &'static str = "http://lv2plug.in/ns/extensions/units#inch";
+    pub const KHZ:              &'static str = "http://lv2plug.in/ns/extensions/units#khz";
+    pub const KM:               &'static str = "http://lv2plug.in/ns/extensions/units#km";
+    pub const M:                &'static str = "http://lv2plug.in/ns/extensions/units#m";
+    pub const MHZ:              &'static str = "http://lv2plug.in/ns/extensions/units#mhz";
+    pub const MIDINOTE:         &'static str = "http://lv2plug.in/ns/extensions/units#midiNote";
+    pub const MILE:             &'static str = "http://lv2plug.in/ns/extensions/units#mile";
+    pub const MIN:              &'static str = "http://lv2plug.in/ns/extensions/units#min";
+    pub const MM:               &'static str = "http://lv2plug.in/ns/extensions/units#mm";
+    pub const MS:               &'static str = "http://lv2plug.in/ns/extensions/units#ms";
+    pub const OCT:              &'static str = "http://lv2plug.in/ns/extensions/units#oct";
+    pub const PC:               &'static str = "http://lv2plug.in/ns/extensions/units#pc";
+    pub const S:                &'static str = "http://lv2plug.in/ns/extensions/units#s";
+    pub const SEMITONE12TET:    &'static str = "http://lv2plug.in/ns/extensions/units#semitone12TET";
+}
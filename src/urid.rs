@@ -60,7 +60,7 @@ impl<'h> Feature<'h> for Unmap<'h>
     unsafe fn from_raw(raw: &RawFeature<'h>) -> Self {
         debug_assert!(raw.uri() == Self::uri());
         Unmap {
-            raw: mem::transmute(raw.raw),
+            raw: mem::transmute((*raw.raw).data),
             marker: marker::PhantomData,
         }
     }
@@ -69,8 +69,12 @@ impl<'h> Feature<'h> for Unmap<'h>
 impl<'h> Unmap<'h> {
     pub fn unmap (&self, id: URID) -> Option<&str> {
         if let Some(func) = unsafe { (*self.raw).unmap } {
+            let ptr = func(unsafe { (*self.raw).handle }, id);
+            if ptr.is_null() {
+                return None;
+            }
             let cstr = unsafe {
-                CStr::from_ptr( func((*self.raw).handle, id) )
+                CStr::from_ptr(ptr)
             };
             cstr.to_str().ok()
         }
@@ -79,3 +83,79 @@ impl<'h> Unmap<'h> {
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use ::core::FeatureList;
+    use ::ffi::core::LV2_Feature;
+
+    use libc::{c_char, c_void};
+    use std::ptr;
+    use std::sync::Mutex;
+
+    // URIs mapped by the test host, URID n being at index n - 1
+    static URIS: Mutex<Vec<CString>> = Mutex::new(Vec::new());
+
+    extern "C" fn map_uri(_handle: LV2_URID_Map_Handle, uri: *const c_char) -> LV2_URID {
+        let uri = unsafe { CStr::from_ptr(uri) };
+        let mut uris = URIS.lock().unwrap();
+        match uris.iter().position(|u| u.as_c_str() == uri) {
+            Some(i) => i as LV2_URID + 1,
+            None => {
+                uris.push(uri.to_owned());
+                uris.len() as LV2_URID
+            },
+        }
+    }
+
+    extern "C" fn unmap_urid(_handle: LV2_URID_Unmap_Handle, urid: LV2_URID) -> *const c_char {
+        // the strings are never freed, so they outlive the lock
+        let uris = URIS.lock().unwrap();
+        match uris.get((urid as usize).wrapping_sub(1)) {
+            Some(uri) => uri.as_ptr(),
+            None => ptr::null(),
+        }
+    }
+
+    /// Calls `f` with the map and unmap features, read from a host feature list.
+    pub(crate) fn with_host<R, F>(f: F) -> R
+        where F: for<'h> FnOnce(&Map<'h>, &Unmap<'h>) -> R
+    {
+        let map = LV2_URID_Map { handle: ptr::null_mut(), map: Some(map_uri) };
+        let unmap = LV2_URID_Unmap { handle: ptr::null_mut(), unmap: Some(unmap_urid) };
+        let map_uri = CString::new(inst::MAP).unwrap();
+        let unmap_uri = CString::new(inst::UNMAP).unwrap();
+        let features = [
+            LV2_Feature { URI: map_uri.as_ptr(), data: &map as *const _ as *mut c_void },
+            LV2_Feature { URI: unmap_uri.as_ptr(), data: &unmap as *const _ as *mut c_void },
+        ];
+        let list = [&features[0] as *const LV2_Feature, &features[1], ptr::null()];
+        let map = unsafe { FeatureList::from_raw(list.as_ptr()) }.feature::<Map>().unwrap();
+        let unmap = unsafe { FeatureList::from_raw(list.as_ptr()) }.feature::<Unmap>().unwrap();
+        f(&map, &unmap)
+    }
+
+    #[test]
+    fn map_unmap() {
+        with_host(|map, unmap| {
+            let a = map.map("urn:test:map_unmap#a");
+            let b = map.map("urn:test:map_unmap#b");
+            assert!(a != 0 && b != 0 && a != b);
+            assert_eq!(map.map("urn:test:map_unmap#a"), a);
+            assert_eq!(unmap.unmap(a), Some("urn:test:map_unmap#a"));
+            assert_eq!(unmap.unmap(b), Some("urn:test:map_unmap#b"));
+        });
+    }
+
+    #[test]
+    fn unmap_unknown() {
+        with_host(|_, unmap| {
+            assert_eq!(unmap.unmap(0), None);
+            assert_eq!(unmap.unmap(!0), None);
+        });
+        let raw = LV2_URID_Unmap { handle: ptr::null_mut(), unmap: None };
+        let unmap = Unmap { raw: &raw, marker: marker::PhantomData };
+        assert_eq!(unmap.unmap(1), None);
+    }
+}
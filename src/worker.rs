@@ -0,0 +1,453 @@
+
+use ::core::{Extension, Feature, Plugin, PluginInstance, RawFeature};
+use ::ffi::core::LV2_Handle;
+use ::ffi::worker::*;
+
+use libc::c_void;
+use std::error;
+use std::fmt;
+use std::marker;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+pub const URI: &'static str = "http://lv2plug.in/ns/ext/worker";
+
+pub mod inst {
+    pub const INTERFACE : &'static str = "http://lv2plug.in/ns/ext/worker#interface";
+    pub const SCHEDULE  : &'static str = "http://lv2plug.in/ns/ext/worker#schedule";
+}
+
+
+/// Error of a worker operation, mapped to the LV2 status codes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    Unknown,
+    NoSpace,
+}
+
+impl Error {
+    fn status(res: Result<(), Error>) -> LV2_Worker_Status {
+        match res {
+            Ok(()) => LV2_WORKER_SUCCESS,
+            Err(Error::Unknown) => LV2_WORKER_ERR_UNKNOWN,
+            Err(Error::NoSpace) => LV2_WORKER_ERR_NO_SPACE,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Error::Unknown => "unknown worker error",
+            Error::NoSpace => "worker queue is full",
+        })
+    }
+}
+
+impl error::Error for Error {}
+
+
+/// Plugins running non real-time jobs in a host worker thread.
+/// Jobs are posted from `run` with the `Schedule` feature and answered with
+/// `Respond`. Messages are moved to the other thread as values of the
+/// associated types, so they can own memory allocated outside of `run`.
+/// Responses are dropped in the audio thread unless `work_response` passes
+/// them back with `Schedule::free`, which drops them in the worker thread.
+/// Messages discarded by the host, e.g. when the plugin is deactivated with
+/// pending jobs, are leaked.
+/// Register with `lv2_extensions!(lv2::worker::Interface)`, which also lists
+/// the extension in the plugin description, and require `inst::SCHEDULE`.
+pub trait Worker<'h>: Plugin<'h> {
+    type WorkData: Send + 'static;
+    type ResponseData: Send + 'static;
+
+    /// Runs a job in the worker thread. This may happen concurrently with `run`,
+    /// hence the plugin itself is not accessible.
+    fn work(respond: &Respond<Self>, data: Self::WorkData) -> Result<(), Error>;
+
+    /// Handles a response, in the audio thread right after `run`.
+    fn work_response(&mut self, data: Self::ResponseData) -> Result<(), Error>;
+
+    /// Called after `run` once all responses of the cycle are handled.
+    fn end_run(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+
+/// The `worker:schedule` feature, posting jobs from `run` to the worker of plugin `P`.
+pub struct Schedule<'h, P> {
+    raw: *const LV2_Worker_Schedule,
+    marker: marker::PhantomData<&'h ()>,
+    plugin: marker::PhantomData<fn (P)>,
+}
+
+impl<'h, P> Feature<'h> for Schedule<'h, P> {
+    fn uri() -> &'static str {
+        inst::SCHEDULE
+    }
+    unsafe fn from_raw(raw: &RawFeature<'h>) -> Self {
+        debug_assert!(raw.uri() == Self::uri());
+        Schedule {
+            raw: mem::transmute((*raw.raw).data),
+            marker: marker::PhantomData,
+            plugin: marker::PhantomData,
+        }
+    }
+}
+
+impl<'h, P: Worker<'h>> Schedule<'h, P> {
+    /// Posts `data` to `Worker::work`.
+    /// Gives `data` back if the host could not queue it.
+    pub fn schedule(&self, data: P::WorkData) -> Result<(), P::WorkData> {
+        let func = match unsafe { (*self.raw).schedule_work } {
+            Some(func) => func,
+            None => return Err(data),
+        };
+        let handle = unsafe { (*self.raw).handle };
+        match send(Job::Work::<_, P::ResponseData>(data), |size, ptr| func(handle, size, ptr)) {
+            Ok(()) => Ok(()),
+            Err(Job::Work(data)) => Err(data),
+            Err(Job::Free(_)) => unreachable!(),
+        }
+    }
+
+    /// Posts `data`, usually a response owning heap memory, to be dropped in
+    /// the worker thread instead of the audio thread.
+    /// Gives `data` back if the host could not queue it.
+    pub fn free(&self, data: P::ResponseData) -> Result<(), P::ResponseData> {
+        let func = match unsafe { (*self.raw).schedule_work } {
+            Some(func) => func,
+            None => return Err(data),
+        };
+        let handle = unsafe { (*self.raw).handle };
+        match send(Job::Free::<P::WorkData, _>(data), |size, ptr| func(handle, size, ptr)) {
+            Ok(()) => Ok(()),
+            Err(Job::Free(data)) => Err(data),
+            Err(Job::Work(_)) => unreachable!(),
+        }
+    }
+}
+
+/// A message posted to the worker thread.
+enum Job<W, R> {
+    Work(W),
+    Free(R),
+}
+
+
+/// Sends responses from `Worker::work` back to the plugin `P`.
+pub struct Respond<P: ?Sized> {
+    func: LV2_Worker_Respond_Function,
+    handle: LV2_Worker_Respond_Handle,
+    marker: marker::PhantomData<fn (P)>,
+}
+
+impl<'h, P: Worker<'h>> Respond<P> {
+    /// Posts `data` to `Worker::work_response`.
+    /// Gives `data` back if the host could not queue it.
+    pub fn respond(&self, data: P::ResponseData) -> Result<(), P::ResponseData> {
+        let func = match self.func {
+            Some(func) => func,
+            None => return Err(data),
+        };
+        let handle = self.handle;
+        send(data, |size, ptr| func(handle, size, ptr))
+    }
+}
+
+/// Moves `data` into the host queue with `post`.
+fn send<T, F>(data: T, post: F) -> Result<(), T>
+    where F: FnOnce(u32, *const c_void) -> LV2_Worker_Status
+{
+    let data = mem::ManuallyDrop::new(data);
+    let ptr = &*data as *const T as *const c_void;
+    match post(mem::size_of::<T>() as u32, ptr) {
+        LV2_WORKER_SUCCESS => Ok(()),
+        _ => Err(mem::ManuallyDrop::into_inner(data)),
+    }
+}
+
+/// Takes back a value moved into the host queue by `send`.
+unsafe fn receive<T>(size: u32, data: *const c_void) -> Option<T> {
+    if size as usize != mem::size_of::<T>() {
+        None
+    }
+    else if mem::size_of::<T>() == 0 {
+        Some(ptr::read(ptr::NonNull::dangling().as_ptr()))
+    }
+    else if data.is_null() {
+        None
+    }
+    else {
+        Some(ptr::read_unaligned(data as *const T))
+    }
+}
+
+
+/// The `worker:interface` extension for plugins implementing `Worker`.
+pub enum Interface {}
+
+unsafe impl<'h, T: Worker<'h>> Extension<'h, T> for Interface {
+    fn uri() -> &'static str {
+        inst::INTERFACE
+    }
+    fn interface() -> *const c_void {
+        let iface: &'static LV2_Worker_Interface = &LV2_Worker_Interface {
+            work: Some(work::<T>),
+            work_response: Some(work_response::<T>),
+            end_run: Some(end_run::<T>),
+        };
+        iface as *const LV2_Worker_Interface as *const c_void
+    }
+}
+
+extern "C" fn work<'h, T: Worker<'h>>(_instance: LV2_Handle,
+                                      respond: LV2_Worker_Respond_Function,
+                                      handle: LV2_Worker_Respond_Handle,
+                                      size: u32,
+                                      data: *const c_void) -> LV2_Worker_Status
+{
+    let data = match unsafe { receive::<Job<T::WorkData, T::ResponseData>>(size, data) } {
+        Some(Job::Work(data)) => data,
+        Some(Job::Free(data)) => {
+            let res = panic::catch_unwind(AssertUnwindSafe(move || drop(data)));
+            return Error::status(res.map_err(|_| Error::Unknown));
+        },
+        None => return LV2_WORKER_ERR_UNKNOWN,
+    };
+    let respond = Respond {
+        func: respond,
+        handle: handle,
+        marker: marker::PhantomData,
+    };
+    // the instance may be running concurrently, so it is left alone
+    let res = panic::catch_unwind(AssertUnwindSafe(|| T::work(&respond, data)));
+    Error::status(res.unwrap_or(Err(Error::Unknown)))
+}
+
+extern "C" fn work_response<'h, T: Worker<'h>>(instance: LV2_Handle,
+                                               size: u32,
+                                               body: *const c_void) -> LV2_Worker_Status
+{
+    let instance = unsafe { PluginInstance::<T>::from_handle(instance) };
+    let data = match unsafe { receive::<T::ResponseData>(size, body) } {
+        Some(data) => data,
+        None => return LV2_WORKER_ERR_UNKNOWN,
+    };
+    let res = unsafe { instance.call("work_response", |plugin| plugin.work_response(data)) };
+    Error::status(res.unwrap_or(Err(Error::Unknown)))
+}
+
+extern "C" fn end_run<'h, T: Worker<'h>>(instance: LV2_Handle) -> LV2_Worker_Status {
+    let instance = unsafe { PluginInstance::<T>::from_handle(instance) };
+    let res = unsafe { instance.call("end_run", |plugin| plugin.end_run()) };
+    Error::status(res.unwrap_or(Err(Error::Unknown)))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::core::{self, Extensions, PortInfo, Ported};
+    use ::ffi::core::LV2_Descriptor;
+
+    use std::ffi::CString;
+    use std::slice;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A message counting its drops.
+    struct Counted {
+        value: u32,
+        drops: Arc<AtomicUsize>,
+    }
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.drops.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// A host queue copying the posted messages.
+    struct Queue {
+        full: bool,
+        messages: Vec<Vec<u8>>,
+    }
+
+    extern "C" fn post(handle: *mut c_void, size: u32, data: *const c_void) -> LV2_Worker_Status {
+        let queue = unsafe { &mut *(handle as *mut Queue) };
+        if queue.full {
+            return LV2_WORKER_ERR_NO_SPACE;
+        }
+        let body = unsafe { slice::from_raw_parts(data as *const u8, size as usize) };
+        queue.messages.push(body.to_vec());
+        LV2_WORKER_SUCCESS
+    }
+
+    struct Loader {
+        loaded: Vec<u32>,
+        ends: usize,
+    }
+
+    unsafe impl<'h> Ported<'h> for Loader {
+        type Ports = ();
+        type PortsRaw = ();
+        fn new_ports_raw() {}
+        fn connect_port(_port: usize, _data: *mut (), _ports_raw: &mut ()) {}
+        fn convert_ports(_ports_raw: (), _sample_count: usize) {}
+        fn silence_outputs(_ports_raw: (), _sample_count: usize) {}
+        fn port_infos() -> Vec<PortInfo> {
+            Vec::new()
+        }
+    }
+
+    impl<'h> Plugin<'h> for Loader {
+        type Features = ();
+        fn new(_sample_rate: f64, _bundle_path: &str, _features: ()) -> Option<Loader> {
+            Some(Loader { loaded: Vec::new(), ends: 0 })
+        }
+        fn run(&mut self, _ports: &mut (), _sample_count: usize) {}
+        fn extension_data(uri: &str) -> Option<*const c_void> {
+            <(Interface,) as Extensions<Self>>::interface(uri)
+        }
+    }
+
+    impl<'h> Worker<'h> for Loader {
+        type WorkData = Counted;
+        type ResponseData = Counted;
+        fn work(respond: &Respond<Self>, data: Counted) -> Result<(), Error> {
+            respond.respond(data).map_err(|_| Error::NoSpace)
+        }
+        fn work_response(&mut self, data: Counted) -> Result<(), Error> {
+            self.loaded.push(data.value);
+            Ok(())
+        }
+        fn end_run(&mut self) -> Result<(), Error> {
+            self.ends += 1;
+            Ok(())
+        }
+    }
+
+    fn with_interface<F: FnOnce(LV2_Handle, &LV2_Worker_Interface)>(f: F) {
+        let bundle = CString::new("/").unwrap();
+        let handle = core::instantiate::<Loader>(ptr::null::<LV2_Descriptor>(), 48000.0,
+                                                 bundle.as_ptr(), ptr::null());
+        assert!(!handle.is_null());
+        let uri = CString::new(inst::INTERFACE).unwrap();
+        let iface = core::extension_data::<Loader>(uri.as_ptr()) as *const LV2_Worker_Interface;
+        assert!(!iface.is_null());
+        f(handle, unsafe { &*iface });
+        core::cleanup::<Loader>(handle);
+    }
+
+    fn schedule_raw(queue: &mut Queue) -> LV2_Worker_Schedule {
+        LV2_Worker_Schedule {
+            handle: queue as *mut Queue as *mut c_void,
+            schedule_work: Some(post),
+        }
+    }
+
+    fn schedule(raw: &LV2_Worker_Schedule) -> Schedule<'_, Loader> {
+        Schedule {
+            raw: raw,
+            marker: marker::PhantomData,
+            plugin: marker::PhantomData,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        with_interface(|handle, iface| {
+            let drops = Arc::new(AtomicUsize::new(0));
+            let mut jobs = Queue { full: false, messages: Vec::new() };
+            let mut responses = Queue { full: false, messages: Vec::new() };
+            let raw = schedule_raw(&mut jobs);
+            assert!(schedule(&raw).schedule(Counted { value: 7, drops: drops.clone() }).is_ok());
+            assert_eq!(drops.load(Ordering::SeqCst), 0);
+
+            let job = jobs.messages.pop().unwrap();
+            let status = (iface.work.unwrap())(handle, Some(post),
+                                               &mut responses as *mut Queue as *mut c_void,
+                                               job.len() as u32, job.as_ptr() as *const c_void);
+            assert_eq!(status, LV2_WORKER_SUCCESS);
+            assert_eq!(drops.load(Ordering::SeqCst), 0);
+
+            let response = responses.messages.pop().unwrap();
+            let status = (iface.work_response.unwrap())(handle, response.len() as u32,
+                                                        response.as_ptr() as *const c_void);
+            assert_eq!(status, LV2_WORKER_SUCCESS);
+            assert_eq!((iface.end_run.unwrap())(handle), LV2_WORKER_SUCCESS);
+            assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+            let instance = unsafe { PluginInstance::<Loader>::from_handle(handle) };
+            let state = unsafe { instance.call("test", |plugin| (plugin.loaded.clone(), plugin.ends)) };
+            assert_eq!(state, Some((vec![7], 1)));
+        });
+    }
+
+    #[test]
+    fn free() {
+        with_interface(|handle, iface| {
+            let drops = Arc::new(AtomicUsize::new(0));
+            let mut jobs = Queue { full: false, messages: Vec::new() };
+            let mut responses = Queue { full: false, messages: Vec::new() };
+            let raw = schedule_raw(&mut jobs);
+            assert!(schedule(&raw).free(Counted { value: 7, drops: drops.clone() }).is_ok());
+            assert_eq!(drops.load(Ordering::SeqCst), 0);
+
+            let job = jobs.messages.pop().unwrap();
+            let status = (iface.work.unwrap())(handle, Some(post),
+                                               &mut responses as *mut Queue as *mut c_void,
+                                               job.len() as u32, job.as_ptr() as *const c_void);
+            assert_eq!(status, LV2_WORKER_SUCCESS);
+            assert_eq!(drops.load(Ordering::SeqCst), 1);
+            assert!(responses.messages.is_empty());
+        });
+    }
+
+    #[test]
+    fn no_space() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let mut jobs = Queue { full: true, messages: Vec::new() };
+        let raw = schedule_raw(&mut jobs);
+        let data = schedule(&raw).schedule(Counted { value: 7, drops: drops.clone() }).err().unwrap();
+        assert_eq!(data.value, 7);
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        drop(data);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+        let respond = Respond::<Loader> {
+            func: Some(post),
+            handle: &mut jobs as *mut Queue as *mut c_void,
+            marker: marker::PhantomData,
+        };
+        let data = respond.respond(Counted { value: 8, drops: drops.clone() }).err().unwrap();
+        assert_eq!(data.value, 8);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn size_mismatch() {
+        let value = 7u32;
+        let data = &value as *const u32 as *const c_void;
+        assert_eq!(unsafe { receive::<u32>(4, data) }, Some(7));
+        assert_eq!(unsafe { receive::<u64>(4, data) }, None);
+        assert_eq!(unsafe { receive::<u32>(4, ptr::null()) }, None);
+        with_interface(|handle, iface| {
+            let status = (iface.work_response.unwrap())(handle, 4, data);
+            assert_eq!(status, LV2_WORKER_ERR_UNKNOWN);
+        });
+    }
+
+    #[test]
+    fn zero_sized() {
+        let mut queue = Queue { full: false, messages: Vec::new() };
+        let handle = &mut queue as *mut Queue as *mut c_void;
+        assert_eq!(send((), |size, ptr| post(handle, size, ptr)), Ok(()));
+        assert_eq!(queue.messages, vec![Vec::<u8>::new()]);
+        assert_eq!(unsafe { receive::<()>(0, ptr::null()) }, Some(()));
+        assert_eq!(unsafe { receive::<()>(1, ptr::null()) }, None);
+    }
+}